@@ -0,0 +1,116 @@
+use crate::model::{extra_field_names, UnknownFields};
+use crate::{AnnoRepoClient, Error};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Server information as returned by `/about`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AboutInfo {
+    pub app_name: String,
+    pub version: String,
+    pub started_at: String,
+    pub with_authentication: bool,
+    #[serde(rename = "baseURI", default, skip_serializing_if = "Option::is_none")]
+    pub base_uri: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_code: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mongo_version: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl UnknownFields for AboutInfo {
+    fn unknown_fields(&self) -> Vec<String> {
+        extra_field_names(&self.extra)
+    }
+}
+
+/// What the server supports, derived from `/about`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capabilities {
+    pub server_version: String,
+    /// Whether the server requires API keys.
+    pub authentication: bool,
+    /// Whether the server also offers its gRPC interface.
+    pub grpc: bool,
+}
+
+impl From<&AboutInfo> for Capabilities {
+    fn from(about: &AboutInfo) -> Self {
+        Self {
+            server_version: about.version.clone(),
+            authentication: about.with_authentication,
+            grpc: about.extra.contains_key("grpcPort"),
+        }
+    }
+}
+
+impl AnnoRepoClient {
+    /// The server's capabilities. Fetched once and then remembered for the
+    /// lifetime of the client.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn capabilities(&self) -> Result<Capabilities, Error> {
+        if let Some(capabilities) = self.capabilities.lock().unwrap().clone() {
+            return Ok(capabilities);
+        }
+        let capabilities = Capabilities::from(&self.get_about().await?);
+        *self.capabilities.lock().unwrap() = Some(capabilities.clone());
+
+        Ok(capabilities)
+    }
+
+    /// Fail early, before calling an endpoint that needs an authenticated
+    /// user, when the server or the client isn't set up for that.
+    pub(crate) async fn require_authentication(&self) -> Result<(), Error> {
+        let capabilities = self.capabilities().await?;
+        match (capabilities.authentication, self.has_api_key) {
+            (false, _) => Err(Error::AuthenticationDisabled),
+            (true, false) => Err(Error::MissingApiKey),
+            (true, true) => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn about_info_is_deserialized() {
+        let about: AboutInfo = serde_json::from_value(json!({
+            "appName": "AnnoRepo",
+            "version": "0.7.2",
+            "startedAt": "2024-03-01T09:00:00.000Z",
+            "baseURI": "https://annorepo.example.com",
+            "withAuthentication": true,
+            "sourceCode": "https://github.com/knaw-huc/annorepo",
+            "mongoVersion": "6.0.4"
+        }))
+        .unwrap();
+
+        assert_eq!(about.app_name, "AnnoRepo");
+        assert_eq!(about.version, "0.7.2");
+        assert!(about.with_authentication);
+        assert_eq!(
+            about.base_uri.as_deref(),
+            Some("https://annorepo.example.com")
+        );
+        assert_eq!(about.mongo_version.as_deref(), Some("6.0.4"));
+    }
+
+    #[test]
+    fn optional_fields_may_be_missing() {
+        let about: AboutInfo = serde_json::from_value(json!({
+            "appName": "AnnoRepo",
+            "version": "0.5.0",
+            "startedAt": "2023-01-01T00:00:00Z",
+            "withAuthentication": false
+        }))
+        .unwrap();
+
+        assert_eq!(about.mongo_version, None);
+    }
+}
@@ -0,0 +1,75 @@
+use crate::{AnnoRepoClient, Error};
+use reqwest::header::{ACCEPT, CONTENT_TYPE};
+use serde_json::Value;
+
+/// The media type of W3C Web Annotations, for servers and validators that
+/// require the exact profile.
+pub const ANNO_JSONLD: &str = r#"application/ld+json; profile="http://www.w3.org/ns/anno.jsonld""#;
+
+/// A response body, with the `Content-Type` the server chose for it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Negotiated<T> {
+    pub content_type: Option<String>,
+    pub body: T,
+}
+
+impl AnnoRepoClient {
+    /// Get an annotation as media type `accept`, such as [`ANNO_JSONLD`],
+    /// overriding the client's
+    /// [`accept`](crate::AnnoRepoClientBuilder::accept) header. Bypasses the
+    /// caches, which don't keep the content type.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container, annotation_name, accept)))]
+    pub async fn get_annotation_as(
+        &self,
+        annotation_name: &str,
+        accept: &str,
+    ) -> Result<Negotiated<Value>, Error> {
+        let url = self.annotation_url(annotation_name);
+
+        let res = self
+            .send(self.client.get(url).header(ACCEPT, accept))
+            .await?;
+        let content_type = res
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+
+        Ok(Negotiated {
+            content_type,
+            body: res.json().await?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+    use serde_json::json;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn annotations_can_be_requested_with_the_w3c_profile() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/w3c/c/a1"))
+            .and(header("accept", ANNO_JSONLD))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_raw(json!({"id": "a1"}).to_string(), ANNO_JSONLD),
+            )
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::builder(server.uri(), "c".to_string())
+            .accept(HeaderValue::from_static("application/json"))
+            .build()
+            .unwrap();
+
+        let annotation = client.get_annotation_as("a1", ANNO_JSONLD).await.unwrap();
+
+        assert_eq!(annotation.content_type.as_deref(), Some(ANNO_JSONLD));
+        assert_eq!(annotation.body["id"], "a1");
+    }
+}
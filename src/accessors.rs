@@ -0,0 +1,192 @@
+use crate::{Annotation, OneOrMany};
+use serde_json::Value;
+
+/// A range of text anchors, as given by a `TextAnchorSelector` on a target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextAnchorRange<'a> {
+    /// The source of the target the selector was found on.
+    pub source: Option<&'a str>,
+    pub start: u64,
+    pub end: u64,
+}
+
+/// Accessors for the parts of an annotation that are awkward to get at
+/// because they can be a scalar, an array or a nested resource.
+pub trait AnnotationExt {
+    /// The IRIs of everything targeted: plain IRI targets, the `source` of
+    /// specific resources and the `id` of embedded resources.
+    fn target_sources(&self) -> Vec<&str>;
+
+    /// The `value` of every textual body, including `bodyValue` and the
+    /// items of choice/composite bodies.
+    fn body_values(&self) -> Vec<&str>;
+
+    /// The first `TextAnchorSelector` range on any of the targets.
+    fn text_anchor_range(&self) -> Option<TextAnchorRange<'_>>;
+}
+
+impl AnnotationExt for Value {
+    fn target_sources(&self) -> Vec<&str> {
+        items(&self["target"]).filter_map(resource_iri).collect()
+    }
+
+    fn body_values(&self) -> Vec<&str> {
+        let mut values: Vec<&str> = self["bodyValue"].as_str().into_iter().collect();
+        for body in items(&self["body"]) {
+            collect_body_values(body, &mut values);
+        }
+        values
+    }
+
+    fn text_anchor_range(&self) -> Option<TextAnchorRange<'_>> {
+        items(&self["target"]).find_map(text_anchor_range)
+    }
+}
+
+impl AnnotationExt for Annotation {
+    fn target_sources(&self) -> Vec<&str> {
+        self.target.iter().filter_map(resource_iri).collect()
+    }
+
+    fn body_values(&self) -> Vec<&str> {
+        let mut values: Vec<&str> = self
+            .extra
+            .get("bodyValue")
+            .and_then(Value::as_str)
+            .into_iter()
+            .collect();
+        for body in self.body.iter().flat_map(OneOrMany::iter) {
+            collect_body_values(body, &mut values);
+        }
+        values
+    }
+
+    fn text_anchor_range(&self) -> Option<TextAnchorRange<'_>> {
+        self.target.iter().find_map(text_anchor_range)
+    }
+}
+
+/// Iterate over a value that may be a single item, an array or absent.
+pub(crate) fn items(value: &Value) -> Box<dyn Iterator<Item = &Value> + '_> {
+    match value {
+        Value::Null => Box::new(std::iter::empty()),
+        Value::Array(items) => Box::new(items.iter()),
+        item => Box::new(std::iter::once(item)),
+    }
+}
+
+pub(crate) fn resource_iri(resource: &Value) -> Option<&str> {
+    match resource {
+        Value::String(iri) => Some(iri),
+        Value::Object(map) => match map.get("source") {
+            Some(Value::String(source)) => Some(source),
+            Some(source @ Value::Object(_)) => resource_iri(source),
+            _ => map.get("id").and_then(Value::as_str),
+        },
+        _ => None,
+    }
+}
+
+fn collect_body_values<'a>(body: &'a Value, values: &mut Vec<&'a str>) {
+    if let Some(value) = body["value"].as_str() {
+        values.push(value);
+    }
+    for item in items(&body["items"]) {
+        collect_body_values(item, values);
+    }
+}
+
+fn text_anchor_range(target: &Value) -> Option<TextAnchorRange<'_>> {
+    let selector = items(&target["selector"]).find(|selector| {
+        selector["type"]
+            .as_str()
+            .is_some_and(|t| t.ends_with("TextAnchorSelector"))
+    })?;
+
+    Some(TextAnchorRange {
+        source: resource_iri(target),
+        start: selector["start"].as_u64()?,
+        end: selector["end"].as_u64()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn annotation() -> Value {
+        json!({
+            "type": "Annotation",
+            "body": [
+                {"type": "TextualBody", "purpose": "tagging", "value": "person"},
+                "https://example.com/vocab/Person",
+                {"type": "Choice", "items": [
+                    {"type": "TextualBody", "value": "Amsterdam", "language": "nl"},
+                    {"type": "TextualBody", "value": "Amsterdam", "language": "en"}
+                ]}
+            ],
+            "target": [
+                "https://example.com/scans/1",
+                {"id": "https://example.com/images/1.jpg", "type": "Image"},
+                {
+                    "source": "https://textrepo.example.com/rest/versions/v1/contents",
+                    "selector": [
+                        {"type": "TextPositionSelector", "start": 10, "end": 20},
+                        {"type": "urn:republic:TextAnchorSelector", "start": 4, "end": 7}
+                    ]
+                },
+                {"type": "SpecificResource", "source": {"id": "https://example.com/canvas/1", "type": "Canvas"}}
+            ]
+        })
+    }
+
+    #[test]
+    fn target_sources_cover_every_target_shape() {
+        assert_eq!(
+            annotation().target_sources(),
+            [
+                "https://example.com/scans/1",
+                "https://example.com/images/1.jpg",
+                "https://textrepo.example.com/rest/versions/v1/contents",
+                "https://example.com/canvas/1"
+            ]
+        );
+        assert_eq!(
+            json!({"target": "https://example.com/1"}).target_sources(),
+            ["https://example.com/1"]
+        );
+        assert!(json!({}).target_sources().is_empty());
+    }
+
+    #[test]
+    fn body_values_include_nested_items() {
+        assert_eq!(
+            annotation().body_values(),
+            ["person", "Amsterdam", "Amsterdam"]
+        );
+        assert_eq!(json!({"bodyValue": "note"}).body_values(), ["note"]);
+    }
+
+    #[test]
+    fn text_anchor_range_is_found_in_selector_arrays() {
+        let annotation = annotation();
+        let range = annotation.text_anchor_range().unwrap();
+
+        assert_eq!(range.start, 4);
+        assert_eq!(range.end, 7);
+        assert_eq!(
+            range.source,
+            Some("https://textrepo.example.com/rest/versions/v1/contents")
+        );
+    }
+
+    #[test]
+    fn typed_model_has_the_same_accessors() {
+        let annotation: Annotation = serde_json::from_value(annotation()).unwrap();
+
+        assert_eq!(annotation.target_sources().len(), 4);
+        assert_eq!(annotation.body_values().len(), 3);
+        assert_eq!(annotation.text_anchor_range().unwrap().start, 4);
+    }
+}
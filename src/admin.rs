@@ -0,0 +1,236 @@
+use crate::encode::segment;
+use crate::model::{extra_field_names, UnknownFields};
+use crate::{AnnoRepoClient, Error};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// An AnnoRepo user and their API key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserEntry {
+    pub user_name: String,
+    pub api_key: String,
+}
+
+impl UnknownFields for UserEntry {
+    fn unknown_fields(&self) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+/// Outcome of [`AnnoRepoClient::create_users`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserAddResults {
+    /// Names of the users that were created.
+    pub added: Vec<String>,
+    pub rejected: Vec<RejectedUserEntry>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl UnknownFields for UserAddResults {
+    fn unknown_fields(&self) -> Vec<String> {
+        extra_field_names(&self.extra)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RejectedUserEntry {
+    pub user_entry: Value,
+    pub reason: String,
+}
+
+/// Instance-wide policy settings, as served by `/admin/settings`.
+///
+/// Settings this client doesn't know about are kept in `extra` and sent back
+/// unchanged on update.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ServerSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allow_anonymous_container_creation: Option<bool>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl UnknownFields for ServerSettings {
+    fn unknown_fields(&self) -> Vec<String> {
+        extra_field_names(&self.extra)
+    }
+}
+
+impl AnnoRepoClient {
+    /// All users of the server. Requires the root API key.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn list_users(&self) -> Result<Vec<UserEntry>, Error> {
+        self.require_authentication().await?;
+        let url = format!("{}/admin/users", self.base_url);
+
+        self.client_get_typed(&url).await
+    }
+
+    /// Create users. Entries the server refuses, for instance because the
+    /// user name is already taken, are listed in the result rather than
+    /// failing the call. Requires the root API key.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(count = users.len())))]
+    pub async fn create_users(&self, users: &[UserEntry]) -> Result<UserAddResults, Error> {
+        self.require_authentication().await?;
+        let url = format!("{}/admin/users", self.base_url);
+
+        self.send(self.client.post(url).json(users))
+            .await?
+            .typed(self.deserialization_mode)
+            .await
+    }
+
+    /// The server's settings. Requires the root API key.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn get_server_settings(&self) -> Result<ServerSettings, Error> {
+        self.require_authentication().await?;
+        let url = format!("{}/admin/settings", self.base_url);
+
+        self.client_get_typed(&url).await
+    }
+
+    /// Replace the server's settings, returning them as the server applied
+    /// them. Requires the root API key.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn update_server_settings(
+        &self,
+        settings: &ServerSettings,
+    ) -> Result<ServerSettings, Error> {
+        self.require_authentication().await?;
+        let url = format!("{}/admin/settings", self.base_url);
+
+        self.send(self.client.put(url).json(settings))
+            .await?
+            .typed(self.deserialization_mode)
+            .await
+    }
+
+    /// Delete a user. Requires the root API key.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(user_name)))]
+    pub async fn delete_user(&self, user_name: &str) -> Result<(), Error> {
+        self.require_authentication().await?;
+        let url = format!("{}/admin/users/{}", self.base_url, segment(user_name));
+
+        self.send(self.client.delete(url)).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{body_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn server(with_authentication: bool) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/about"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "appName": "AnnoRepo",
+                "version": "0.7.2",
+                "startedAt": "2024-03-01T09:00:00Z",
+                "withAuthentication": with_authentication
+            })))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    fn root_client(server: &MockServer) -> AnnoRepoClient {
+        AnnoRepoClient::builder(server.uri(), "c".to_string())
+            .api_key("root-key")
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn admin_calls_fail_early_on_auth_mismatch() {
+        let open_server = server(false).await;
+        let result = root_client(&open_server).list_users().await;
+        assert!(matches!(result, Err(Error::AuthenticationDisabled)));
+
+        let secured_server = server(true).await;
+        let anonymous = AnnoRepoClient::new(secured_server.uri(), "c".to_string()).unwrap();
+        let result = anonymous.list_users().await;
+        assert!(matches!(result, Err(Error::MissingApiKey)));
+
+        for server in [open_server, secured_server] {
+            let requests = server.received_requests().await.unwrap();
+            assert_eq!(requests.len(), 1, "only /about should have been called");
+        }
+    }
+
+    #[tokio::test]
+    async fn unknown_settings_are_sent_back_unchanged() {
+        let server = server(true).await;
+        let settings = json!({"allowAnonymousContainerCreation": false, "maxPageSize": 100});
+        Mock::given(method("GET"))
+            .and(path("/admin/settings"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(settings))
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/admin/settings"))
+            .and(body_json(
+                json!({"allowAnonymousContainerCreation": true, "maxPageSize": 100}),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                json!({"allowAnonymousContainerCreation": true, "maxPageSize": 100}),
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = root_client(&server);
+
+        let mut settings = client.get_server_settings().await.unwrap();
+        settings.allow_anonymous_container_creation = Some(true);
+        let updated = client.update_server_settings(&settings).await.unwrap();
+
+        assert_eq!(updated, settings);
+    }
+
+    #[tokio::test]
+    async fn users_are_created_with_the_root_key() {
+        let server = server(true).await;
+        Mock::given(method("POST"))
+            .and(path("/admin/users"))
+            .and(header("authorization", "Bearer root-key"))
+            .and(body_json(json!([
+                {"userName": "harvester", "apiKey": "k1"},
+                {"userName": "root", "apiKey": "k2"}
+            ])))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "added": ["harvester"],
+                "rejected": [{
+                    "userEntry": {"userName": "root", "apiKey": "k2"},
+                    "reason": "userName 'root' is reserved"
+                }]
+            })))
+            .mount(&server)
+            .await;
+        let client = root_client(&server);
+
+        let results = client
+            .create_users(&[
+                UserEntry {
+                    user_name: "harvester".to_string(),
+                    api_key: "k1".to_string(),
+                },
+                UserEntry {
+                    user_name: "root".to_string(),
+                    api_key: "k2".to_string(),
+                },
+            ])
+            .await
+            .unwrap();
+
+        assert_eq!(results.added, ["harvester"]);
+        assert_eq!(results.rejected[0].reason, "userName 'root' is reserved");
+    }
+}
@@ -0,0 +1,115 @@
+use crate::{AnnotationStream, Error};
+use futures::TryStreamExt;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// How often each value occurs, as counted by
+/// [`AnnotationStream::count_by`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Counts {
+    /// Occurrences by value; strings as they are, other values as JSON.
+    pub counts: HashMap<String, u64>,
+    /// Number of annotations counted, including those without the value.
+    pub annotations: u64,
+    /// Number of annotations without the value.
+    pub missing: u64,
+}
+
+impl Counts {
+    fn add(&mut self, value: Option<&Value>) {
+        self.annotations += 1;
+        match value {
+            None | Some(Value::Null) => self.missing += 1,
+            // Each element of an array, such as several motivations, counts.
+            Some(Value::Array(values)) => values.iter().for_each(|value| self.add_value(value)),
+            Some(value) => self.add_value(value),
+        }
+    }
+
+    fn add_value(&mut self, value: &Value) {
+        let key = match value {
+            Value::String(value) => value.clone(),
+            value => value.to_string(),
+        };
+        *self.counts.entry(key).or_default() += 1;
+    }
+
+    /// The `n` most frequent values, most frequent first, ties in order of
+    /// value.
+    pub fn top(&self, n: usize) -> Vec<(&str, u64)> {
+        let mut counts: Vec<_> = self
+            .counts
+            .iter()
+            .map(|(value, count)| (value.as_str(), *count))
+            .collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        counts.truncate(n);
+        counts
+    }
+}
+
+impl AnnotationStream<'_> {
+    /// Count the values at a JSON pointer (`/motivation`) over all
+    /// annotations, as they stream in, without collecting them.
+    pub async fn count_by(self, pointer: &str) -> Result<Counts, Error> {
+        self.try_fold(Counts::default(), |mut counts, annotation| {
+            counts.add(annotation.pointer(pointer));
+            async move { Ok(counts) }
+        })
+        .await
+    }
+
+    /// Group the annotations by the value at a JSON pointer, keyed like
+    /// [`Counts`]. Annotations without the value are left out. Unlike
+    /// [`count_by`](Self::count_by), this keeps every annotation.
+    pub async fn group_by(self, pointer: &str) -> Result<HashMap<String, Vec<Value>>, Error> {
+        self.try_fold(HashMap::new(), |mut groups, annotation| {
+            let key = match annotation.pointer(pointer) {
+                None | Some(Value::Null) => None,
+                Some(Value::String(value)) => Some(value.clone()),
+                Some(value) => Some(value.to_string()),
+            };
+            if let Some(key) = key {
+                groups.entry(key).or_insert_with(Vec::new).push(annotation);
+            }
+            async move { Ok(groups) }
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::AnnoRepoClient;
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn values_are_counted_across_the_stream() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/search/s1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [
+                    {"id": "a1", "motivation": "tagging"},
+                    {"id": "a2", "motivation": ["tagging", "classifying"]},
+                    {"id": "a3", "motivation": "commenting"},
+                    {"id": "a4"}
+                ]
+            })))
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        let counts = client
+            .stream_search_result_annotations("c", "s1", None)
+            .count_by("/motivation")
+            .await
+            .unwrap();
+
+        assert_eq!(counts.annotations, 4);
+        assert_eq!(counts.missing, 1);
+        assert_eq!(counts.top(2), [("tagging", 2), ("classifying", 1)]);
+    }
+}
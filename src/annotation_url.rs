@@ -0,0 +1,148 @@
+use crate::builder::normalize_base_url;
+use crate::encode::segment;
+use crate::{AnnoRepoClient, Error};
+use percent_encoding::percent_decode_str;
+use std::fmt;
+use std::str::FromStr;
+
+/// The parts of an annotation's IRI, `{base_url}/w3c/{container}/{name}`,
+/// with the names decoded.
+///
+/// ```
+/// use annorepo_client::AnnotationUrl;
+///
+/// let url: AnnotationUrl = "https://annorepo.example.com/w3c/letters/brief%201".parse()?;
+/// assert_eq!(url.container, "letters");
+/// assert_eq!(url.name, "brief 1");
+/// assert_eq!(url.to_string(), "https://annorepo.example.com/w3c/letters/brief%201");
+/// # Ok::<(), annorepo_client::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AnnotationUrl {
+    /// Without a trailing slash, as in
+    /// [`AnnoRepoClient::builder`](AnnoRepoClient::builder).
+    pub base_url: String,
+    pub container: String,
+    pub name: String,
+}
+
+impl AnnotationUrl {
+    pub fn new<S: Into<String>>(base_url: S, container: S, name: S) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            container: container.into(),
+            name: name.into(),
+        }
+    }
+
+    /// Split `url` at its last `/w3c/` segment into the base URL before it,
+    /// and the container and name after it, failing with
+    /// [`Error::InvalidAnnotationUrl`] unless exactly those two follow.
+    pub fn parse(url: &str) -> Result<Self, Error> {
+        let invalid = || Error::InvalidAnnotationUrl(url.to_string());
+        let parsed = reqwest::Url::parse(url).map_err(|_| invalid())?;
+        if parsed.query().is_some() || parsed.fragment().is_some() {
+            return Err(invalid());
+        }
+        let (base_url, path) = parsed.as_str().rsplit_once("/w3c/").ok_or_else(invalid)?;
+        let base_url = normalize_base_url(base_url).map_err(|_| invalid())?;
+        let (container, name) = match path.split('/').collect::<Vec<_>>()[..] {
+            [container, name] if !container.is_empty() && !name.is_empty() => (container, name),
+            _ => return Err(invalid()),
+        };
+        let decode = |segment: &str| percent_decode_str(segment).decode_utf8_lossy().into_owned();
+
+        Ok(Self {
+            base_url,
+            container: decode(container),
+            name: decode(name),
+        })
+    }
+}
+
+impl fmt::Display for AnnotationUrl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}/w3c/{}/{}",
+            self.base_url,
+            segment(&self.container),
+            segment(&self.name)
+        )
+    }
+}
+
+impl FromStr for AnnotationUrl {
+    type Err = Error;
+
+    fn from_str(url: &str) -> Result<Self, Error> {
+        Self::parse(url)
+    }
+}
+
+impl AnnoRepoClient {
+    /// [`AnnotationUrl::parse`], failing with [`Error::ForeignAnnotationUrl`]
+    /// for annotations on other servers than this client's.
+    pub fn parse_annotation_url(&self, url: &str) -> Result<AnnotationUrl, Error> {
+        let parsed = AnnotationUrl::parse(url)?;
+        if parsed.base_url != *self.base_url {
+            return Err(Error::ForeignAnnotationUrl(url.to_string()));
+        }
+        Ok(parsed)
+    }
+
+    /// The IRI of annotation `name` in the client's container.
+    pub fn annotation_iri(&self, name: &str) -> AnnotationUrl {
+        AnnotationUrl::new(&*self.base_url, &*self.container, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_container_and_name_may_follow_w3c() {
+        for url in [
+            "https://annorepo.example.com/w3c/letters/",
+            "https://annorepo.example.com/w3c/letters/a1/extra",
+            "https://annorepo.example.com/w3c/letters/a1?page=1",
+            "https://annorepo.example.com/services/letters/a1",
+            "letters/a1",
+        ] {
+            assert!(
+                matches!(
+                    AnnotationUrl::parse(url),
+                    Err(Error::InvalidAnnotationUrl(_))
+                ),
+                "{url}"
+            );
+        }
+        let url = AnnotationUrl::parse("http://localhost:8080/annorepo/w3c/c/a%2F1").unwrap();
+        assert_eq!(
+            url,
+            AnnotationUrl::new("http://localhost:8080/annorepo", "c", "a/1")
+        );
+    }
+
+    #[test]
+    fn urls_must_be_on_the_clients_server() {
+        let client = AnnoRepoClient::new("https://annorepo.example.com/", "c").unwrap();
+
+        assert_eq!(
+            client
+                .parse_annotation_url("https://annorepo.example.com/w3c/other/a1")
+                .unwrap()
+                .container,
+            "other"
+        );
+        assert!(matches!(
+            client.parse_annotation_url("https://elsewhere.example.com/w3c/c/a1"),
+            Err(Error::ForeignAnnotationUrl(_))
+        ));
+        assert_eq!(
+            client.annotation_iri("a 1").to_string(),
+            "https://annorepo.example.com/w3c/c/a%201"
+        );
+    }
+}
@@ -0,0 +1,956 @@
+use crate::cancel::is_cancelled;
+use crate::encode::{last_segment, segment};
+use crate::model::{extra_field_names, UnknownFields};
+use crate::validate;
+use crate::{
+    AnnoRepoClient, Annotation, CancellationToken, Error, Phase, Progress, ProgressUpdate,
+};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use futures::{StreamExt, TryStreamExt};
+use reqwest::header::{CONTENT_ENCODING, CONTENT_TYPE, ETAG, IF_MATCH};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::sync::Arc;
+
+/// Where the server stored an annotation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnnotationIdentifier {
+    pub container_name: String,
+    pub annotation_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl UnknownFields for AnnotationIdentifier {
+    fn unknown_fields(&self) -> Vec<String> {
+        extra_field_names(&self.extra)
+    }
+}
+
+/// Annotations requested at the same time by
+/// [`AnnoRepoClient::get_annotations`].
+const GET_CONCURRENCY: usize = 8;
+
+/// An annotation stored by [`AnnoRepoClient::create_annotation`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreatedAnnotation {
+    pub annotation_name: String,
+    pub url: String,
+    pub etag: Option<String>,
+    /// Whether the server named the annotation after the requested slug.
+    pub slug_honored: bool,
+}
+
+/// Whether an annotation exists, from [`AnnoRepoClient::annotation_exists`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnnotationStatus {
+    pub exists: bool,
+    pub etag: Option<String>,
+}
+
+/// What [`AnnoRepoClient::upsert_annotation`] did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UpsertOutcome {
+    /// There was no annotation by that name, and now there is.
+    Created(CreatedAnnotation),
+    /// The existing annotation was replaced, and has this new ETag.
+    Updated { etag: Option<String> },
+}
+
+/// How [`AnnoRepoClient::upload_annotations`] splits up its work.
+#[derive(Debug, Clone)]
+pub struct UploadOptions {
+    /// Annotations per batch request.
+    pub batch_size: usize,
+    /// Batch requests in flight at the same time.
+    pub concurrency: usize,
+    /// Told about every batch stored.
+    pub progress: Option<Arc<dyn Progress>>,
+    /// Stops sending batches once cancelled.
+    pub cancellation: Option<CancellationToken>,
+    /// Check every annotation with
+    /// [`validate_annotation`](crate::validate_annotation) before sending
+    /// any, failing with [`Error::InvalidAnnotations`].
+    pub validate: bool,
+    /// Validate and split up the annotations, and report progress, but send
+    /// nothing. No identifiers are returned.
+    pub dry_run: bool,
+}
+
+impl Default for UploadOptions {
+    fn default() -> Self {
+        Self {
+            batch_size: 100,
+            concurrency: 4,
+            progress: None,
+            cancellation: None,
+            validate: false,
+            dry_run: false,
+        }
+    }
+}
+
+impl AnnoRepoClient {
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container, annotation_name)))]
+    pub async fn get_annotation(&self, annotation_name: &str) -> Result<Annotation, Error> {
+        self.annotation_at(&self.annotation_url(annotation_name))
+            .await
+    }
+
+    async fn annotation_at(&self, url: &str) -> Result<Annotation, Error> {
+        if let Some(annotation) = self.annotation_cache.as_ref().and_then(|c| c.get(url)) {
+            return Ok(annotation);
+        }
+
+        let annotation = self.client_get_typed(url).await?;
+        if let Some(cache) = &self.annotation_cache {
+            cache.insert(url, &annotation);
+        }
+        Ok(annotation)
+    }
+
+    /// Get several annotations at once, a few at a time, returning the
+    /// result for each name in the same order as `names`.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container, count = names.len())))]
+    pub async fn get_annotations(&self, names: &[&str]) -> Vec<Result<Annotation, Error>> {
+        // Collected first, so the future stays `Send` behind `AnnoRepoApi`.
+        let requests: Vec<_> = names.iter().map(|name| self.get_annotation(name)).collect();
+        futures::stream::iter(requests)
+            .buffered(GET_CONCURRENCY)
+            .collect()
+            .await
+    }
+
+    /// Get annotations by their full IRIs, such as cross-references stored
+    /// in other annotations, a few at a time, returning the result for each
+    /// in the same order as `urls`. The annotations may be in any container
+    /// on this client's server; see
+    /// [`parse_annotation_url`](Self::parse_annotation_url) for the IRIs that
+    /// fail without being requested.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(count = urls.len())))]
+    pub async fn get_annotations_by_url(&self, urls: &[&str]) -> Vec<Result<Annotation, Error>> {
+        let requests: Vec<_> = urls
+            .iter()
+            .map(|url| async move {
+                let url = self.parse_annotation_url(url)?.to_string();
+                self.annotation_at(&url).await
+            })
+            .collect();
+        futures::stream::iter(requests)
+            .buffered(GET_CONCURRENCY)
+            .collect()
+            .await
+    }
+
+    /// Check whether the container has an annotation with a `HEAD` request,
+    /// without downloading it.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container, annotation_name)))]
+    pub async fn annotation_exists(
+        &self,
+        annotation_name: &str,
+    ) -> Result<AnnotationStatus, Error> {
+        let url = self.annotation_url(annotation_name);
+
+        match self.send(self.client.head(url)).await {
+            Ok(res) => Ok(AnnotationStatus {
+                exists: true,
+                etag: res
+                    .headers()
+                    .get(ETAG)
+                    .and_then(|etag| etag.to_str().ok())
+                    .map(str::to_string),
+            }),
+            Err(Error::NotFound(_)) => Ok(AnnotationStatus {
+                exists: false,
+                etag: None,
+            }),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Add one annotation to the container, asking the server to name it
+    /// `slug`, so that re-ingests can reproduce the name. The server may pick
+    /// another name, for instance when `slug` is taken.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container, slug)))]
+    pub async fn create_annotation<T: Serialize>(
+        &self,
+        annotation: &T,
+        slug: Option<&str>,
+    ) -> Result<CreatedAnnotation, Error> {
+        let url = self.container_url();
+        let mut request = self.client.post(url).json(annotation);
+        if let Some(slug) = slug {
+            request = request.header("Slug", slug);
+        }
+
+        self.send_create(request, slug).await
+    }
+
+    /// Send a request that creates an annotation, asking for the name `slug`.
+    pub(crate) async fn send_create(
+        &self,
+        request: reqwest::RequestBuilder,
+        slug: Option<&str>,
+    ) -> Result<CreatedAnnotation, Error> {
+        let res = self.send(request).await;
+        self.invalidate_container_metadata();
+        let res = res?;
+        let etag = res
+            .headers()
+            .get(ETAG)
+            .and_then(|etag| etag.to_str().ok())
+            .map(str::to_string);
+        let url = match res.location()? {
+            Some(location) => location.to_string(),
+            None => {
+                let created: Value = res.json().await?;
+                created["id"]
+                    .as_str()
+                    .ok_or(Error::MissingAnnotationId)?
+                    .to_string()
+            }
+        };
+        let annotation_name = last_segment(&url);
+
+        Ok(CreatedAnnotation {
+            slug_honored: slug.is_some_and(|slug| slug == annotation_name),
+            annotation_name,
+            url,
+            etag,
+        })
+    }
+
+    /// Create the annotation named `annotation_name`, or replace it if it
+    /// exists, so that ingests can be re-run.
+    ///
+    /// Replacing uses the current ETag, so an annotation changed in the
+    /// meantime fails with [`Error::PreconditionFailed`], and one the server
+    /// sends no ETag for with [`Error::MissingETag`]. Should the server give a
+    /// new annotation another name, it is deleted again and this fails with
+    /// [`Error::SlugNotHonored`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container, annotation_name)))]
+    pub async fn upsert_annotation<T: Serialize>(
+        &self,
+        annotation_name: &str,
+        annotation: &T,
+    ) -> Result<UpsertOutcome, Error> {
+        let status = self.annotation_exists(annotation_name).await?;
+        if !status.exists {
+            let created = self
+                .create_annotation(annotation, Some(annotation_name))
+                .await?;
+            if !created.slug_honored {
+                self.delete_created(&created).await?;
+                return Err(Error::SlugNotHonored {
+                    requested: annotation_name.to_string(),
+                    assigned: created.annotation_name,
+                });
+            }
+            return Ok(UpsertOutcome::Created(created));
+        }
+
+        let etag = status
+            .etag
+            .ok_or_else(|| Error::MissingETag(annotation_name.to_string()))?;
+        let url = self.annotation_url(annotation_name);
+        let request = self
+            .client
+            .put(&url)
+            .json(annotation)
+            .header(IF_MATCH, etag);
+        let res = self.send(request).await;
+        self.invalidate_container_metadata();
+        self.invalidate_annotation(&url);
+        let etag = res?
+            .headers()
+            .get(ETAG)
+            .and_then(|etag| etag.to_str().ok())
+            .map(str::to_string);
+
+        Ok(UpsertOutcome::Updated { etag })
+    }
+
+    /// Delete an annotation just created, if it hasn't changed since.
+    pub(crate) async fn delete_created(&self, created: &CreatedAnnotation) -> Result<(), Error> {
+        let mut request = self.client.delete(&created.url);
+        if let Some(etag) = &created.etag {
+            request = request.header(IF_MATCH, etag);
+        }
+        let res = self.send(request).await;
+        self.invalidate_container_metadata();
+        res.map(drop)
+    }
+
+    /// Add annotations to the container in a single request, returning where
+    /// each one was stored, in the same order.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container, count = annotations.len())))]
+    pub async fn add_annotations<T: Serialize>(
+        &self,
+        annotations: &[T],
+    ) -> Result<Vec<AnnotationIdentifier>, Error> {
+        let url = format!(
+            "{base}/batch/{container}/annotations",
+            base = self.base_url,
+            container = segment(&self.container)
+        );
+
+        let request = self.client.post(url);
+        let request = if self.gzip_uploads {
+            gzip_json(request, annotations)?
+        } else {
+            request.json(annotations)
+        };
+
+        let res = self.send(request).await;
+        self.invalidate_container_metadata();
+
+        res?.typed(self.deserialization_mode).await
+    }
+
+    /// Add any number of annotations in batches, several at a time, returning
+    /// where each one was stored, in the same order as `annotations`.
+    ///
+    /// Stops at the first batch that fails; batches already sent stay stored.
+    /// The server names the annotations, so re-running an upload stores them
+    /// again; see [`create_annotations_idempotent`](Self::create_annotations_idempotent)
+    /// for uploads that can be re-run. When cancelled, the batches in flight are completed, and only their
+    /// annotations and those before them are returned.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container, count = annotations.len())))]
+    pub async fn upload_annotations<T: Serialize + Sync>(
+        &self,
+        annotations: &[T],
+        options: &UploadOptions,
+    ) -> Result<Vec<AnnotationIdentifier>, Error> {
+        if options.validate || options.dry_run {
+            validate::validate_all(annotations)?;
+        }
+        if options.dry_run {
+            let mut done = 0;
+            for batch in annotations.chunks(options.batch_size.max(1)) {
+                if is_cancelled(&options.cancellation) {
+                    break;
+                }
+                done += batch.len() as u64;
+                #[cfg(feature = "tracing")]
+                tracing::info!(count = batch.len(), "dry run: would upload batch");
+                if let Some(progress) = &options.progress {
+                    progress.update(ProgressUpdate {
+                        phase: Phase::Uploading,
+                        done,
+                        total: Some(annotations.len() as u64),
+                    });
+                }
+            }
+            return Ok(Vec::new());
+        }
+        // Futures do nothing until polled, so only `concurrency` of these
+        // requests are in flight at a time, and each checks for cancellation
+        // just before it is sent.
+        let cancellation = &options.cancellation;
+        let requests: Vec<_> = annotations
+            .chunks(options.batch_size.max(1))
+            .map(|batch| async move {
+                if is_cancelled(cancellation) {
+                    return Ok(None);
+                }
+                self.add_annotations(batch).await.map(Some)
+            })
+            .collect();
+        let total = annotations.len() as u64;
+        let mut done = 0;
+        let batches: Vec<Option<Vec<AnnotationIdentifier>>> = futures::stream::iter(requests)
+            .buffered(options.concurrency.max(1))
+            .inspect_ok(|batch| {
+                let Some(batch) = batch else {
+                    return;
+                };
+                done += batch.len() as u64;
+                if let Some(progress) = &options.progress {
+                    progress.update(ProgressUpdate {
+                        phase: Phase::Uploading,
+                        done,
+                        total: Some(total),
+                    });
+                }
+            })
+            .try_collect()
+            .await?;
+
+        // Once one batch saw the cancellation, all later ones did.
+        Ok(batches
+            .into_iter()
+            .map_while(|batch| batch)
+            .flatten()
+            .collect())
+    }
+}
+
+/// Send `body` as gzip-compressed JSON.
+fn gzip_json<T: Serialize + ?Sized>(
+    request: reqwest::RequestBuilder,
+    body: &T,
+) -> Result<reqwest::RequestBuilder, Error> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    serde_json::to_writer(&mut encoder, body).map_err(|e| Error::Io(e.into()))?;
+    let body = encoder.finish().map_err(Error::Io)?;
+
+    Ok(request
+        .header(CONTENT_TYPE, "application/json")
+        .header(CONTENT_ENCODING, "gzip")
+        .body(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OneOrMany;
+    use serde_json::json;
+    use std::io::Read;
+    use std::time::Duration;
+    use wiremock::matchers::{body_json, header, method, path};
+    use wiremock::{Mock, MockServer, Request, Respond, ResponseTemplate};
+
+    /// Names the stored annotations after their targets, answering the first
+    /// batch last.
+    struct NameAfterTarget;
+
+    impl Respond for NameAfterTarget {
+        fn respond(&self, request: &Request) -> ResponseTemplate {
+            let batch: Vec<Value> = request.body_json().unwrap();
+            let identifiers: Vec<Value> = batch
+                .iter()
+                .map(|a| json!({"containerName": "c", "annotationName": a["target"]}))
+                .collect();
+            let delay = if batch[0]["target"] == "t0" { 100 } else { 0 };
+            ResponseTemplate::new(200)
+                .set_body_json(identifiers)
+                .set_delay(Duration::from_millis(delay))
+        }
+    }
+
+    #[tokio::test]
+    async fn annotations_are_fetched_by_url_across_containers() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/w3c/other/a2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": format!("{}/w3c/other/a2", server.uri()),
+                "type": "Annotation",
+                "target": "https://example.com/letters/1"
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+        let url = format!("{}/w3c/other/a2", server.uri());
+
+        let results = client
+            .get_annotations_by_url(&[
+                &url,
+                "https://elsewhere.example.com/w3c/other/a2",
+                &format!("{}/w3c/other/", server.uri()),
+            ])
+            .await;
+
+        assert!(results[0].is_ok());
+        assert!(matches!(results[1], Err(Error::ForeignAnnotationUrl(_))));
+        assert!(matches!(results[2], Err(Error::InvalidAnnotationUrl(_))));
+    }
+
+    #[tokio::test]
+    async fn unchanged_annotations_come_from_the_etag_cache() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/w3c/c/a1"))
+            .and(header("if-none-match", "\"v1\""))
+            .respond_with(ResponseTemplate::new(304))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/w3c/c/a1"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("etag", "\"v1\"")
+                    .set_body_json(json!({"type": "Annotation", "target": "t1"})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::builder(server.uri(), "c".to_string())
+            .etag_cache(10)
+            .build()
+            .unwrap();
+
+        let first = client.get_annotation("a1").await.unwrap();
+        let second = client.get_annotation("a1").await.unwrap();
+
+        assert_eq!(first, second);
+        assert_eq!(second.target, OneOrMany::One(json!("t1")));
+    }
+
+    #[tokio::test]
+    async fn cached_annotations_are_not_fetched_again() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/w3c/c/a1"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({"type": "Annotation", "target": "t1"})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::builder(server.uri(), "c".to_string())
+            .annotation_cache(10, Duration::from_secs(60))
+            .build()
+            .unwrap();
+
+        let first = client.get_annotation("a1").await.unwrap();
+        let second = client.get_annotation("a1").await.unwrap();
+
+        assert_eq!(first, second);
+    }
+
+    #[tokio::test]
+    async fn batches_can_be_sent_compressed() {
+        let annotations = [json!({"type": "Annotation", "target": "https://example.com/1"})];
+        let expected = annotations.to_vec();
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/batch/c/annotations"))
+            .and(header("content-encoding", "gzip"))
+            .and(move |request: &Request| {
+                let mut json = String::new();
+                flate2::read::GzDecoder::new(&request.body[..])
+                    .read_to_string(&mut json)
+                    .is_ok()
+                    && serde_json::from_str::<Vec<Value>>(&json).ok() == Some(expected.clone())
+            })
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                {"containerName": "c", "annotationName": "a1"}
+            ])))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::builder(server.uri(), "c".to_string())
+            .gzip_uploads(true)
+            .build()
+            .unwrap();
+
+        let identifiers = client.add_annotations(&annotations).await.unwrap();
+
+        assert_eq!(identifiers[0].annotation_name, "a1");
+    }
+
+    #[tokio::test]
+    async fn concurrent_uploads_keep_the_input_order() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/batch/c/annotations"))
+            .respond_with(NameAfterTarget)
+            .expect(4)
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+        let annotations: Vec<Value> = (0..7)
+            .map(|i| json!({"type": "Annotation", "target": format!("t{i}")}))
+            .collect();
+        let options = UploadOptions {
+            batch_size: 2,
+            concurrency: 3,
+            ..UploadOptions::default()
+        };
+
+        let identifiers = client
+            .upload_annotations(&annotations, &options)
+            .await
+            .unwrap();
+
+        let names: Vec<&str> = identifiers
+            .iter()
+            .map(|id| id.annotation_name.as_str())
+            .collect();
+        assert_eq!(names, ["t0", "t1", "t2", "t3", "t4", "t5", "t6"]);
+    }
+
+    #[tokio::test]
+    async fn upload_progress_is_reported_per_batch() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/batch/c/annotations"))
+            .respond_with(NameAfterTarget)
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+        let annotations: Vec<Value> = (0..5)
+            .map(|i| json!({"type": "Annotation", "target": format!("t{i}")}))
+            .collect();
+        let updates = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = updates.clone();
+        let options = UploadOptions {
+            batch_size: 2,
+            concurrency: 1,
+            progress: Some(Arc::new(move |update: ProgressUpdate| {
+                seen.lock().unwrap().push((update.done, update.total));
+            })),
+            ..UploadOptions::default()
+        };
+
+        client
+            .upload_annotations(&annotations, &options)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            *updates.lock().unwrap(),
+            [(2, Some(5)), (4, Some(5)), (5, Some(5))]
+        );
+    }
+
+    #[tokio::test]
+    async fn cancelled_uploads_return_the_batches_sent() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/batch/c/annotations"))
+            .respond_with(NameAfterTarget)
+            .expect(2)
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+        let annotations: Vec<Value> = (0..6)
+            .map(|i| json!({"type": "Annotation", "target": format!("t{i}")}))
+            .collect();
+        let cancellation = CancellationToken::new();
+        let token = cancellation.clone();
+        let options = UploadOptions {
+            batch_size: 2,
+            concurrency: 1,
+            progress: Some(Arc::new(move |update: ProgressUpdate| {
+                if update.done == 4 {
+                    token.cancel();
+                }
+            })),
+            cancellation: Some(cancellation),
+            ..UploadOptions::default()
+        };
+
+        let identifiers = client
+            .upload_annotations(&annotations, &options)
+            .await
+            .unwrap();
+
+        assert_eq!(identifiers.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn annotations_are_added_in_one_batch() {
+        let annotations = [
+            json!({"type": "Annotation", "target": "https://example.com/1"}),
+            json!({"type": "Annotation", "target": "https://example.com/2"}),
+        ];
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/batch/c/annotations"))
+            .and(body_json(&annotations))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                {"containerName": "c", "annotationName": "a1", "etag": "123"},
+                {"containerName": "c", "annotationName": "a2", "etag": "456"}
+            ])))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        let identifiers = client.add_annotations(&annotations).await.unwrap();
+
+        let names: Vec<&str> = identifiers
+            .iter()
+            .map(|id| id.annotation_name.as_str())
+            .collect();
+        assert_eq!(names, ["a1", "a2"]);
+    }
+
+    #[tokio::test]
+    async fn created_annotations_report_whether_the_slug_was_used() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/w3c/c/"))
+            .and(header("slug", "letter-1"))
+            .respond_with(
+                ResponseTemplate::new(201)
+                    .insert_header("location", format!("{}/w3c/c/letter-1", server.uri()))
+                    .insert_header("etag", "\"1\""),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/w3c/c/"))
+            .and(header("slug", "taken"))
+            .respond_with(
+                ResponseTemplate::new(201)
+                    .insert_header("location", format!("{}/w3c/c/0b9a", server.uri())),
+            )
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+        let annotation = json!({"type": "Annotation", "target": "https://example.com/1"});
+
+        let honored = client
+            .create_annotation(&annotation, Some("letter-1"))
+            .await
+            .unwrap();
+        let renamed = client
+            .create_annotation(&annotation, Some("taken"))
+            .await
+            .unwrap();
+
+        assert_eq!(honored.annotation_name, "letter-1");
+        assert_eq!(honored.etag.as_deref(), Some("\"1\""));
+        assert!(honored.slug_honored);
+        assert_eq!(renamed.annotation_name, "0b9a");
+        assert!(!renamed.slug_honored);
+    }
+
+    #[tokio::test]
+    async fn existence_is_checked_without_the_body() {
+        let server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .and(path("/w3c/c/a1"))
+            .respond_with(ResponseTemplate::new(200).insert_header("etag", "\"7\""))
+            .mount(&server)
+            .await;
+        Mock::given(method("HEAD"))
+            .and(path("/w3c/c/a2"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        let present = client.annotation_exists("a1").await.unwrap();
+        let absent = client.annotation_exists("a2").await.unwrap();
+
+        assert!(present.exists);
+        assert_eq!(present.etag.as_deref(), Some("\"7\""));
+        assert!(!absent.exists);
+    }
+
+    #[tokio::test]
+    async fn many_annotations_are_returned_in_order() {
+        let server = MockServer::start().await;
+        for (name, delay) in [("a1", 100), ("a2", 0)] {
+            Mock::given(method("GET"))
+                .and(path(format!("/w3c/c/{name}")))
+                .respond_with(
+                    ResponseTemplate::new(200)
+                        .set_body_json(json!({"type": "Annotation", "target": name}))
+                        .set_delay(Duration::from_millis(delay)),
+                )
+                .mount(&server)
+                .await;
+        }
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        let results = client.get_annotations(&["a1", "missing", "a2"]).await;
+
+        assert_eq!(
+            results[0].as_ref().unwrap().target,
+            OneOrMany::One("a1".into())
+        );
+        assert!(matches!(results[1], Err(Error::NotFound(_))));
+        assert_eq!(
+            results[2].as_ref().unwrap().target,
+            OneOrMany::One("a2".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn upserts_replace_existing_annotations_with_their_etag() {
+        let server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .and(path("/w3c/c/a1"))
+            .respond_with(ResponseTemplate::new(200).insert_header("etag", "\"1\""))
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/w3c/c/a1"))
+            .and(header("if-match", "\"1\""))
+            .respond_with(ResponseTemplate::new(200).insert_header("etag", "\"2\""))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("HEAD"))
+            .and(path("/w3c/c/a2"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/w3c/c/"))
+            .and(header("slug", "a2"))
+            .respond_with(
+                ResponseTemplate::new(201)
+                    .insert_header("location", format!("{}/w3c/c/a2", server.uri())),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+        let annotation = json!({"type": "Annotation", "target": "https://example.com/1"});
+
+        let updated = client.upsert_annotation("a1", &annotation).await.unwrap();
+        let created = client.upsert_annotation("a2", &annotation).await.unwrap();
+
+        assert_eq!(
+            updated,
+            UpsertOutcome::Updated {
+                etag: Some("\"2\"".to_string())
+            }
+        );
+        assert!(matches!(created, UpsertOutcome::Created(c) if c.slug_honored));
+    }
+
+    #[tokio::test]
+    async fn upserts_fail_rather_than_store_under_another_name_or_blindly() {
+        let server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .and(path("/w3c/c/a1"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&server)
+            .await;
+        Mock::given(method("HEAD"))
+            .and(path("/w3c/c/a2"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/w3c/c/"))
+            .respond_with(
+                ResponseTemplate::new(201)
+                    .insert_header("location", format!("{}/w3c/c/x9", server.uri()))
+                    .insert_header("etag", "\"1\""),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/w3c/c/x9"))
+            .and(header("if-match", "\"1\""))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+        let annotation = json!({"type": "Annotation", "target": "https://example.com/1"});
+
+        assert!(matches!(
+            client.upsert_annotation("a1", &annotation).await,
+            Err(Error::MissingETag(name)) if name == "a1"
+        ));
+        assert!(matches!(
+            client.upsert_annotation("a2", &annotation).await,
+            Err(Error::SlugNotHonored { assigned, .. }) if assigned == "x9"
+        ));
+    }
+
+    #[tokio::test]
+    async fn invalid_annotations_are_not_uploaded() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+            .expect(0)
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+        let annotations = [
+            json!({
+                "@context": "http://www.w3.org/ns/anno.jsonld",
+                "type": "Annotation",
+                "target": "https://example.com/1"
+            }),
+            json!({"type": "Annotation"}),
+        ];
+        let options = UploadOptions {
+            validate: true,
+            ..UploadOptions::default()
+        };
+
+        let result = client.upload_annotations(&annotations, &options).await;
+
+        match result {
+            Err(Error::InvalidAnnotations(invalid)) => {
+                let pointers: Vec<_> = invalid
+                    .iter()
+                    .map(|(i, v)| (*i, v.pointer.as_str()))
+                    .collect();
+                assert_eq!(pointers, [(1, "/@context"), (1, "/target")]);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn dry_run_uploads_report_batches_without_sending() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+            .expect(0)
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+        let annotations: Vec<Value> = (0..5)
+            .map(|i| {
+                json!({
+                    "@context": "http://www.w3.org/ns/anno.jsonld",
+                    "type": "Annotation",
+                    "target": format!("https://example.com/{i}")
+                })
+            })
+            .collect();
+        let updates = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = updates.clone();
+        let options = UploadOptions {
+            batch_size: 2,
+            progress: Some(Arc::new(move |update: ProgressUpdate| {
+                seen.lock().unwrap().push(update.done)
+            })),
+            dry_run: true,
+            ..UploadOptions::default()
+        };
+
+        let identifiers = client
+            .upload_annotations(&annotations, &options)
+            .await
+            .unwrap();
+
+        assert!(identifiers.is_empty());
+        assert_eq!(*updates.lock().unwrap(), [2, 4, 5]);
+
+        let cancellation = CancellationToken::new();
+        let cancel = cancellation.clone();
+        let seen = updates.clone();
+        seen.lock().unwrap().clear();
+        let options = UploadOptions {
+            progress: Some(Arc::new(move |update: ProgressUpdate| {
+                seen.lock().unwrap().push(update.done);
+                cancel.cancel();
+            })),
+            cancellation: Some(cancellation),
+            ..options
+        };
+
+        client
+            .upload_annotations(&annotations, &options)
+            .await
+            .unwrap();
+
+        assert_eq!(*updates.lock().unwrap(), [2]);
+    }
+}
@@ -0,0 +1,416 @@
+use crate::{
+    AboutInfo, AnnoRepoClient, Annotation, AnnotationIdentifier, AnnotationStatus, Capabilities,
+    Checksums, ContainerMetadata, CreatedAnnotation, DistinctValue, Error, Health, IndexConfig,
+    IndexInfo, IndexStatus, IndexType, Negotiated, Query, SavedQuery, ServerSettings,
+    ServerVersion, Sort, UploadOptions, UpsertOutcome, UserAddResults, UserEntry, VerifyReport,
+};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// The operations of [`AnnoRepoClient`], as a trait, so code using the
+/// client can be tested against a fake implementation instead of a server.
+///
+/// Methods that return values borrowing the client, like
+/// [`AnnoRepoClient::search`], are replaced by ones returning plain data,
+/// like [`create_search_id`](Self::create_search_id). Those that stream, like
+/// [`AnnoRepoClient::modified_since`] and the exports, have no counterpart:
+/// their results can be read page by page with
+/// [`read_search_result_page`](Self::read_search_result_page). Neither does
+/// [`AnnoRepoClient::update_by_query`], which runs a closure while it
+/// streams. Methods generic over the annotation type take [`Value`]s here.
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+pub trait AnnoRepoApi: MaybeSendSync {
+    async fn get_about(&self) -> Result<AboutInfo, Error>;
+
+    async fn ping(&self, timeout: Duration) -> Result<Duration, Error>;
+
+    async fn healthy(&self, timeout: Duration) -> Health;
+
+    async fn check_server_version(&self) -> Result<ServerVersion, Error>;
+
+    async fn capabilities(&self) -> Result<Capabilities, Error> {
+        Ok(Capabilities::from(&self.get_about().await?))
+    }
+
+    async fn create_container(&self, label: Option<&str>) -> Result<String, Error>;
+
+    async fn get_container_metadata(&self) -> Result<ContainerMetadata, Error>;
+
+    async fn get_fields(&self) -> Result<HashMap<String, u64>, Error>;
+
+    async fn get_field_names(&self) -> Result<Vec<String>, Error> {
+        let mut names: Vec<String> = self.get_fields().await?.into_keys().collect();
+        names.sort();
+
+        Ok(names)
+    }
+
+    async fn get_distinct_values(&self, field: &str) -> Result<Value, Error>;
+
+    async fn get_distinct_value_counts(&self, field: &str) -> Result<Vec<DistinctValue>, Error>;
+
+    async fn read_container_page(&self, page: u32) -> Result<Value, Error>;
+
+    /// Create a search, returning its id.
+    async fn create_search_id(&self, query: &Query) -> Result<String, Error>;
+
+    /// [`create_search_id`](Self::create_search_id) for
+    /// [`AnnoRepoClient::search_sorted`].
+    async fn create_sorted_search_id(&self, query: &Query, sort: &[Sort]) -> Result<String, Error>;
+
+    /// [`create_search_id`](Self::create_search_id) for
+    /// [`AnnoRepoClient::search_saved`].
+    async fn create_saved_search_id(&self, saved: &SavedQuery) -> Result<String, Error> {
+        self.create_search_id(&saved.query).await
+    }
+
+    async fn unknown_query_fields(&self, query: &Query) -> Result<Vec<String>, Error>;
+
+    async fn create_custom_query(&self, saved: &SavedQuery) -> Result<String, Error>;
+
+    async fn read_search_info(&self, container_name: &str, search_id: &str)
+        -> Result<Value, Error>;
+
+    async fn read_search_result_page(
+        &self,
+        container_name: &str,
+        search_id: &str,
+        page: Option<u32>,
+    ) -> Result<Value, Error>;
+
+    async fn get_annotation(&self, annotation_name: &str) -> Result<Annotation, Error>;
+
+    /// [`get_annotation`](Self::get_annotation) for each of `names`, in the
+    /// same order; by default one after the other.
+    async fn get_annotations(&self, names: &[&str]) -> Vec<Result<Annotation, Error>> {
+        let mut annotations = Vec::with_capacity(names.len());
+        for name in names {
+            annotations.push(self.get_annotation(name).await);
+        }
+        annotations
+    }
+
+    async fn get_annotations_by_url(&self, urls: &[&str]) -> Vec<Result<Annotation, Error>>;
+
+    async fn get_annotation_as(
+        &self,
+        annotation_name: &str,
+        accept: &str,
+    ) -> Result<Negotiated<Value>, Error>;
+
+    async fn annotation_exists(&self, annotation_name: &str) -> Result<AnnotationStatus, Error>;
+
+    async fn create_annotation(
+        &self,
+        annotation: &Value,
+        slug: Option<&str>,
+    ) -> Result<CreatedAnnotation, Error>;
+
+    async fn create_annotation_idempotent(
+        &self,
+        annotation: &Value,
+        key: &str,
+    ) -> Result<CreatedAnnotation, Error>;
+
+    async fn upsert_annotation(
+        &self,
+        annotation_name: &str,
+        annotation: &Value,
+    ) -> Result<UpsertOutcome, Error>;
+
+    async fn verify(
+        &self,
+        checksums: &Checksums,
+        sample: Option<usize>,
+    ) -> Result<VerifyReport, Error>;
+
+    async fn add_annotations(
+        &self,
+        annotations: &[Value],
+    ) -> Result<Vec<AnnotationIdentifier>, Error>;
+
+    async fn upload_annotations(
+        &self,
+        annotations: &[Value],
+        options: &UploadOptions,
+    ) -> Result<Vec<AnnotationIdentifier>, Error>;
+
+    async fn get_indexes(&self) -> Result<Vec<IndexInfo>, Error>;
+
+    async fn list_indexes(&self) -> Result<Vec<IndexConfig>, Error>;
+
+    async fn create_index(&self, field: &str, index_type: IndexType) -> Result<String, Error>;
+
+    async fn delete_index(&self, field: &str, index_type: IndexType) -> Result<(), Error>;
+
+    async fn index_status(&self, field: &str, index_type: IndexType) -> Result<IndexStatus, Error>;
+
+    async fn list_users(&self) -> Result<Vec<UserEntry>, Error>;
+
+    async fn create_users(&self, users: &[UserEntry]) -> Result<UserAddResults, Error>;
+
+    async fn delete_user(&self, user_name: &str) -> Result<(), Error>;
+
+    async fn get_server_settings(&self) -> Result<ServerSettings, Error>;
+
+    async fn update_server_settings(
+        &self,
+        settings: &ServerSettings,
+    ) -> Result<ServerSettings, Error>;
+}
+
+/// `Send + Sync`, except on wasm32, where nothing needs to cross threads.
+#[cfg(not(target_arch = "wasm32"))]
+pub trait MaybeSendSync: Send + Sync {}
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: Send + Sync + ?Sized> MaybeSendSync for T {}
+#[cfg(target_arch = "wasm32")]
+pub trait MaybeSendSync {}
+#[cfg(target_arch = "wasm32")]
+impl<T: ?Sized> MaybeSendSync for T {}
+
+#[cfg_attr(not(target_arch = "wasm32"), async_trait)]
+#[cfg_attr(target_arch = "wasm32", async_trait(?Send))]
+impl AnnoRepoApi for AnnoRepoClient {
+    async fn get_about(&self) -> Result<AboutInfo, Error> {
+        AnnoRepoClient::get_about(self).await
+    }
+
+    async fn ping(&self, timeout: Duration) -> Result<Duration, Error> {
+        AnnoRepoClient::ping(self, timeout).await
+    }
+
+    async fn healthy(&self, timeout: Duration) -> Health {
+        AnnoRepoClient::healthy(self, timeout).await
+    }
+
+    async fn check_server_version(&self) -> Result<ServerVersion, Error> {
+        AnnoRepoClient::check_server_version(self).await
+    }
+
+    async fn capabilities(&self) -> Result<Capabilities, Error> {
+        AnnoRepoClient::capabilities(self).await
+    }
+
+    async fn create_container(&self, label: Option<&str>) -> Result<String, Error> {
+        AnnoRepoClient::create_container(self, label).await
+    }
+
+    async fn get_container_metadata(&self) -> Result<ContainerMetadata, Error> {
+        AnnoRepoClient::get_container_metadata(self).await
+    }
+
+    async fn get_fields(&self) -> Result<HashMap<String, u64>, Error> {
+        AnnoRepoClient::get_fields(self).await
+    }
+
+    async fn get_distinct_values(&self, field: &str) -> Result<Value, Error> {
+        AnnoRepoClient::get_distinct_values(self, field).await
+    }
+
+    async fn get_distinct_value_counts(&self, field: &str) -> Result<Vec<DistinctValue>, Error> {
+        AnnoRepoClient::get_distinct_value_counts(self, field).await
+    }
+
+    async fn read_container_page(&self, page: u32) -> Result<Value, Error> {
+        AnnoRepoClient::read_container_page(self, page).await
+    }
+
+    async fn create_search_id(&self, query: &Query) -> Result<String, Error> {
+        let search = AnnoRepoClient::search(self, query).await?;
+
+        Ok(search.search_id().clone())
+    }
+
+    async fn create_sorted_search_id(&self, query: &Query, sort: &[Sort]) -> Result<String, Error> {
+        let search = AnnoRepoClient::search_sorted(self, query, sort).await?;
+
+        Ok(search.search_id().clone())
+    }
+
+    async fn unknown_query_fields(&self, query: &Query) -> Result<Vec<String>, Error> {
+        AnnoRepoClient::unknown_query_fields(self, query).await
+    }
+
+    async fn create_custom_query(&self, saved: &SavedQuery) -> Result<String, Error> {
+        AnnoRepoClient::create_custom_query(self, saved).await
+    }
+
+    async fn read_search_info(
+        &self,
+        container_name: &str,
+        search_id: &str,
+    ) -> Result<Value, Error> {
+        AnnoRepoClient::read_search_info(self, container_name, search_id).await
+    }
+
+    async fn read_search_result_page(
+        &self,
+        container_name: &str,
+        search_id: &str,
+        page: Option<u32>,
+    ) -> Result<Value, Error> {
+        AnnoRepoClient::read_search_result_page(self, container_name, search_id, page).await
+    }
+
+    async fn get_annotation(&self, annotation_name: &str) -> Result<Annotation, Error> {
+        AnnoRepoClient::get_annotation(self, annotation_name).await
+    }
+
+    async fn get_annotations(&self, names: &[&str]) -> Vec<Result<Annotation, Error>> {
+        AnnoRepoClient::get_annotations(self, names).await
+    }
+
+    async fn get_annotations_by_url(&self, urls: &[&str]) -> Vec<Result<Annotation, Error>> {
+        AnnoRepoClient::get_annotations_by_url(self, urls).await
+    }
+
+    async fn get_annotation_as(
+        &self,
+        annotation_name: &str,
+        accept: &str,
+    ) -> Result<Negotiated<Value>, Error> {
+        AnnoRepoClient::get_annotation_as(self, annotation_name, accept).await
+    }
+
+    async fn annotation_exists(&self, annotation_name: &str) -> Result<AnnotationStatus, Error> {
+        AnnoRepoClient::annotation_exists(self, annotation_name).await
+    }
+
+    async fn create_annotation(
+        &self,
+        annotation: &Value,
+        slug: Option<&str>,
+    ) -> Result<CreatedAnnotation, Error> {
+        AnnoRepoClient::create_annotation(self, annotation, slug).await
+    }
+
+    async fn upsert_annotation(
+        &self,
+        annotation_name: &str,
+        annotation: &Value,
+    ) -> Result<UpsertOutcome, Error> {
+        AnnoRepoClient::upsert_annotation(self, annotation_name, annotation).await
+    }
+
+    async fn create_annotation_idempotent(
+        &self,
+        annotation: &Value,
+        key: &str,
+    ) -> Result<CreatedAnnotation, Error> {
+        AnnoRepoClient::create_annotation_idempotent(self, annotation, key).await
+    }
+
+    async fn verify(
+        &self,
+        checksums: &Checksums,
+        sample: Option<usize>,
+    ) -> Result<VerifyReport, Error> {
+        AnnoRepoClient::verify(self, checksums, sample).await
+    }
+
+    async fn add_annotations(
+        &self,
+        annotations: &[Value],
+    ) -> Result<Vec<AnnotationIdentifier>, Error> {
+        AnnoRepoClient::add_annotations(self, annotations).await
+    }
+
+    async fn upload_annotations(
+        &self,
+        annotations: &[Value],
+        options: &UploadOptions,
+    ) -> Result<Vec<AnnotationIdentifier>, Error> {
+        AnnoRepoClient::upload_annotations(self, annotations, options).await
+    }
+
+    async fn get_indexes(&self) -> Result<Vec<IndexInfo>, Error> {
+        AnnoRepoClient::get_indexes(self).await
+    }
+
+    async fn list_indexes(&self) -> Result<Vec<IndexConfig>, Error> {
+        AnnoRepoClient::list_indexes(self).await
+    }
+
+    async fn create_index(&self, field: &str, index_type: IndexType) -> Result<String, Error> {
+        AnnoRepoClient::create_index(self, field, index_type).await
+    }
+
+    async fn delete_index(&self, field: &str, index_type: IndexType) -> Result<(), Error> {
+        AnnoRepoClient::delete_index(self, field, index_type).await
+    }
+
+    async fn index_status(&self, field: &str, index_type: IndexType) -> Result<IndexStatus, Error> {
+        AnnoRepoClient::index_status(self, field, index_type).await
+    }
+
+    async fn list_users(&self) -> Result<Vec<UserEntry>, Error> {
+        AnnoRepoClient::list_users(self).await
+    }
+
+    async fn create_users(&self, users: &[UserEntry]) -> Result<UserAddResults, Error> {
+        AnnoRepoClient::create_users(self, users).await
+    }
+
+    async fn delete_user(&self, user_name: &str) -> Result<(), Error> {
+        AnnoRepoClient::delete_user(self, user_name).await
+    }
+
+    async fn get_server_settings(&self) -> Result<ServerSettings, Error> {
+        AnnoRepoClient::get_server_settings(self).await
+    }
+
+    async fn update_server_settings(
+        &self,
+        settings: &ServerSettings,
+    ) -> Result<ServerSettings, Error> {
+        AnnoRepoClient::update_server_settings(self, settings).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn field_count(api: &dyn AnnoRepoApi) -> Result<usize, Error> {
+        Ok(api.get_field_names().await?.len())
+    }
+
+    #[tokio::test]
+    async fn client_can_be_used_through_the_trait() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/fields"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({"body.type": 3, "target": 3})),
+            )
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        assert_eq!(field_count(&client).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn annotations_can_be_checked_through_the_trait() {
+        let server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .and(path("/w3c/c/a1"))
+            .respond_with(ResponseTemplate::new(200).insert_header("etag", "\"1\""))
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+        let api: &dyn AnnoRepoApi = &client;
+
+        let status = api.annotation_exists("a1").await.unwrap();
+
+        assert!(status.exists);
+        assert_eq!(status.etag.as_deref(), Some("\"1\""));
+    }
+}
@@ -0,0 +1,429 @@
+use crate::sync::{annotation_name, strip_id};
+use crate::{AnnoRepoClient, ContainerMetadata, Error, IndexInfo, UploadOptions};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use futures::{StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// The version of the backup format written by [`AnnoRepoClient::backup`].
+pub const BACKUP_FORMAT: u32 = 1;
+
+/// The first line of a backup: everything about the container besides its
+/// annotations, which follow one per line.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupHeader {
+    /// [`BACKUP_FORMAT`] at the time of writing.
+    pub annorepo_backup: u32,
+    pub container: String,
+    /// Includes settings such as `isReadOnlyForAnonymousUsers` among its
+    /// extra fields.
+    pub metadata: ContainerMetadata,
+    pub indexes: Vec<IndexInfo>,
+    /// The container's users and their roles, as the server lists them;
+    /// absent when the API key may not see them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub users: Option<Value>,
+}
+
+/// How long [`AnnoRepoClient::restore`] waits for each index to be built.
+const INDEX_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// The container setting kept in [`ContainerMetadata::extra`] that a restore
+/// sets again.
+const READ_ONLY_SETTING: &str = "isReadOnlyForAnonymousUsers";
+
+/// How [`AnnoRepoClient::restore`] recreates a container.
+#[derive(Debug, Clone, Default)]
+pub struct RestoreOptions {
+    /// Ask for every annotation's name from the backup with a `Slug`, one
+    /// request each, instead of uploading the annotations in batches under
+    /// new names.
+    pub preserve_names: bool,
+    /// How the annotations are uploaded; with `preserve_names`, only the
+    /// concurrency applies. A [`dry_run`](UploadOptions::dry_run) fails with
+    /// [`Error::InvalidOptions`], as the rest of a restore isn't one.
+    pub upload: UploadOptions,
+}
+
+/// What a restore did.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RestoreReport {
+    pub annotations: usize,
+    pub indexes: usize,
+    /// With [`preserve_names`](RestoreOptions::preserve_names), the
+    /// annotations the server gave another name.
+    pub renamed: usize,
+}
+
+impl AnnoRepoClient {
+    /// Write the container's metadata, indexes, users and annotations to a
+    /// gzip-compressed file at `path`, returning the number of annotations.
+    ///
+    /// The file holds newline-delimited JSON: a [`BackupHeader`], then the
+    /// annotations as the server returns them.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container)))]
+    pub async fn backup<P: AsRef<Path>>(&self, path: P) -> Result<usize, Error> {
+        let file = File::create(path).map_err(Error::Io)?;
+        let mut encoder = GzEncoder::new(BufWriter::new(file), Compression::default());
+        let count = self.backup_to(&mut encoder).await?;
+        encoder
+            .finish()
+            .map_err(Error::Io)?
+            .flush()
+            .map_err(Error::Io)?;
+
+        Ok(count)
+    }
+
+    /// [`backup`](Self::backup) to `writer`, uncompressed.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container)))]
+    pub async fn backup_to<W: Write>(&self, mut writer: W) -> Result<usize, Error> {
+        let mut indexes = self.get_indexes().await?;
+        for index in &mut indexes {
+            index.status = None;
+        }
+        let header = BackupHeader {
+            annorepo_backup: BACKUP_FORMAT,
+            container: self.container.to_string(),
+            metadata: self.get_container_metadata().await?,
+            indexes,
+            users: self.container_users().await?,
+        };
+        write_line(&mut writer, &header)?;
+
+        let mut annotations = self.all_annotations().await?;
+        let mut count = 0;
+        while let Some(annotation) = annotations.try_next().await? {
+            write_line(&mut writer, &annotation)?;
+            count += 1;
+        }
+        writer.flush().map_err(Error::Io)?;
+
+        Ok(count)
+    }
+
+    /// Recreate the container backed up in the file at `path` as
+    /// `target_container` on this client's server: its settings, users,
+    /// annotations and indexes. The container must not exist yet.
+    ///
+    /// Afterwards the number of annotations in the container is checked
+    /// against the backup, failing with [`Error::RestoreMismatch`].
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(target_container))
+    )]
+    pub async fn restore<P: AsRef<Path>>(
+        &self,
+        path: P,
+        target_container: &str,
+        options: &RestoreOptions,
+    ) -> Result<RestoreReport, Error> {
+        let file = File::open(path).map_err(Error::Io)?;
+        let reader = BufReader::new(GzDecoder::new(file));
+
+        self.restore_from(reader, target_container, options).await
+    }
+
+    /// [`restore`](Self::restore) from an uncompressed `reader`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(target_container))
+    )]
+    pub async fn restore_from<R: BufRead>(
+        &self,
+        reader: R,
+        target_container: &str,
+        options: &RestoreOptions,
+    ) -> Result<RestoreReport, Error> {
+        if options.upload.dry_run {
+            return Err(Error::InvalidOptions(
+                "a restore can't be a dry run".to_string(),
+            ));
+        }
+        let mut lines = reader.lines();
+        let header = match lines.next() {
+            Some(line) => line.map_err(Error::Io)?,
+            None => return Err(Error::InvalidBackup("the file is empty".to_string())),
+        };
+        let header: BackupHeader = serde_json::from_str(&header)
+            .map_err(|e| Error::InvalidBackup(format!("invalid header: {e}")))?;
+        if header.annorepo_backup > BACKUP_FORMAT {
+            return Err(Error::InvalidBackup(format!(
+                "format {} is newer than this client's {BACKUP_FORMAT}",
+                header.annorepo_backup
+            )));
+        }
+
+        let target = self.with_container(target_container);
+        target
+            .create_container(header.metadata.label.as_deref())
+            .await?;
+        if let Some(Value::Bool(read_only)) = header.metadata.extra.get(READ_ONLY_SETTING) {
+            let url = target.resolve_service_param("settings", READ_ONLY_SETTING);
+            target.send(target.client.put(url).json(read_only)).await?;
+        }
+        if let Some(users) = &header.users {
+            let url = target.resolve_service("users");
+            target.send(target.client.post(url).json(users)).await?;
+        }
+
+        let mut report = RestoreReport::default();
+        let chunk_size = options.upload.batch_size.max(1) * options.upload.concurrency.max(1);
+        let mut chunk = Vec::with_capacity(chunk_size);
+        // The header is line 1.
+        for (index, line) in lines.enumerate() {
+            let line = line.map_err(Error::Io)?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let annotation =
+                serde_json::from_str(&line).map_err(|source| Error::InvalidRecord {
+                    line: index + 2,
+                    source,
+                })?;
+            chunk.push(annotation);
+            if chunk.len() == chunk_size {
+                target
+                    .restore_chunk(std::mem::take(&mut chunk), options, &mut report)
+                    .await?;
+            }
+        }
+        if !chunk.is_empty() {
+            target.restore_chunk(chunk, options, &mut report).await?;
+        }
+
+        // Indexes are built once, after the annotations are in.
+        for index in &header.indexes {
+            target
+                .create_index(&index.field, index.index_type.clone())
+                .await?;
+            target
+                .wait_for_index(&index.field, index.index_type.clone(), INDEX_TIMEOUT)
+                .await?;
+            report.indexes += 1;
+        }
+
+        let found = target.get_container_metadata().await?.annotation_count;
+        if found != report.annotations as u64 {
+            return Err(Error::RestoreMismatch {
+                expected: report.annotations as u64,
+                found,
+            });
+        }
+
+        Ok(report)
+    }
+
+    async fn restore_chunk(
+        &self,
+        mut chunk: Vec<Value>,
+        options: &RestoreOptions,
+        report: &mut RestoreReport,
+    ) -> Result<(), Error> {
+        if !options.preserve_names {
+            chunk.iter_mut().for_each(strip_id);
+            let identifiers = self.upload_annotations(&chunk, &options.upload).await?;
+            report.annotations += identifiers.len();
+            return Ok(());
+        }
+        let created: Vec<bool> = futures::stream::iter(chunk)
+            .map(|mut annotation| async move {
+                let name = annotation_name(&annotation)?;
+                strip_id(&mut annotation);
+                let created = self.create_annotation(&annotation, Some(&name)).await?;
+                Ok::<_, Error>(created.slug_honored)
+            })
+            .buffered(options.upload.concurrency.max(1))
+            .try_collect()
+            .await?;
+        report.annotations += created.len();
+        report.renamed += created.iter().filter(|&&honored| !honored).count();
+
+        Ok(())
+    }
+
+    async fn container_users(&self) -> Result<Option<Value>, Error> {
+        let url = self.resolve_service("users");
+
+        match self.send(self.client.get(url)).await {
+            Ok(res) => res.json().await.map(Some),
+            Err(Error::Unauthorized(_) | Error::Forbidden(_) | Error::NotFound(_)) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+fn write_line<W: Write, T: Serialize>(writer: &mut W, value: &T) -> Result<(), Error> {
+    serde_json::to_writer(&mut *writer, value).map_err(|e| Error::Io(e.into()))?;
+    writer.write_all(b"\n").map_err(Error::Io)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{body_json, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn backups_start_with_the_container_header() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/metadata"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "size": 1,
+                "isReadOnlyForAnonymousUsers": true
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/indexes"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                {"field": "body.type", "type": "hashed", "status": "done"}
+            ])))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/users"))
+            .respond_with(ResponseTemplate::new(403))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/services/c/search"))
+            .respond_with(
+                ResponseTemplate::new(201)
+                    .insert_header("location", format!("{}/services/c/search/s1", server.uri())),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/search/s1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [{"id": "a1"}]
+            })))
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+        let mut out = Vec::new();
+
+        let count = client.backup_to(&mut out).await.unwrap();
+
+        assert_eq!(count, 1);
+        let lines: Vec<Value> = out
+            .split(|&b| b == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+        assert_eq!(lines[1], json!({"id": "a1"}));
+        let header: BackupHeader = serde_json::from_value(lines[0].clone()).unwrap();
+        assert_eq!(header.annorepo_backup, BACKUP_FORMAT);
+        assert_eq!(header.metadata.extra["isReadOnlyForAnonymousUsers"], true);
+        assert_eq!(header.indexes[0].field, "body.type");
+        assert_eq!(header.indexes[0].status, None);
+        assert_eq!(header.users, None);
+    }
+
+    #[tokio::test]
+    async fn restores_cannot_be_dry_runs() {
+        let server = MockServer::start().await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+        let options = RestoreOptions {
+            upload: UploadOptions {
+                dry_run: true,
+                ..UploadOptions::default()
+            },
+            ..RestoreOptions::default()
+        };
+
+        let result = client.restore_from(&b""[..], "c2", &options).await;
+
+        assert!(matches!(result, Err(Error::InvalidOptions(_))));
+        assert!(server.received_requests().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn restores_keep_names_settings_and_indexes() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/w3c/"))
+            .and(header("slug", "c2"))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/services/c2/settings/isReadOnlyForAnonymousUsers"))
+            .and(body_json(json!(true)))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/w3c/c2/"))
+            .and(header("slug", "a1"))
+            .and(body_json(json!({"type": "Annotation"})))
+            .respond_with(
+                ResponseTemplate::new(201)
+                    .insert_header("location", format!("{}/w3c/c2/a1", server.uri())),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/services/c2/indexes/body.type/hashed"))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/services/c2/indexes/body.type/hashed"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "field": "body.type",
+                "type": "hashed"
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/services/c2/metadata"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"size": 1})))
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+        let backup = [
+            json!({
+                "annorepoBackup": 1,
+                "container": "c",
+                "metadata": {"annotationCount": 1, "isReadOnlyForAnonymousUsers": true},
+                "indexes": [{"field": "body.type", "type": "hashed"}]
+            }),
+            json!({"id": "https://old.example.com/w3c/c/a1", "type": "Annotation"}),
+        ]
+        .map(|line| line.to_string())
+        .join("\n");
+        let options = RestoreOptions {
+            preserve_names: true,
+            ..RestoreOptions::default()
+        };
+
+        let report = client
+            .restore_from(backup.as_bytes(), "c2", &options)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            report,
+            RestoreReport {
+                annotations: 1,
+                indexes: 1,
+                renamed: 0
+            }
+        );
+    }
+}
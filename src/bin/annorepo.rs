@@ -0,0 +1,219 @@
+//! Command line access to an AnnoRepo server.
+
+use annorepo_client::{AnnoRepoClient, Error, Query, TableFormat, UploadOptions};
+use clap::{Parser, Subcommand};
+use futures::TryStreamExt;
+use serde_json::Value;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(Parser)]
+#[command(name = "annorepo", version, about = "Talk to an AnnoRepo server")]
+struct Cli {
+    /// Base URL of the server.
+    #[arg(long, env = "ANNOREPO_URL")]
+    url: String,
+    /// Container to work on.
+    #[arg(short, long, env = "ANNOREPO_CONTAINER")]
+    container: Option<String>,
+    /// API key, for servers with authentication enabled.
+    #[arg(long, env = "ANNOREPO_API_KEY", hide_env_values = true)]
+    api_key: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Show the server information.
+    About,
+    /// List the fields used in the container, with their number of annotations.
+    Fields,
+    /// Search the container and print the results as NDJSON.
+    Search {
+        /// The query, as JSON in the AnnoRepo query language.
+        query: String,
+    },
+    /// Search the container and print the given fields of the results as CSV.
+    Table {
+        /// The query, as JSON in the AnnoRepo query language.
+        query: String,
+        /// Dotted path of a column, like `body.value`; repeat for more columns.
+        #[arg(short, long = "field", required = true)]
+        fields: Vec<String>,
+        /// Separate the columns with tabs instead of commas.
+        #[arg(long)]
+        tsv: bool,
+    },
+    /// Add the annotations in a JSON array or NDJSON file (`-` for stdin).
+    Upload {
+        file: PathBuf,
+        #[arg(long, default_value_t = 100)]
+        batch_size: usize,
+        /// Batches to send at the same time.
+        #[arg(long, default_value_t = 4)]
+        parallel: usize,
+        /// Check the annotations against the Web Annotation model first.
+        #[arg(long)]
+        validate: bool,
+        /// Validate and batch the annotations without sending them.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Write every annotation in the container as NDJSON.
+    Export {
+        /// Output file; stdout when absent.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+impl Command {
+    fn needs_container(&self) -> bool {
+        !matches!(self, Command::About)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+enum CliError {
+    #[error(transparent)]
+    Client(#[from] Error),
+    #[error("invalid query: {0}")]
+    InvalidQuery(#[source] serde_json::Error),
+    #[error("this command needs a container; pass --container or set ANNOREPO_CONTAINER")]
+    MissingContainer,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("annorepo: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), CliError> {
+    let container = match cli.container {
+        Some(container) => container,
+        None if cli.command.needs_container() => return Err(CliError::MissingContainer),
+        None => String::new(),
+    };
+    let mut builder = AnnoRepoClient::builder(cli.url, container);
+    if let Some(api_key) = cli.api_key {
+        builder = builder.api_key(api_key);
+    }
+    let client = builder.build()?;
+    let mut stdout = BufWriter::new(io::stdout().lock());
+
+    match cli.command {
+        Command::About => {
+            let about = client.get_about().await?;
+            print_json(&mut stdout, &about)?;
+        }
+        Command::Fields => {
+            let fields = client.get_fields().await?;
+            for name in client.get_field_names().await? {
+                writeln!(stdout, "{name}\t{}", fields[&name]).map_err(Error::Io)?;
+            }
+        }
+        Command::Search { query } => {
+            let query: Query = serde_json::from_str(&query).map_err(CliError::InvalidQuery)?;
+            let search = client.search(&query).await?;
+            let mut annotations = search.annotations();
+            while let Some(annotation) = annotations.try_next().await? {
+                print_json(&mut stdout, &annotation)?;
+            }
+        }
+        Command::Table { query, fields, tsv } => {
+            let query: Query = serde_json::from_str(&query).map_err(CliError::InvalidQuery)?;
+            let fields: Vec<&str> = fields.iter().map(String::as_str).collect();
+            let format = if tsv {
+                TableFormat::Tsv
+            } else {
+                TableFormat::Csv
+            };
+            client
+                .export_table(&query, &fields, format, &mut stdout)
+                .await?;
+        }
+        Command::Upload {
+            file,
+            batch_size,
+            parallel,
+            validate,
+            dry_run,
+        } => {
+            let annotations = read_annotations(&file)?;
+            let options = UploadOptions {
+                batch_size,
+                concurrency: parallel,
+                validate,
+                dry_run,
+                ..UploadOptions::default()
+            };
+            let identifiers = client.upload_annotations(&annotations, &options).await?;
+            if dry_run {
+                eprintln!(
+                    "Would upload {} annotations in {} batches",
+                    annotations.len(),
+                    annotations.len().div_ceil(batch_size.max(1))
+                );
+            } else {
+                eprintln!("Uploaded {} annotations", identifiers.len());
+            }
+        }
+        Command::Export { output } => {
+            let count = match output {
+                Some(path) => {
+                    let file = File::create(path).map_err(Error::Io)?;
+                    client.export_ndjson(BufWriter::new(file)).await?
+                }
+                None => client.export_ndjson(&mut stdout).await?,
+            };
+            eprintln!("Exported {count} annotations");
+        }
+    }
+
+    stdout.flush().map_err(Error::Io)?;
+    Ok(())
+}
+
+fn print_json<T: serde::Serialize>(out: &mut impl Write, value: &T) -> Result<(), Error> {
+    serde_json::to_writer(&mut *out, value).map_err(|e| Error::Io(e.into()))?;
+    writeln!(out).map_err(Error::Io)
+}
+
+/// Read a JSON array of annotations, or one annotation per line.
+fn read_annotations(file: &PathBuf) -> Result<Vec<Value>, Error> {
+    let mut input = String::new();
+    if file.as_os_str() == "-" {
+        io::stdin().read_to_string(&mut input)
+    } else {
+        File::open(file).and_then(|mut f| f.read_to_string(&mut input))
+    }
+    .map_err(Error::Io)?;
+
+    if input.trim_start().starts_with('[') {
+        return serde_json::from_str(&input).map_err(|source| Error::InvalidRecord {
+            line: source.line(),
+            source,
+        });
+    }
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line).map_err(|source| Error::InvalidRecord {
+                line: index + 1,
+                source,
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,563 @@
+//! A blocking wrapper around [`AnnoRepoClient`] for tools that don't run an
+//! async runtime themselves.
+//!
+//! Like `reqwest::blocking`, it drives the async client on a private
+//! single-threaded runtime, so it must not be used from within async code.
+
+use crate::{
+    AboutInfo, AnnoIter, AnnoRepoClient, AnnoRepoClientBuilder, Annotation, AnnotationIdentifier,
+    AnnotationStatus, AnnotationStream, Capabilities, Checksums, ContainerDiff, ContainerMetadata,
+    ContainerPreference, CreatedAnnotation, DistinctValue, Error, ExportOptions, FieldSchema,
+    Health, IndexConfig, IndexInfo, IndexStatus, IndexType, Links, Negotiated, Query,
+    RestoreOptions, RestoreReport, SavedQuery, SearchInfo, ServerSettings, ServerVersion, Sort,
+    SyncOptions, SyncReport, TableFormat, UpdateOptions, UpdateReport, UploadOptions,
+    UpsertOutcome, UserAddResults, UserEntry, VerifyReport,
+};
+use futures::StreamExt;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+
+/// The blocking counterpart of [`AnnoRepoClient`], with the same methods.
+#[derive(Debug)]
+pub struct AnnoRepoBlockingClient {
+    inner: AnnoRepoClient,
+    runtime: Runtime,
+}
+
+impl AnnoRepoBlockingClient {
+    pub fn new<S: Into<String>>(base_url: S, container: S) -> Result<Self, Error> {
+        AnnoRepoClient::builder(base_url, container).build_blocking()
+    }
+
+    pub fn builder<S: Into<String>>(base_url: S, container: S) -> AnnoRepoClientBuilder {
+        AnnoRepoClient::builder(base_url, container)
+    }
+
+    pub(crate) fn wrap(inner: AnnoRepoClient) -> Result<Self, Error> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(Error::Runtime)?;
+
+        Ok(Self { inner, runtime })
+    }
+
+    /// The async client this one wraps.
+    pub fn as_async(&self) -> &AnnoRepoClient {
+        &self.inner
+    }
+
+    pub fn get_about(&self) -> Result<AboutInfo, Error> {
+        self.runtime.block_on(self.inner.get_about())
+    }
+
+    pub fn ping(&self, timeout: Duration) -> Result<Duration, Error> {
+        self.runtime.block_on(self.inner.ping(timeout))
+    }
+
+    pub fn healthy(&self, timeout: Duration) -> Health {
+        self.runtime.block_on(self.inner.healthy(timeout))
+    }
+
+    pub fn capabilities(&self) -> Result<Capabilities, Error> {
+        self.runtime.block_on(self.inner.capabilities())
+    }
+
+    pub fn check_server_version(&self) -> Result<ServerVersion, Error> {
+        self.runtime.block_on(self.inner.check_server_version())
+    }
+
+    pub fn create_container(&self, label: Option<&str>) -> Result<String, Error> {
+        self.runtime.block_on(self.inner.create_container(label))
+    }
+
+    pub fn get_container_metadata(&self) -> Result<ContainerMetadata, Error> {
+        self.runtime.block_on(self.inner.get_container_metadata())
+    }
+
+    pub fn read_container_page(&self, page: u32) -> Result<Value, Error> {
+        self.runtime.block_on(self.inner.read_container_page(page))
+    }
+
+    pub fn read_container_page_with(
+        &self,
+        page: u32,
+        preference: ContainerPreference,
+    ) -> Result<Value, Error> {
+        self.runtime
+            .block_on(self.inner.read_container_page_with(page, preference))
+    }
+
+    pub fn get_container_links(&self) -> Result<Links, Error> {
+        self.runtime.block_on(self.inner.get_container_links())
+    }
+
+    pub fn get_annotation_links(&self, annotation_name: &str) -> Result<Links, Error> {
+        self.runtime
+            .block_on(self.inner.get_annotation_links(annotation_name))
+    }
+
+    pub fn get_fields(&self) -> Result<HashMap<String, u64>, Error> {
+        self.runtime.block_on(self.inner.get_fields())
+    }
+
+    pub fn get_field_names(&self) -> Result<Vec<String>, Error> {
+        self.runtime.block_on(self.inner.get_field_names())
+    }
+
+    pub fn get_distinct_values(&self, field: &str) -> Result<Value, Error> {
+        self.runtime.block_on(self.inner.get_distinct_values(field))
+    }
+
+    pub fn get_distinct_value_counts(&self, field: &str) -> Result<Vec<DistinctValue>, Error> {
+        self.runtime
+            .block_on(self.inner.get_distinct_value_counts(field))
+    }
+
+    pub fn infer_schema(&self, sample_size: usize) -> Result<FieldSchema, Error> {
+        self.runtime.block_on(self.inner.infer_schema(sample_size))
+    }
+
+    pub fn create_search(&self, query: HashMap<&str, &str>) -> Result<SearchInfo<'_>, Error> {
+        self.runtime.block_on(self.inner.create_search(query))
+    }
+
+    pub fn search(&self, query: &Query) -> Result<SearchInfo<'_>, Error> {
+        self.runtime.block_on(self.inner.search(query))
+    }
+
+    pub fn unknown_query_fields(&self, query: &Query) -> Result<Vec<String>, Error> {
+        self.runtime
+            .block_on(self.inner.unknown_query_fields(query))
+    }
+
+    pub fn search_saved(&self, saved: &SavedQuery) -> Result<SearchInfo<'_>, Error> {
+        self.runtime.block_on(self.inner.search_saved(saved))
+    }
+
+    pub fn create_custom_query(&self, saved: &SavedQuery) -> Result<String, Error> {
+        self.runtime.block_on(self.inner.create_custom_query(saved))
+    }
+
+    pub fn search_sorted(&self, query: &Query, sort: &[Sort]) -> Result<SearchInfo<'_>, Error> {
+        self.runtime.block_on(self.inner.search_sorted(query, sort))
+    }
+
+    pub fn read_search_info(&self, container_name: &str, search_id: &str) -> Result<Value, Error> {
+        self.runtime
+            .block_on(self.inner.read_search_info(container_name, search_id))
+    }
+
+    pub fn read_search_result_page(
+        &self,
+        container_name: &str,
+        search_id: &str,
+        page: Option<u32>,
+    ) -> Result<Value, Error> {
+        self.runtime.block_on(
+            self.inner
+                .read_search_result_page(container_name, search_id, page),
+        )
+    }
+
+    pub fn read_search_result_annotations(
+        &self,
+        container_name: &str,
+        search_id: &str,
+        start_page: Option<u32>,
+    ) -> Result<AnnoIter<'_>, Error> {
+        self.runtime
+            .block_on(self.inner.read_search_result_annotations(
+                container_name,
+                search_id,
+                start_page,
+            ))
+    }
+
+    /// The blocking counterpart of
+    /// [`AnnoRepoClient::stream_search_result_annotations`].
+    pub fn search_result_annotations(
+        &self,
+        container_name: &str,
+        search_id: &str,
+        start_page: Option<u32>,
+    ) -> BlockingAnnotations<'_> {
+        BlockingAnnotations {
+            runtime: &self.runtime,
+            stream: self.inner.stream_search_result_annotations(
+                container_name,
+                search_id,
+                start_page,
+            ),
+        }
+    }
+
+    /// The blocking counterpart of
+    /// [`AnnoRepoClient::stream_container_annotations`].
+    pub fn container_annotations(&self, start_page: Option<u32>) -> BlockingAnnotations<'_> {
+        BlockingAnnotations {
+            runtime: &self.runtime,
+            stream: self.inner.stream_container_annotations(start_page),
+        }
+    }
+
+    /// The blocking counterpart of [`AnnoRepoClient::stream_container_iris`].
+    pub fn container_iris(&self, start_page: Option<u32>) -> BlockingAnnotations<'_> {
+        BlockingAnnotations {
+            runtime: &self.runtime,
+            stream: self.inner.stream_container_iris(start_page),
+        }
+    }
+
+    /// The blocking counterpart of [`AnnoRepoClient::all_annotations`].
+    pub fn all_annotations(&self) -> Result<BlockingAnnotations<'_>, Error> {
+        Ok(BlockingAnnotations {
+            runtime: &self.runtime,
+            stream: self.runtime.block_on(self.inner.all_annotations())?,
+        })
+    }
+
+    /// The blocking counterpart of [`AnnoRepoClient::modified_since`].
+    pub fn modified_since(&self, since: &str) -> Result<BlockingAnnotations<'_>, Error> {
+        Ok(BlockingAnnotations {
+            runtime: &self.runtime,
+            stream: self.runtime.block_on(self.inner.modified_since(since))?,
+        })
+    }
+
+    pub fn backup<P: AsRef<Path>>(&self, path: P) -> Result<usize, Error> {
+        self.runtime.block_on(self.inner.backup(path))
+    }
+
+    pub fn backup_to<W: Write>(&self, writer: W) -> Result<usize, Error> {
+        self.runtime.block_on(self.inner.backup_to(writer))
+    }
+
+    pub fn restore<P: AsRef<Path>>(
+        &self,
+        path: P,
+        target_container: &str,
+        options: &RestoreOptions,
+    ) -> Result<RestoreReport, Error> {
+        self.runtime
+            .block_on(self.inner.restore(path, target_container, options))
+    }
+
+    pub fn restore_from<R: BufRead>(
+        &self,
+        reader: R,
+        target_container: &str,
+        options: &RestoreOptions,
+    ) -> Result<RestoreReport, Error> {
+        self.runtime
+            .block_on(self.inner.restore_from(reader, target_container, options))
+    }
+
+    pub fn export_ndjson_checksummed<W: Write>(&self, writer: W) -> Result<Checksums, Error> {
+        self.runtime
+            .block_on(self.inner.export_ndjson_checksummed(writer))
+    }
+
+    pub fn verify(
+        &self,
+        checksums: &Checksums,
+        sample: Option<usize>,
+    ) -> Result<VerifyReport, Error> {
+        self.runtime.block_on(self.inner.verify(checksums, sample))
+    }
+
+    pub fn export_ndjson<W: Write>(&self, writer: W) -> Result<usize, Error> {
+        self.runtime.block_on(self.inner.export_ndjson(writer))
+    }
+
+    pub fn export_ndjson_with<W: Write>(
+        &self,
+        writer: W,
+        options: &ExportOptions,
+    ) -> Result<usize, Error> {
+        self.runtime
+            .block_on(self.inner.export_ndjson_with(writer, options))
+    }
+
+    pub fn export_table<W: Write>(
+        &self,
+        query: &Query,
+        fields: &[&str],
+        format: TableFormat,
+        writer: W,
+    ) -> Result<usize, Error> {
+        self.runtime
+            .block_on(self.inner.export_table(query, fields, format, writer))
+    }
+
+    pub fn export_iiif<F>(&self, page_id: F) -> Result<BTreeMap<String, Value>, Error>
+    where
+        F: Fn(&str) -> String,
+    {
+        self.runtime.block_on(self.inner.export_iiif(page_id))
+    }
+
+    pub fn import_ndjson<R: BufRead>(
+        &self,
+        reader: R,
+        options: &UploadOptions,
+    ) -> Result<usize, Error> {
+        self.runtime
+            .block_on(self.inner.import_ndjson(reader, options))
+    }
+
+    pub fn get_annotation(&self, annotation_name: &str) -> Result<Annotation, Error> {
+        self.runtime
+            .block_on(self.inner.get_annotation(annotation_name))
+    }
+
+    pub fn get_annotation_as(
+        &self,
+        annotation_name: &str,
+        accept: &str,
+    ) -> Result<Negotiated<Value>, Error> {
+        self.runtime
+            .block_on(self.inner.get_annotation_as(annotation_name, accept))
+    }
+
+    pub fn get_annotations_by_url(&self, urls: &[&str]) -> Vec<Result<Annotation, Error>> {
+        self.runtime
+            .block_on(self.inner.get_annotations_by_url(urls))
+    }
+
+    pub fn get_annotations(&self, names: &[&str]) -> Vec<Result<Annotation, Error>> {
+        self.runtime.block_on(self.inner.get_annotations(names))
+    }
+
+    pub fn annotation_exists(&self, annotation_name: &str) -> Result<AnnotationStatus, Error> {
+        self.runtime
+            .block_on(self.inner.annotation_exists(annotation_name))
+    }
+
+    pub fn create_annotation<T: Serialize>(
+        &self,
+        annotation: &T,
+        slug: Option<&str>,
+    ) -> Result<CreatedAnnotation, Error> {
+        self.runtime
+            .block_on(self.inner.create_annotation(annotation, slug))
+    }
+
+    pub fn upsert_annotation<T: Serialize>(
+        &self,
+        annotation_name: &str,
+        annotation: &T,
+    ) -> Result<UpsertOutcome, Error> {
+        self.runtime
+            .block_on(self.inner.upsert_annotation(annotation_name, annotation))
+    }
+
+    pub fn add_annotations<T: Serialize>(
+        &self,
+        annotations: &[T],
+    ) -> Result<Vec<AnnotationIdentifier>, Error> {
+        self.runtime
+            .block_on(self.inner.add_annotations(annotations))
+    }
+
+    pub fn upload_annotations<T: Serialize + Sync>(
+        &self,
+        annotations: &[T],
+        options: &UploadOptions,
+    ) -> Result<Vec<AnnotationIdentifier>, Error> {
+        self.runtime
+            .block_on(self.inner.upload_annotations(annotations, options))
+    }
+
+    pub fn create_annotation_idempotent<T: Serialize>(
+        &self,
+        annotation: &T,
+        key: &str,
+    ) -> Result<CreatedAnnotation, Error> {
+        self.runtime
+            .block_on(self.inner.create_annotation_idempotent(annotation, key))
+    }
+
+    pub fn create_annotations_idempotent<T, F>(
+        &self,
+        annotations: &[T],
+        key: F,
+        concurrency: usize,
+    ) -> Result<Vec<CreatedAnnotation>, Error>
+    where
+        T: Serialize,
+        F: Fn(&T) -> String,
+    {
+        self.runtime
+            .block_on(
+                self.inner
+                    .create_annotations_idempotent(annotations, key, concurrency),
+            )
+    }
+
+    pub fn upload_annotations_checksummed(
+        &self,
+        annotations: &[Value],
+        options: &UploadOptions,
+    ) -> Result<Checksums, Error> {
+        self.runtime.block_on(
+            self.inner
+                .upload_annotations_checksummed(annotations, options),
+        )
+    }
+
+    pub fn update_by_query<F>(&self, query: &Query, transform: F) -> Result<UpdateReport, Error>
+    where
+        F: Fn(&Value) -> Option<Value>,
+    {
+        self.runtime
+            .block_on(self.inner.update_by_query(query, transform))
+    }
+
+    pub fn update_by_query_with<F>(
+        &self,
+        query: &Query,
+        transform: F,
+        options: &UpdateOptions,
+    ) -> Result<UpdateReport, Error>
+    where
+        F: Fn(&Value) -> Option<Value>,
+    {
+        self.runtime
+            .block_on(self.inner.update_by_query_with(query, transform, options))
+    }
+
+    pub fn sync_to(
+        &self,
+        target: &AnnoRepoBlockingClient,
+        options: &SyncOptions,
+    ) -> Result<SyncReport, Error> {
+        self.runtime
+            .block_on(self.inner.sync_to(&target.inner, options))
+    }
+
+    /// Compare this client's container with that of `other`; see
+    /// [`diff_containers`](crate::diff_containers).
+    pub fn diff_containers(&self, other: &AnnoRepoBlockingClient) -> Result<ContainerDiff, Error> {
+        self.runtime
+            .block_on(crate::diff_containers(&self.inner, &other.inner))
+    }
+
+    pub fn get_indexes(&self) -> Result<Vec<IndexInfo>, Error> {
+        self.runtime.block_on(self.inner.get_indexes())
+    }
+
+    pub fn list_indexes(&self) -> Result<Vec<IndexConfig>, Error> {
+        self.runtime.block_on(self.inner.list_indexes())
+    }
+
+    pub fn create_index(&self, field: &str, index_type: IndexType) -> Result<String, Error> {
+        self.runtime
+            .block_on(self.inner.create_index(field, index_type))
+    }
+
+    pub fn delete_index(&self, field: &str, index_type: IndexType) -> Result<(), Error> {
+        self.runtime
+            .block_on(self.inner.delete_index(field, index_type))
+    }
+
+    pub fn index_status(&self, field: &str, index_type: IndexType) -> Result<IndexStatus, Error> {
+        self.runtime
+            .block_on(self.inner.index_status(field, index_type))
+    }
+
+    pub fn wait_for_index(
+        &self,
+        field: &str,
+        index_type: IndexType,
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        self.runtime
+            .block_on(self.inner.wait_for_index(field, index_type, timeout))
+    }
+
+    pub fn list_users(&self) -> Result<Vec<UserEntry>, Error> {
+        self.runtime.block_on(self.inner.list_users())
+    }
+
+    pub fn create_users(&self, users: &[UserEntry]) -> Result<UserAddResults, Error> {
+        self.runtime.block_on(self.inner.create_users(users))
+    }
+
+    pub fn delete_user(&self, user_name: &str) -> Result<(), Error> {
+        self.runtime.block_on(self.inner.delete_user(user_name))
+    }
+
+    pub fn get_server_settings(&self) -> Result<ServerSettings, Error> {
+        self.runtime.block_on(self.inner.get_server_settings())
+    }
+
+    pub fn update_server_settings(
+        &self,
+        settings: &ServerSettings,
+    ) -> Result<ServerSettings, Error> {
+        self.runtime
+            .block_on(self.inner.update_server_settings(settings))
+    }
+}
+
+/// An iterator over search results that fetches the pages as it goes.
+#[derive(Debug)]
+pub struct BlockingAnnotations<'a> {
+    runtime: &'a Runtime,
+    stream: AnnotationStream<'a>,
+}
+
+impl Iterator for BlockingAnnotations<'_> {
+    type Item = Result<Value, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.runtime.block_on(self.stream.next())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn blocking_client_mirrors_the_async_one() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let server = runtime.block_on(async {
+            let server = MockServer::start().await;
+            Mock::given(method("GET"))
+                .and(path("/services/c/fields"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({"body.type": 3})))
+                .mount(&server)
+                .await;
+            Mock::given(method("GET"))
+                .and(path("/services/c/search/s1"))
+                .and(query_param("page", "0"))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "items": [{"id": "a1"}, {"id": "a2"}]
+                })))
+                .mount(&server)
+                .await;
+            server
+        });
+        let client = AnnoRepoBlockingClient::new(server.uri(), "c".to_string()).unwrap();
+
+        assert_eq!(client.get_field_names().unwrap(), ["body.type"]);
+        let ids: Vec<Value> = client
+            .search_result_annotations("c", "s1", None)
+            .map(|annotation| annotation.unwrap()["id"].take())
+            .collect();
+        assert_eq!(ids, [json!("a1"), json!("a2")]);
+    }
+}
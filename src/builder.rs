@@ -0,0 +1,641 @@
+use crate::cache::{AnnotationCache, EtagCache, MetadataCache};
+use crate::interceptor::Interceptors;
+use crate::retry::CircuitBreaker;
+use crate::{
+    AnnoRepoClient, CircuitBreakerConfig, DeserializationMode, Error, Interceptor, QueryFieldCheck,
+    RetryPolicy, APP_USER_AGENT,
+};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT, AUTHORIZATION};
+use std::fmt;
+#[cfg(not(target_arch = "wasm32"))]
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Builder for [`AnnoRepoClient`], created with [`AnnoRepoClient::builder`].
+#[derive(Debug)]
+pub struct AnnoRepoClientBuilder {
+    base_url: String,
+    fallback_base_urls: Vec<String>,
+    container: String,
+    api_key: Option<ApiKey>,
+    accept: Option<HeaderValue>,
+    user_agent_suffix: Option<String>,
+    interceptors: Interceptors,
+    retry_policy: Option<RetryPolicy>,
+    circuit_breaker: Option<CircuitBreakerConfig>,
+    deserialization_mode: DeserializationMode,
+    query_field_check: QueryFieldCheck,
+    metadata_cache_ttl: Option<Duration>,
+    etag_cache_capacity: Option<usize>,
+    annotation_cache: Option<(usize, Duration)>,
+    gzip_uploads: bool,
+    max_response_size: Option<u64>,
+    request_id_header: Option<HeaderName>,
+    incompatible_server_handler: Option<IncompatibleServerHandler>,
+    #[cfg(all(feature = "wire-log", not(target_arch = "wasm32")))]
+    wire_log: Option<usize>,
+    #[cfg(not(target_arch = "wasm32"))]
+    http: HttpOptions,
+}
+
+impl AnnoRepoClientBuilder {
+    pub(crate) fn new(base_url: String, container: String) -> Self {
+        Self {
+            base_url,
+            fallback_base_urls: Vec::new(),
+            container,
+            api_key: None,
+            accept: None,
+            user_agent_suffix: None,
+            interceptors: Interceptors::default(),
+            retry_policy: None,
+            circuit_breaker: None,
+            deserialization_mode: DeserializationMode::default(),
+            query_field_check: QueryFieldCheck::default(),
+            metadata_cache_ttl: None,
+            etag_cache_capacity: None,
+            annotation_cache: None,
+            gzip_uploads: false,
+            max_response_size: None,
+            request_id_header: None,
+            incompatible_server_handler: None,
+            #[cfg(all(feature = "wire-log", not(target_arch = "wasm32")))]
+            wire_log: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            http: HttpOptions::default(),
+        }
+    }
+
+    /// Read from the server at `base_url`, such as a read-only mirror, when
+    /// the primary can't be reached or its circuit breaker is open. Fallbacks
+    /// are tried in the order they were added, once each, and the primary is
+    /// tried first again for every request.
+    ///
+    /// Only `GET` and `HEAD` requests fail over. Writes, and searches, which
+    /// live on the server that created them, fail with the primary's error.
+    pub fn fallback_base_url<S: Into<String>>(mut self, base_url: S) -> Self {
+        self.fallback_base_urls.push(base_url.into());
+        self
+    }
+
+    /// Authenticate every request with this API key.
+    pub fn api_key<S: Into<String>>(mut self, api_key: S) -> Self {
+        self.api_key = Some(ApiKey(api_key.into()));
+        self
+    }
+
+    /// Send this `Accept` header with every request that doesn't set its own,
+    /// such as [`ANNO_JSONLD`](crate::ANNO_JSONLD).
+    pub fn accept(mut self, media_type: HeaderValue) -> Self {
+        self.accept = Some(media_type);
+        self
+    }
+
+    /// Identify the application in the `User-Agent` header, after the
+    /// client's own product token, e.g. `myharvester/2.3`.
+    pub fn user_agent_suffix<S: Into<String>>(mut self, suffix: S) -> Self {
+        self.user_agent_suffix = Some(suffix.into());
+        self
+    }
+
+    /// Register an interceptor; interceptors run in registration order.
+    pub fn interceptor<I: Interceptor + 'static>(mut self, interceptor: I) -> Self {
+        self.interceptors.push(Arc::new(interceptor));
+        self
+    }
+
+    /// Retry failed requests; see [`RetryPolicy`] for which requests qualify.
+    pub fn retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// Fail fast after repeated server errors instead of piling more load on
+    /// a struggling server.
+    pub fn circuit_breaker(mut self, config: CircuitBreakerConfig) -> Self {
+        self.circuit_breaker = Some(config);
+        self
+    }
+
+    /// Choose between keeping or rejecting undeclared fields in typed responses.
+    pub fn deserialization_mode(mut self, mode: DeserializationMode) -> Self {
+        self.deserialization_mode = mode;
+        self
+    }
+
+    /// Check the fields of every search query against the container's
+    /// fields before creating the search. Costs a `/fields` request per
+    /// search, unless the [metadata cache](Self::metadata_cache_ttl) is on.
+    pub fn query_field_check(mut self, check: QueryFieldCheck) -> Self {
+        self.query_field_check = check;
+        self
+    }
+
+    /// Cache the results of `/about`, `/fields` and `/indexes` for `ttl`.
+    /// The container entries are dropped whenever the client writes to it.
+    pub fn metadata_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.metadata_cache_ttl = Some(ttl);
+        self
+    }
+
+    /// Keep the bodies of up to `capacity` GET responses that came with an
+    /// `ETag`, and revalidate them with `If-None-Match`: when the server
+    /// answers `304 Not Modified`, the kept body is used.
+    pub fn etag_cache(mut self, capacity: usize) -> Self {
+        self.etag_cache_capacity = Some(capacity);
+        self
+    }
+
+    /// Give up on any request that hasn't completed within `timeout`,
+    /// including reading its response body.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.http.timeout = Some(timeout);
+        self
+    }
+
+    /// Give up on connecting to the server after `timeout`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.http.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Send requests through `proxy`, such as
+    /// `Proxy::all("http://proxy.example.org:3128")?.basic_auth(user, password)`.
+    /// `socks5://` proxies need the `socks` feature. Without any, the
+    /// `HTTP_PROXY`, `HTTPS_PROXY` and `NO_PROXY` environment variables are
+    /// followed.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn proxy(mut self, proxy: reqwest::Proxy) -> Self {
+        self.http.proxies.push(proxy);
+        self
+    }
+
+    /// Connect directly, ignoring the proxy environment variables.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn no_proxy(mut self) -> Self {
+        self.http.no_proxy = true;
+        self
+    }
+
+    /// Connect to `addr` for requests to `domain`, instead of looking it up,
+    /// e.g. to reach a server on a docker network under its public name. A port
+    /// in the URL takes precedence over that of `addr`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn resolve<S: Into<String>>(mut self, domain: S, addr: SocketAddr) -> Self {
+        self.http.resolve.push((domain.into(), addr));
+        self
+    }
+
+    /// Connect from this local address, on hosts with more than one.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn local_address(mut self, addr: IpAddr) -> Self {
+        self.http.local_address = Some(addr);
+        self
+    }
+
+    /// Speak HTTP/2 from the first byte, without negotiating it, so plain
+    /// `http://` connections can multiplex too. Only for servers, or
+    /// proxies in front of them, known to accept HTTP/2 without TLS.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn http2_prior_knowledge(mut self) -> Self {
+        self.http.version = HttpVersion::Http2PriorKnowledge;
+        self
+    }
+
+    /// Never use HTTP/2, even when the server offers it over TLS.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn http1_only(mut self) -> Self {
+        self.http.version = HttpVersion::Http1Only;
+        self
+    }
+
+    /// Let HTTP/2 flow control windows grow with the connection's throughput,
+    /// for large result pages on long-distance links.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn http2_adaptive_window(mut self, enabled: bool) -> Self {
+        self.http.adaptive_window = enabled;
+        self
+    }
+
+    /// Ping idle HTTP/2 connections every `interval`, so that ingresses
+    /// don't close them between bursts of requests.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn http2_keep_alive_interval(mut self, interval: Duration) -> Self {
+        self.http.keep_alive_interval = Some(interval);
+        self
+    }
+
+    /// Keep up to `capacity` annotations read with
+    /// [`get_annotation`](AnnoRepoClient::get_annotation) for `ttl`, dropping
+    /// the least recently used ones first. Unlike the
+    /// [ETag cache](Self::etag_cache), hits don't reach the server at all, so
+    /// changes made by others show up only after `ttl`.
+    pub fn annotation_cache(mut self, capacity: usize, ttl: Duration) -> Self {
+        self.annotation_cache = Some((capacity, ttl));
+        self
+    }
+
+    /// Compress the bodies of batch uploads with gzip. Annotations compress
+    /// well, so this pays off whenever upload bandwidth is the bottleneck.
+    pub fn gzip_uploads(mut self, enabled: bool) -> Self {
+        self.gzip_uploads = enabled;
+        self
+    }
+
+    /// Stop reading any response body larger than `bytes`, failing with
+    /// [`Error::ResponseTooLarge`], instead of holding it in memory. Checked
+    /// against `Content-Length` up front, and while the body arrives.
+    pub fn max_response_size(mut self, bytes: u64) -> Self {
+        self.max_response_size = Some(bytes);
+        self
+    }
+
+    /// Send a new ID in header `name` (usually `X-Request-Id`) with every
+    /// call, to match failed requests against the server's logs. Retries reuse
+    /// the ID, and an [`Interceptor`] can set its own. The ID is included in
+    /// errors, through [`RequestContext`](crate::RequestContext), and in
+    /// tracing spans.
+    pub fn request_id_header(mut self, name: HeaderName) -> Self {
+        self.request_id_header = Some(name);
+        self
+    }
+
+    /// Log every request and response at `DEBUG` level, with target
+    /// `annorepo_client::wire`: the request line, the status, the headers,
+    /// and up to `max_body` bytes of the bodies. Credentials in headers are
+    /// redacted; bodies are logged as they are.
+    #[cfg(all(feature = "wire-log", not(target_arch = "wasm32")))]
+    pub fn wire_log(mut self, max_body: usize) -> Self {
+        self.wire_log = Some(max_body);
+        self
+    }
+
+    /// With [`connect`](Self::connect), call `handler` with the
+    /// [`Error::IncompatibleServer`] instead of failing.
+    pub fn on_incompatible_server<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&Error) + Send + Sync + 'static,
+    {
+        self.incompatible_server_handler = Some(IncompatibleServerHandler(Arc::new(handler)));
+        self
+    }
+
+    /// Build the client, then check that it supports the server's version;
+    /// see [`AnnoRepoClient::check_server_version`].
+    pub async fn connect(mut self) -> Result<AnnoRepoClient, Error> {
+        let handler = self.incompatible_server_handler.take();
+        let client = self.build()?;
+        match client.check_server_version().await {
+            Err(e @ Error::IncompatibleServer { .. }) => match handler {
+                Some(IncompatibleServerHandler(handler)) => handler(&e),
+                None => return Err(e),
+            },
+            result => {
+                result?;
+            }
+        }
+
+        Ok(client)
+    }
+
+    /// Build a [`AnnoRepoBlockingClient`](crate::AnnoRepoBlockingClient)
+    /// instead.
+    #[cfg(feature = "blocking")]
+    pub fn build_blocking(self) -> Result<crate::AnnoRepoBlockingClient, Error> {
+        crate::AnnoRepoBlockingClient::wrap(self.build()?)
+    }
+
+    pub fn build(self) -> Result<AnnoRepoClient, Error> {
+        let base_url = normalize_base_url(&self.base_url)?;
+        let mut headers = HeaderMap::new();
+        if let Some(ApiKey(api_key)) = &self.api_key {
+            let mut value = HeaderValue::from_str(&format!("Bearer {api_key}"))
+                .map_err(|_| Error::InvalidApiKey)?;
+            value.set_sensitive(true);
+            headers.insert(AUTHORIZATION, value);
+        }
+        if let Some(accept) = &self.accept {
+            headers.insert(ACCEPT, accept.clone());
+        }
+        let user_agent = match &self.user_agent_suffix {
+            Some(suffix) => HeaderValue::from_str(&format!("{APP_USER_AGENT} {suffix}"))
+                .map_err(|_| Error::InvalidUserAgent(suffix.clone()))?,
+            None => HeaderValue::from_static(APP_USER_AGENT),
+        };
+        let client = reqwest::ClientBuilder::new()
+            .user_agent(user_agent)
+            .default_headers(headers);
+        // The browser manages connections itself.
+        #[cfg(not(target_arch = "wasm32"))]
+        let client = self.http.apply(client);
+        let client = client.build().map_err(Error::ReqError)?;
+
+        let fallback_base_urls = self
+            .fallback_base_urls
+            .iter()
+            .map(|url| normalize_base_url(url))
+            .collect::<Result<_, _>>()?;
+
+        Ok(AnnoRepoClient {
+            base_url: base_url.into(),
+            fallback_base_urls,
+            container: self.container.into(),
+            has_api_key: self.api_key.is_some(),
+            client,
+            interceptors: self.interceptors,
+            retry_policy: self.retry_policy,
+            circuit_breaker: self
+                .circuit_breaker
+                .map(|config| Arc::new(CircuitBreaker::new(config))),
+            deserialization_mode: self.deserialization_mode,
+            query_field_check: self.query_field_check,
+            metadata_cache: self
+                .metadata_cache_ttl
+                .map(|ttl| Arc::new(MetadataCache::new(ttl))),
+            etag_cache: self
+                .etag_cache_capacity
+                .map(|capacity| Arc::new(EtagCache::new(capacity))),
+            annotation_cache: self
+                .annotation_cache
+                .map(|(capacity, ttl)| Arc::new(AnnotationCache::new(capacity, ttl))),
+            gzip_uploads: self.gzip_uploads,
+            max_response_size: self.max_response_size,
+            #[cfg(all(feature = "wire-log", not(target_arch = "wasm32")))]
+            wire_log: self.wire_log,
+            request_id_header: self.request_id_header,
+            capabilities: Arc::default(),
+        })
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Default)]
+struct HttpOptions {
+    version: HttpVersion,
+    adaptive_window: bool,
+    keep_alive_interval: Option<Duration>,
+    timeout: Option<Duration>,
+    connect_timeout: Option<Duration>,
+    resolve: Vec<(String, SocketAddr)>,
+    local_address: Option<IpAddr>,
+    proxies: Vec<reqwest::Proxy>,
+    no_proxy: bool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Default)]
+enum HttpVersion {
+    /// HTTP/2 when negotiated over TLS, HTTP/1.1 otherwise.
+    #[default]
+    Negotiated,
+    Http1Only,
+    Http2PriorKnowledge,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl HttpOptions {
+    fn apply(&self, client: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+        let client = match self.version {
+            HttpVersion::Negotiated => client,
+            HttpVersion::Http1Only => client.http1_only(),
+            HttpVersion::Http2PriorKnowledge => client.http2_prior_knowledge(),
+        };
+        let mut client = client.http2_adaptive_window(self.adaptive_window);
+        if let Some(timeout) = self.timeout {
+            client = client.timeout(timeout);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            client = client.connect_timeout(timeout);
+        }
+        if self.no_proxy {
+            client = client.no_proxy();
+        }
+        for proxy in &self.proxies {
+            client = client.proxy(proxy.clone());
+        }
+        for (domain, addr) in &self.resolve {
+            client = client.resolve(domain, *addr);
+        }
+        if self.local_address.is_some() {
+            client = client.local_address(self.local_address);
+        }
+        match self.keep_alive_interval {
+            Some(interval) => client
+                .http2_keep_alive_interval(interval)
+                .http2_keep_alive_while_idle(true),
+            None => client,
+        }
+    }
+}
+
+/// Keeps the key out of `Debug` output.
+struct ApiKey(String);
+
+impl fmt::Debug for ApiKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ApiKey(***)")
+    }
+}
+
+struct IncompatibleServerHandler(Arc<dyn Fn(&Error) + Send + Sync>);
+
+impl fmt::Debug for IncompatibleServerHandler {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("IncompatibleServerHandler")
+    }
+}
+
+/// Check that `base_url` is an absolute http(s) URL and strip any trailing slashes.
+pub(crate) fn normalize_base_url(base_url: &str) -> Result<String, Error> {
+    let invalid = |reason: &str| Error::InvalidBaseUrl {
+        url: base_url.to_string(),
+        reason: reason.to_string(),
+    };
+
+    let url = reqwest::Url::parse(base_url).map_err(|e| invalid(&e.to_string()))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(invalid("scheme must be http or https"));
+    }
+    if url.host_str().is_none() {
+        return Err(invalid("missing host"));
+    }
+    if url.query().is_some() || url.fragment().is_some() {
+        return Err(invalid("must not contain a query or fragment"));
+    }
+
+    Ok(url.as_str().trim_end_matches('/').to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base_url_is_normalized() {
+        assert_eq!(
+            normalize_base_url("https://annorepo.example.com/").unwrap(),
+            "https://annorepo.example.com"
+        );
+        assert_eq!(
+            normalize_base_url("http://localhost:8080/annorepo//").unwrap(),
+            "http://localhost:8080/annorepo"
+        );
+    }
+
+    #[test]
+    fn api_key_is_not_shown_in_debug_output() {
+        let builder =
+            AnnoRepoClient::builder("https://annorepo.example.com", "c").api_key("secret");
+
+        assert!(!format!("{builder:?}").contains("secret"));
+    }
+
+    #[tokio::test]
+    async fn user_agent_names_the_application() {
+        use serde_json::json;
+        use wiremock::matchers::{header, method};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(header(
+                "user-agent",
+                format!("{APP_USER_AGENT} myharvester/2.3").as_str(),
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::builder(server.uri(), "c".to_string())
+            .user_agent_suffix("myharvester/2.3")
+            .build()
+            .unwrap();
+
+        client.get_fields().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn hosts_can_be_pinned_to_an_address() {
+        use serde_json::json;
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let addr = *server.address();
+        let client = AnnoRepoClient::builder(
+            format!("http://annorepo.invalid:{}", addr.port()),
+            "c".to_string(),
+        )
+        .resolve("annorepo.invalid", addr)
+        .build()
+        .unwrap();
+
+        client.get_fields().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn requests_go_through_the_proxy() {
+        use serde_json::json;
+        use wiremock::matchers::{header, method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let proxy = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/fields"))
+            .and(header("proxy-authorization", "Basic dXNlcjpwYXNz"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .expect(1)
+            .mount(&proxy)
+            .await;
+        let client = AnnoRepoClient::builder("http://annorepo.invalid", "c")
+            .proxy(
+                reqwest::Proxy::all(proxy.uri())
+                    .unwrap()
+                    .basic_auth("user", "pass"),
+            )
+            .build()
+            .unwrap();
+
+        client.get_fields().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn http2_can_be_used_without_tls() {
+        use serde_json::json;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/fields"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"body.type": 1})))
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::builder(server.uri(), "c".to_string())
+            .http2_prior_knowledge()
+            .http2_keep_alive_interval(Duration::from_secs(30))
+            .build()
+            .unwrap();
+
+        assert_eq!(client.get_field_names().await.unwrap(), ["body.type"]);
+    }
+
+    #[tokio::test]
+    async fn connect_checks_the_server_version() {
+        use serde_json::json;
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/about"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "appName": "AnnoRepo",
+                "version": "1.2.0",
+                "startedAt": "2024-03-01T09:00:00Z",
+                "withAuthentication": false
+            })))
+            .mount(&server)
+            .await;
+
+        let result = AnnoRepoClient::builder(server.uri(), "c".to_string())
+            .connect()
+            .await;
+        assert!(
+            matches!(result, Err(Error::IncompatibleServer { version, .. }) if version == "1.2.0")
+        );
+
+        let warned = Arc::new(AtomicBool::new(false));
+        let flag = warned.clone();
+        AnnoRepoClient::builder(server.uri(), "c".to_string())
+            .on_incompatible_server(move |_| flag.store(true, Ordering::SeqCst))
+            .connect()
+            .await
+            .unwrap();
+        assert!(warned.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn invalid_base_urls_are_rejected() {
+        for url in [
+            "annorepo.example.com",
+            "ftp://annorepo.example.com",
+            "https://annorepo.example.com/?page=1",
+            "",
+        ] {
+            assert!(
+                matches!(normalize_base_url(url), Err(Error::InvalidBaseUrl { .. })),
+                "{url} should be rejected"
+            );
+        }
+    }
+}
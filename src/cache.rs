@@ -0,0 +1,277 @@
+use crate::time::Instant;
+use crate::{AboutInfo, Annotation, IndexInfo};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Client-side cache for the container metadata endpoints, enabled with
+/// [`AnnoRepoClientBuilder::metadata_cache_ttl`](crate::AnnoRepoClientBuilder::metadata_cache_ttl).
+#[derive(Debug)]
+pub(crate) struct MetadataCache {
+    pub(crate) about: TimedSlot<AboutInfo>,
+    pub(crate) fields: TimedSlot<HashMap<String, u64>>,
+    pub(crate) indexes: TimedSlot<Vec<IndexInfo>>,
+}
+
+impl MetadataCache {
+    pub(crate) fn new(ttl: Duration) -> Self {
+        Self {
+            about: TimedSlot::new(ttl),
+            fields: TimedSlot::new(ttl),
+            indexes: TimedSlot::new(ttl),
+        }
+    }
+
+    pub(crate) fn ttl(&self) -> Duration {
+        self.fields.ttl
+    }
+
+    /// Forget everything derived from the container's contents.
+    pub(crate) fn invalidate_container(&self) {
+        self.fields.clear();
+        self.indexes.clear();
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct TimedSlot<T> {
+    ttl: Duration,
+    value: Mutex<Option<(Instant, T)>>,
+}
+
+impl<T: Clone> TimedSlot<T> {
+    fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            value: Mutex::new(None),
+        }
+    }
+
+    pub(crate) fn get(&self) -> Option<T> {
+        let value = self.value.lock().unwrap();
+        match &*value {
+            Some((stored, value)) if stored.elapsed() < self.ttl => Some(value.clone()),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn set(&self, value: &T) {
+        *self.value.lock().unwrap() = Some((Instant::now(), value.clone()));
+    }
+
+    pub(crate) fn clear(&self) {
+        *self.value.lock().unwrap() = None;
+    }
+}
+
+/// Responses kept with their `ETag`, and revalidated with `If-None-Match`,
+/// enabled with
+/// [`AnnoRepoClientBuilder::etag_cache`](crate::AnnoRepoClientBuilder::etag_cache).
+///
+/// Once full, the oldest entries make way for new ones.
+#[derive(Debug)]
+pub(crate) struct EtagCache {
+    capacity: usize,
+    entries: Mutex<EtagEntries>,
+}
+
+#[derive(Debug, Default)]
+struct EtagEntries {
+    by_url: HashMap<String, (String, Vec<u8>)>,
+    order: VecDeque<String>,
+}
+
+impl EtagCache {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::default(),
+        }
+    }
+
+    pub(crate) fn etag(&self, url: &str) -> Option<String> {
+        let entries = self.entries.lock().unwrap();
+        entries.by_url.get(url).map(|(etag, _)| etag.clone())
+    }
+
+    /// The stored body, if it still has `etag`.
+    pub(crate) fn body(&self, url: &str, etag: &str) -> Option<Vec<u8>> {
+        let entries = self.entries.lock().unwrap();
+        match entries.by_url.get(url) {
+            Some((stored, body)) if stored == etag => Some(body.clone()),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn insert(&self, url: &str, etag: String, body: Vec<u8>) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        if entries
+            .by_url
+            .insert(url.to_string(), (etag, body))
+            .is_none()
+        {
+            entries.order.push_back(url.to_string());
+        }
+        while entries.order.len() > self.capacity {
+            if let Some(oldest) = entries.order.pop_front() {
+                entries.by_url.remove(&oldest);
+            }
+        }
+    }
+
+    pub(crate) fn remove(&self, url: &str) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.by_url.remove(url).is_some() {
+            entries.order.retain(|stored| stored != url);
+        }
+    }
+}
+
+/// Recently read annotations by URL, enabled with
+/// [`AnnoRepoClientBuilder::annotation_cache`](crate::AnnoRepoClientBuilder::annotation_cache).
+///
+/// Once full, the least recently used entry makes way for a new one.
+#[derive(Debug)]
+pub(crate) struct AnnotationCache {
+    capacity: usize,
+    ttl: Duration,
+    entries: Mutex<LruEntries>,
+}
+
+#[derive(Debug, Default)]
+struct LruEntries {
+    by_url: HashMap<String, LruEntry>,
+    /// URLs by the tick of their last use, oldest first.
+    by_use: BTreeMap<u64, String>,
+    tick: u64,
+}
+
+#[derive(Debug)]
+struct LruEntry {
+    last_use: u64,
+    stored: Instant,
+    annotation: Annotation,
+}
+
+impl LruEntries {
+    fn touch(&mut self, url: &str) {
+        self.tick += 1;
+        if let Some(entry) = self.by_url.get_mut(url) {
+            self.by_use.remove(&entry.last_use);
+            entry.last_use = self.tick;
+            self.by_use.insert(self.tick, url.to_string());
+        }
+    }
+
+    fn remove(&mut self, url: &str) {
+        if let Some(entry) = self.by_url.remove(url) {
+            self.by_use.remove(&entry.last_use);
+        }
+    }
+}
+
+impl AnnotationCache {
+    pub(crate) fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            capacity,
+            ttl,
+            entries: Mutex::default(),
+        }
+    }
+
+    pub(crate) fn get(&self, url: &str) -> Option<Annotation> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.by_url.get(url) {
+            Some(entry) if entry.stored.elapsed() < self.ttl => {
+                let annotation = entry.annotation.clone();
+                entries.touch(url);
+                Some(annotation)
+            }
+            Some(_) => {
+                entries.remove(url);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub(crate) fn insert(&self, url: &str, annotation: &Annotation) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        entries.remove(url);
+        while entries.by_url.len() >= self.capacity {
+            match entries.by_use.pop_first() {
+                Some((_, oldest)) => entries.by_url.remove(&oldest),
+                None => break,
+            };
+        }
+        entries.by_url.insert(
+            url.to_string(),
+            LruEntry {
+                last_use: 0,
+                stored: Instant::now(),
+                annotation: annotation.clone(),
+            },
+        );
+        entries.touch(url);
+    }
+
+    pub(crate) fn remove(&self, url: &str) {
+        self.entries.lock().unwrap().remove(url);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_expire_after_the_ttl() {
+        let slot = TimedSlot::new(Duration::from_millis(20));
+        slot.set(&1);
+        assert_eq!(slot.get(), Some(1));
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(slot.get(), None);
+    }
+
+    #[test]
+    fn oldest_etags_are_evicted_first() {
+        let cache = EtagCache::new(2);
+        cache.insert("a", "1".to_string(), b"a".to_vec());
+        cache.insert("b", "1".to_string(), b"b".to_vec());
+        cache.insert("a", "2".to_string(), b"a2".to_vec());
+        cache.insert("c", "1".to_string(), b"c".to_vec());
+
+        assert_eq!(cache.etag("a"), None);
+        assert_eq!(cache.body("b", "1"), Some(b"b".to_vec()));
+        assert_eq!(cache.body("c", "2"), None);
+    }
+
+    #[test]
+    fn least_recently_used_annotations_are_evicted_first() {
+        let annotation = |target: &str| Annotation {
+            context: None,
+            id: None,
+            annotation_type: crate::OneOrMany::One("Annotation".to_string()),
+            motivation: None,
+            body: None,
+            target: crate::OneOrMany::One(target.into()),
+            extra: Default::default(),
+        };
+        let cache = AnnotationCache::new(2, Duration::from_secs(60));
+        cache.insert("a", &annotation("a"));
+        cache.insert("b", &annotation("b"));
+        assert!(cache.get("a").is_some());
+        cache.insert("c", &annotation("c"));
+
+        assert_eq!(cache.get("a"), Some(annotation("a")));
+        assert_eq!(cache.get("b"), None);
+        assert_eq!(cache.get("c"), Some(annotation("c")));
+    }
+}
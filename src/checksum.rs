@@ -0,0 +1,268 @@
+use crate::sync::annotation_name;
+use crate::{AnnoRepoClient, Error, UploadOptions};
+use futures::{StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::Write;
+
+/// Fields the server sets itself, which differ between the annotation sent
+/// and the one stored.
+const SERVER_FIELDS: [&str; 2] = ["id", "via"];
+
+/// Annotations re-read at the same time by [`AnnoRepoClient::verify`].
+const VERIFY_CONCURRENCY: usize = 8;
+
+/// The SHA-256 of an annotation's content, in hex: its JSON with object keys
+/// sorted and without the server-assigned `id` and `via`, so that an
+/// annotation hashes the same before upload, after export, and in another
+/// container.
+pub fn content_hash(annotation: &Value) -> String {
+    let mut hasher = Sha256::new();
+    match annotation {
+        Value::Object(fields) => {
+            let fields = fields
+                .iter()
+                .filter(|(key, _)| !SERVER_FIELDS.contains(&key.as_str()));
+            write_canonical_object(&mut hasher, fields);
+        }
+        value => write_canonical(&mut hasher, value),
+    }
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+fn write_canonical(hasher: &mut Sha256, value: &Value) {
+    match value {
+        Value::Array(items) => {
+            hasher.update(b"[");
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    hasher.update(b",");
+                }
+                write_canonical(hasher, item);
+            }
+            hasher.update(b"]");
+        }
+        Value::Object(fields) => write_canonical_object(hasher, fields.iter()),
+        scalar => hasher.update(scalar.to_string()),
+    }
+}
+
+fn write_canonical_object<'a>(
+    hasher: &mut Sha256,
+    fields: impl Iterator<Item = (&'a String, &'a Value)>,
+) {
+    let mut fields: Vec<_> = fields.collect();
+    fields.sort_by_key(|(key, _)| *key);
+    hasher.update(b"{");
+    for (index, (key, value)) in fields.into_iter().enumerate() {
+        if index > 0 {
+            hasher.update(b",");
+        }
+        hasher.update(Value::String(key.clone()).to_string());
+        hasher.update(b":");
+        write_canonical(hasher, value);
+    }
+    hasher.update(b"}");
+}
+
+/// [`content_hash`]es by annotation name, to check a container against
+/// later; serializable, to keep with an export.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Checksums(BTreeMap<String, String>);
+
+impl Checksums {
+    /// Record the hash of `annotation` under the name in its `id`.
+    pub fn add(&mut self, annotation: &Value) -> Result<(), Error> {
+        self.0
+            .insert(annotation_name(annotation)?, content_hash(annotation));
+        Ok(())
+    }
+
+    pub fn insert(&mut self, annotation_name: String, hash: String) {
+        self.0.insert(annotation_name, hash);
+    }
+
+    pub fn get(&self, annotation_name: &str) -> Option<&str> {
+        self.0.get(annotation_name).map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Names and hashes, ordered by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.0
+            .iter()
+            .map(|(name, hash)| (name.as_str(), hash.as_str()))
+    }
+}
+
+/// What [`AnnoRepoClient::verify`] found.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VerifyReport {
+    /// Annotations re-read.
+    pub checked: usize,
+    /// Annotations stored with different content.
+    pub mismatched: Vec<String>,
+    /// Annotations no longer in the container.
+    pub missing: Vec<String>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.mismatched.is_empty() && self.missing.is_empty()
+    }
+}
+
+impl AnnoRepoClient {
+    /// [`upload_annotations`](Self::upload_annotations), also returning the
+    /// hashes of the annotations under the names the server gave them.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container, count = annotations.len())))]
+    pub async fn upload_annotations_checksummed(
+        &self,
+        annotations: &[Value],
+        options: &UploadOptions,
+    ) -> Result<Checksums, Error> {
+        let identifiers = self.upload_annotations(annotations, options).await?;
+        let mut checksums = Checksums::default();
+        for (annotation, identifier) in annotations.iter().zip(identifiers) {
+            checksums.insert(identifier.annotation_name, content_hash(annotation));
+        }
+
+        Ok(checksums)
+    }
+
+    /// [`export_ndjson`](Self::export_ndjson), returning the hashes of the
+    /// annotations written.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container)))]
+    pub async fn export_ndjson_checksummed<W: Write>(
+        &self,
+        mut writer: W,
+    ) -> Result<Checksums, Error> {
+        let mut annotations = self.all_annotations().await?;
+        let mut checksums = Checksums::default();
+        while let Some(annotation) = annotations.try_next().await? {
+            checksums.add(&annotation)?;
+            serde_json::to_writer(&mut writer, &annotation).map_err(|e| Error::Io(e.into()))?;
+            writer.write_all(b"\n").map_err(Error::Io)?;
+        }
+        writer.flush().map_err(Error::Io)?;
+
+        Ok(checksums)
+    }
+
+    /// Re-read the annotations in `checksums`, or an evenly spread `sample`
+    /// of that many, and compare their hashes.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container, sample)))]
+    pub async fn verify(
+        &self,
+        checksums: &Checksums,
+        sample: Option<usize>,
+    ) -> Result<VerifyReport, Error> {
+        let step = match sample {
+            Some(0) => return Ok(VerifyReport::default()),
+            Some(sample) => checksums.len().div_ceil(sample).max(1),
+            None => 1,
+        };
+        // Collected first, so the future stays `Send` behind `AnnoRepoApi`.
+        let requests: Vec<_> = checksums
+            .iter()
+            .step_by(step)
+            .map(|(name, hash)| async move {
+                // Straight from the server, past the annotation cache.
+                let url = self.annotation_url(name);
+                match self.send(self.client.get(url)).await {
+                    Ok(res) => {
+                        let stored: Value = res.json().await?;
+                        Ok((name, Some(content_hash(&stored) == hash)))
+                    }
+                    Err(Error::NotFound(_)) => Ok((name, None)),
+                    Err(e) => Err(e),
+                }
+            })
+            .collect();
+        let found: Vec<(&str, Option<bool>)> = futures::stream::iter(requests)
+            .buffered(VERIFY_CONCURRENCY)
+            .try_collect()
+            .await?;
+
+        let mut report = VerifyReport {
+            checked: found.len(),
+            ..VerifyReport::default()
+        };
+        for (name, matches) in found {
+            match matches {
+                Some(true) => {}
+                Some(false) => report.mismatched.push(name.to_string()),
+                None => report.missing.push(name.to_string()),
+            }
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn hashes_ignore_key_order_and_server_fields() {
+        let sent = json!({"type": "Annotation", "body": {"value": "x", "type": "TextualBody"}});
+        let stored = json!({
+            "id": "https://annorepo.example.com/w3c/c/a1",
+            "body": {"type": "TextualBody", "value": "x"},
+            "type": "Annotation"
+        });
+
+        assert_eq!(content_hash(&sent), content_hash(&stored));
+        assert_ne!(
+            content_hash(&sent),
+            content_hash(&json!({"type": "Annotation"}))
+        );
+    }
+
+    #[tokio::test]
+    async fn verify_reports_changed_and_missing_annotations() {
+        let server = MockServer::start().await;
+        for (name, value) in [("a1", "x"), ("a2", "changed")] {
+            Mock::given(method("GET"))
+                .and(path(format!("/w3c/c/{name}")))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "id": format!("{}/w3c/c/{name}", server.uri()),
+                    "type": "Annotation",
+                    "body": {"value": value},
+                    "target": "t"
+                })))
+                .mount(&server)
+                .await;
+        }
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+        let mut checksums = Checksums::default();
+        for name in ["a1", "a2", "a3"] {
+            let annotation = json!({"type": "Annotation", "body": {"value": "x"}, "target": "t"});
+            checksums.insert(name.to_string(), content_hash(&annotation));
+        }
+
+        let report = client.verify(&checksums, None).await.unwrap();
+
+        assert_eq!(report.checked, 3);
+        assert_eq!(report.mismatched, ["a2"]);
+        assert_eq!(report.missing, ["a3"]);
+        assert!(!report.is_ok());
+    }
+}
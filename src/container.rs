@@ -0,0 +1,275 @@
+use crate::encode::segment;
+use crate::model::{extra_field_names, UnknownFields};
+use crate::page::PageDecoder;
+use crate::{AnnoRepoClient, Error};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+/// Summary of a container, as given by its metadata service.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// Number of annotations in the container.
+    #[serde(alias = "size")]
+    pub annotation_count: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl UnknownFields for ContainerMetadata {
+    fn unknown_fields(&self) -> Vec<String> {
+        extra_field_names(&self.extra)
+    }
+}
+
+/// How much of its annotations a container read includes, sent as a
+/// `Prefer` header.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ContainerPreference {
+    /// The annotations themselves.
+    #[default]
+    ContainedDescriptions,
+    /// Only the IRIs of the annotations, which is much cheaper when only
+    /// their names are needed.
+    ContainedIris,
+    /// Only the container itself.
+    Minimal,
+}
+
+impl ContainerPreference {
+    fn header_value(self) -> &'static str {
+        match self {
+            Self::ContainedDescriptions => {
+                r#"return=representation;include="http://www.w3.org/ns/oa#PreferContainedDescriptions""#
+            }
+            Self::ContainedIris => {
+                r#"return=representation;include="http://www.w3.org/ns/oa#PreferContainedIRIs""#
+            }
+            Self::Minimal => {
+                r#"return=representation;include="http://www.w3.org/ns/ldp#PreferMinimalContainer""#
+            }
+        }
+    }
+}
+
+impl AnnoRepoClient {
+    /// Create the client's container, returning its URL.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container)))]
+    pub async fn create_container(&self, label: Option<&str>) -> Result<String, Error> {
+        let url = format!("{}/w3c/", self.base_url);
+        let mut container = json!({
+            "@context": [
+                "http://www.w3.org/ns/anno.jsonld",
+                "http://www.w3.org/ns/ldp.jsonld"
+            ],
+            "type": ["BasicContainer", "AnnotationCollection"]
+        });
+        if let Some(label) = label {
+            container["label"] = json!(label);
+        }
+
+        let res = self
+            .send(
+                self.client
+                    .post(url)
+                    .header("Slug", &*self.container)
+                    .json(&container),
+            )
+            .await;
+        self.invalidate_container_metadata();
+
+        let location = res?.location()?.map(str::to_string);
+        Ok(location.unwrap_or_else(|| self.container_url()))
+    }
+
+    /// The size and timestamps of the container, without listing its
+    /// annotations.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container)))]
+    pub async fn get_container_metadata(&self) -> Result<ContainerMetadata, Error> {
+        let url = self.resolve_service("metadata");
+
+        self.client_get_typed(&url).await
+    }
+
+    /// A page of the container's annotations, listed without creating a
+    /// search first.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container, page)))]
+    pub async fn read_container_page(&self, page: u32) -> Result<Value, Error> {
+        self.read_container_page_with(page, ContainerPreference::default())
+            .await
+    }
+
+    /// [`read_container_page`](Self::read_container_page), asking for only
+    /// as much of the annotations as `preference` says.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container, page, ?preference)))]
+    pub async fn read_container_page_with(
+        &self,
+        page: u32,
+        preference: ContainerPreference,
+    ) -> Result<Value, Error> {
+        let res = self.send(self.container_page_request(page, preference)?);
+        if preference == ContainerPreference::Minimal {
+            // Without items, there is nothing to decode incrementally.
+            return res.await?.json().await;
+        }
+        let page = res.await?.result_page(&mut PageDecoder::default()).await?;
+
+        Ok(page.into_value())
+    }
+
+    /// The items on a page of the container listing, and whether there are
+    /// more pages.
+    pub(crate) async fn read_container_items(
+        &self,
+        page: u32,
+        preference: ContainerPreference,
+        decoder: &mut PageDecoder,
+    ) -> Result<(Vec<Value>, bool), Error> {
+        let page = self
+            .send(self.container_page_request(page, preference)?)
+            .await?
+            .result_page(decoder)
+            .await?;
+        if page.rest.contains_key("items") {
+            return Err(Error::MalformedAnnotationPage {
+                url: self.container_url(),
+                page: page.into_value(),
+            });
+        }
+        let has_next = !page.items.is_empty() && page.has_next();
+
+        Ok((page.items, has_next))
+    }
+
+    fn container_page_request(
+        &self,
+        page: u32,
+        preference: ContainerPreference,
+    ) -> Result<reqwest::RequestBuilder, Error> {
+        let params = [("page", page.to_string())];
+        let url = reqwest::Url::parse_with_params(&self.container_url(), &params)?;
+
+        Ok(self
+            .client
+            .get(url)
+            .header("Prefer", preference.header_value()))
+    }
+
+    pub(crate) fn container_url(&self) -> String {
+        format!("{}/w3c/{}/", self.base_url, segment(&self.container))
+    }
+
+    pub(crate) fn annotation_url(&self, annotation_name: &str) -> String {
+        format!("{}{}", self.container_url(), segment(annotation_name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn container_is_created_with_its_name_as_slug() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/w3c/"))
+            .and(header("slug", "c"))
+            .respond_with(
+                ResponseTemplate::new(201)
+                    .insert_header("location", format!("{}/w3c/c/", server.uri())),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        let url = client.create_container(Some("Letters")).await.unwrap();
+
+        assert_eq!(url, format!("{}/w3c/c/", server.uri()));
+    }
+
+    #[tokio::test]
+    async fn metadata_is_read_from_the_service() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/metadata"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "https://annorepo.example.com/w3c/c/",
+                "label": "Letters",
+                "size": 1024,
+                "created": "2024-03-01T09:00:00Z",
+                "modified": "2024-03-02T10:30:00Z",
+                "isReadOnlyForAnonymousUsers": true
+            })))
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        let metadata = client.get_container_metadata().await.unwrap();
+
+        assert_eq!(metadata.annotation_count, 1024);
+        assert_eq!(metadata.label.as_deref(), Some("Letters"));
+        assert_eq!(metadata.modified.as_deref(), Some("2024-03-02T10:30:00Z"));
+        assert_eq!(metadata.unknown_fields(), ["isReadOnlyForAnonymousUsers"]);
+    }
+
+    #[tokio::test]
+    async fn container_pages_are_read_without_a_search() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/w3c/c/"))
+            .and(query_param("page", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "type": "AnnotationPage",
+                "startIndex": 100,
+                "items": [{"id": "a101"}],
+                "next": "/w3c/c/?page=2"
+            })))
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        let page = client.read_container_page(1).await.unwrap();
+
+        assert_eq!(page["startIndex"], 100);
+        assert_eq!(page["items"], json!([{"id": "a101"}]));
+    }
+
+    #[tokio::test]
+    async fn container_pages_can_list_only_iris() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/w3c/c/"))
+            .and(header(
+                "prefer",
+                r#"return=representation;include="http://www.w3.org/ns/oa#PreferContainedIRIs""#,
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": ["https://annorepo.example.com/w3c/c/a1"]
+            })))
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        let page = client
+            .read_container_page_with(0, ContainerPreference::ContainedIris)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            page["items"],
+            json!(["https://annorepo.example.com/w3c/c/a1"])
+        );
+    }
+}
@@ -0,0 +1,74 @@
+use crate::Query;
+use chrono::{DateTime, SecondsFormat, TimeZone, Utc};
+use std::ops::{Bound, RangeBounds};
+
+/// A timestamp as AnnoRepo stores `created` and `modified`: UTC, with
+/// milliseconds and a `Z`, e.g. `2024-03-01T09:00:00.000Z`.
+///
+/// Timestamps are compared as strings, so a value in another format, such
+/// as one with an offset or without the milliseconds, silently matches the
+/// wrong annotations.
+pub fn format_timestamp<Tz: TimeZone>(timestamp: &DateTime<Tz>) -> String {
+    timestamp
+        .with_timezone(&Utc)
+        .to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+impl Query {
+    /// Match annotations created within `range`, such as `start..end` or
+    /// `start..`.
+    pub fn created_in<Tz: TimeZone>(self, range: impl RangeBounds<DateTime<Tz>>) -> Self {
+        self.timestamp_in("created", range)
+    }
+
+    /// Match annotations last modified within `range`.
+    pub fn modified_in<Tz: TimeZone>(self, range: impl RangeBounds<DateTime<Tz>>) -> Self {
+        self.timestamp_in("modified", range)
+    }
+
+    fn timestamp_in<Tz: TimeZone>(
+        self,
+        field: &str,
+        range: impl RangeBounds<DateTime<Tz>>,
+    ) -> Self {
+        let query = match range.start_bound() {
+            Bound::Included(start) => self.greater_than_or_equal(field, format_timestamp(start)),
+            Bound::Excluded(start) => self.greater_than(field, format_timestamp(start)),
+            Bound::Unbounded => self,
+        };
+        match range.end_bound() {
+            Bound::Included(end) => query.less_than_or_equal(field, format_timestamp(end)),
+            Bound::Excluded(end) => query.less_than(field, format_timestamp(end)),
+            Bound::Unbounded => query,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::FixedOffset;
+    use serde_json::json;
+
+    #[test]
+    fn ranges_are_sent_in_utc_with_milliseconds() {
+        let cet = FixedOffset::east_opt(3600).unwrap();
+        let start = cet.with_ymd_and_hms(2024, 3, 1, 10, 0, 0).unwrap();
+        let end = Utc.with_ymd_and_hms(2024, 4, 1, 0, 0, 0).unwrap();
+
+        let query = Query::new()
+            .created_in(start..)
+            .modified_in(start.to_utc()..end);
+
+        assert_eq!(
+            query.into_value(),
+            json!({
+                "created": {":isGreaterThanOrEqualTo": "2024-03-01T09:00:00.000Z"},
+                "modified": {
+                    ":isGreaterThanOrEqualTo": "2024-03-01T09:00:00.000Z",
+                    ":isLessThan": "2024-04-01T00:00:00.000Z"
+                }
+            })
+        );
+    }
+}
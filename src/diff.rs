@@ -0,0 +1,162 @@
+use crate::encode::last_segment;
+use crate::sync::annotation_name;
+use crate::{AnnoRepoClient, Error};
+use futures::TryStreamExt;
+use serde_json::Value;
+use std::collections::HashSet;
+
+/// Annotations compared at the same time by [`diff_containers`].
+const DIFF_CONCURRENCY: usize = 4;
+
+/// How the containers compared by [`diff_containers`] differ, by annotation
+/// name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContainerDiff {
+    pub only_in_a: Vec<String>,
+    pub only_in_b: Vec<String>,
+    /// Annotations in both containers, with a different body or target.
+    pub differing: Vec<String>,
+    /// The number of annotations in both containers with the same body and
+    /// target.
+    pub same: usize,
+}
+
+impl ContainerDiff {
+    pub fn is_empty(&self) -> bool {
+        self.only_in_a.is_empty() && self.only_in_b.is_empty() && self.differing.is_empty()
+    }
+}
+
+enum Comparison {
+    OnlyInA,
+    Differing,
+    Same,
+}
+
+/// Compare the containers of `a` and `b`, which may be on different servers,
+/// matching annotations by name and comparing their bodies and targets.
+///
+/// The annotations of `a` are streamed and looked up in `b` one by one, and
+/// only the IRIs of `b` are listed, so neither container is held in memory;
+/// only the names of `a` are.
+#[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(a = %a.container, b = %b.container)))]
+pub async fn diff_containers(
+    a: &AnnoRepoClient,
+    b: &AnnoRepoClient,
+) -> Result<ContainerDiff, Error> {
+    let (mut diff, names) = a
+        .stream_container_annotations(None)
+        .map_ok(|annotation| async move {
+            let name = annotation_name(&annotation)?;
+            let comparison = compare(&annotation, &name, b).await?;
+            Ok((name, comparison))
+        })
+        .try_buffer_unordered(DIFF_CONCURRENCY)
+        .try_fold(
+            (ContainerDiff::default(), HashSet::new()),
+            |(mut diff, mut names), (name, comparison)| async move {
+                match comparison {
+                    Comparison::OnlyInA => diff.only_in_a.push(name.clone()),
+                    Comparison::Differing => diff.differing.push(name.clone()),
+                    Comparison::Same => diff.same += 1,
+                }
+                names.insert(name);
+                Ok((diff, names))
+            },
+        )
+        .await?;
+
+    diff.only_in_b = b
+        .stream_container_iris(None)
+        .try_filter_map(|iri| {
+            let name = iri.as_str().map(last_segment);
+            let only_in_b = name.filter(|name| !names.contains(name));
+            async move { Ok(only_in_b) }
+        })
+        .try_collect()
+        .await?;
+    diff.only_in_a.sort();
+    diff.only_in_b.sort();
+    diff.differing.sort();
+
+    Ok(diff)
+}
+
+async fn compare(annotation: &Value, name: &str, b: &AnnoRepoClient) -> Result<Comparison, Error> {
+    let url = b.annotation_url(name);
+    let other: Value = match b.client_get_json(&url).await {
+        Ok(other) => other,
+        Err(Error::NotFound(_)) => return Ok(Comparison::OnlyInA),
+        Err(e) => return Err(e),
+    };
+
+    let same = ["body", "target"]
+        .into_iter()
+        .all(|field| normalize(annotation.get(field)) == normalize(other.get(field)));
+    Ok(if same {
+        Comparison::Same
+    } else {
+        Comparison::Differing
+    })
+}
+
+/// A field's value, with a one-element array taken as its element.
+fn normalize(value: Option<&Value>) -> Option<&Value> {
+    match value {
+        Some(Value::Array(values)) if values.len() == 1 => values.first(),
+        value => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{header_exists, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn containers_are_compared_by_name_body_and_target() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/w3c/a/"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"items": [
+                {"id": "https://x/w3c/a/same", "body": "b", "target": ["t"]},
+                {"id": "https://x/w3c/a/changed", "body": "b", "target": "t"},
+                {"id": "https://x/w3c/a/gone", "body": "b", "target": "t"}
+            ]})))
+            .mount(&server)
+            .await;
+        for (name, body) in [("same", "b"), ("changed", "c")] {
+            Mock::given(method("GET"))
+                .and(path(format!("/w3c/b/{name}")))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "id": format!("https://y/w3c/b/{name}"), "body": body, "target": "t"
+                })))
+                .mount(&server)
+                .await;
+        }
+        Mock::given(method("GET"))
+            .and(path("/w3c/b/"))
+            .and(header_exists("prefer"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"items": [
+                "https://y/w3c/b/same", "https://y/w3c/b/changed", "https://y/w3c/b/new"
+            ]})))
+            .mount(&server)
+            .await;
+        let a = AnnoRepoClient::new(server.uri(), "a".to_string()).unwrap();
+        let b = AnnoRepoClient::new(server.uri(), "b".to_string()).unwrap();
+
+        let diff = diff_containers(&a, &b).await.unwrap();
+
+        assert_eq!(
+            diff,
+            ContainerDiff {
+                only_in_a: vec!["gone".to_string()],
+                only_in_b: vec!["new".to_string()],
+                differing: vec!["changed".to_string()],
+                same: 1,
+            }
+        );
+    }
+}
@@ -0,0 +1,139 @@
+use crate::{AnnoRepoClient, Error};
+use futures::stream::{self, Stream, TryStreamExt};
+use serde_json::Value;
+
+/// A distinct value of a field, with the number of annotations it occurs in
+/// if the server counted them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DistinctValue {
+    pub value: Value,
+    pub count: Option<u64>,
+}
+
+impl DistinctValue {
+    /// Read the server's list of distinct values: plain values, or
+    /// `{"value": …, "count": …}` objects. A map from values to counts is
+    /// read as well.
+    fn parse_all(values: Value) -> Vec<Self> {
+        match values {
+            Value::Array(values) => values.into_iter().map(Self::parse).collect(),
+            Value::Object(counts) => counts
+                .into_iter()
+                .map(|(value, count)| Self {
+                    value: Value::String(value),
+                    count: count.as_u64(),
+                })
+                .collect(),
+            value => vec![Self { value, count: None }],
+        }
+    }
+
+    fn parse(value: Value) -> Self {
+        match value {
+            Value::Object(mut counted)
+                if counted.len() == 2
+                    && counted.contains_key("value")
+                    && counted.get("count").is_some_and(Value::is_u64) =>
+            {
+                Self {
+                    count: counted["count"].as_u64(),
+                    value: counted["value"].take(),
+                }
+            }
+            value => Self { value, count: None },
+        }
+    }
+}
+
+impl AnnoRepoClient {
+    /// The distinct values of `field`, with their number of occurrences
+    /// where the server provides them; most servers don't, leaving `count`
+    /// at `None`.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container, field)))]
+    pub async fn get_distinct_value_counts(
+        &self,
+        field: &str,
+    ) -> Result<Vec<DistinctValue>, Error> {
+        let values = self.get_distinct_values(field).await?;
+
+        Ok(DistinctValue::parse_all(values))
+    }
+
+    /// Stream the distinct values of `field`, decoding them as they arrive
+    /// instead of holding the whole list, for fields with very many values.
+    ///
+    /// The server sends the values in a single response, so an interrupted
+    /// stream has to be started again; what was yielded so far is usable.
+    pub fn stream_distinct_values(
+        &self,
+        field: &str,
+    ) -> impl Stream<Item = Result<Value, Error>> + '_ {
+        let url = self.resolve_service_param("distinct-values", field);
+        let values = async move {
+            let res = self.send(self.client.get(url)).await?;
+            Ok::<_, Error>(res.array_items())
+        };
+
+        stream::once(values).try_flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn counts_are_read_when_the_server_sends_them() {
+        let counted = DistinctValue::parse_all(json!([
+            {"value": "Person", "count": 12},
+            "Place"
+        ]));
+        let mapped = DistinctValue::parse_all(json!({"Person": 12}));
+
+        assert_eq!(
+            counted,
+            [
+                DistinctValue {
+                    value: json!("Person"),
+                    count: Some(12)
+                },
+                DistinctValue {
+                    value: json!("Place"),
+                    count: None
+                }
+            ]
+        );
+        assert_eq!(mapped, counted[..1]);
+    }
+
+    #[tokio::test]
+    async fn distinct_values_are_streamed() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/distinct-values/target.source"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                "https://example.com/text/1",
+                "https://example.com/text/2"
+            ])))
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        let values: Vec<Value> = client
+            .stream_distinct_values("target.source")
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            values,
+            [
+                json!("https://example.com/text/1"),
+                json!("https://example.com/text/2")
+            ]
+        );
+    }
+}
@@ -0,0 +1,43 @@
+//! Percent-encoding of the names the client puts in URLs.
+
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::borrow::Cow;
+
+/// Everything but the unreserved characters of RFC 3986, so that a name
+/// stays a single path segment.
+const SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// `name` encoded as a path segment.
+pub(crate) fn segment(name: &str) -> Cow<'_, str> {
+    utf8_percent_encode(name, SEGMENT).into()
+}
+
+/// The decoded last path segment of `iri`, such as the name of an
+/// annotation from its id.
+pub(crate) fn last_segment(iri: &str) -> String {
+    let segment = iri.trim_end_matches('/').rsplit('/').next().unwrap_or(iri);
+    percent_decode_str(segment).decode_utf8_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_round_trip_through_urls() {
+        let name = "brief van/aan Zoë #1";
+
+        let encoded = segment(name);
+
+        assert_eq!(encoded, "brief%20van%2Faan%20Zo%C3%AB%20%231");
+        assert_eq!(
+            last_segment(&format!("https://annorepo.example.com/w3c/c/{encoded}")),
+            name
+        );
+        assert_eq!(segment("body.value"), "body.value");
+    }
+}
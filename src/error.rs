@@ -0,0 +1,371 @@
+use crate::{IndexType, ServerVersion, Violation};
+use reqwest::{Method, StatusCode, Url};
+use serde::Deserialize;
+use serde_json::Value;
+use std::fmt;
+use std::time::Duration;
+
+/// Variants come and go with the crate's features, so matches need a
+/// wildcard arm.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum Error {
+    #[error("URL not found")]
+    UrlNotFound,
+    #[error("Malformed annotation page from {url}")]
+    MalformedAnnotationPage { url: String, page: Value },
+    /// A strictly deserialized response contained undeclared fields.
+    #[error("{request}: unexpected fields in response: {}", fields.join(", "))]
+    UnknownFields {
+        request: Box<RequestContext>,
+        fields: Vec<String>,
+    },
+    #[error("There is no {index_type} index on field {field:?}")]
+    IndexNotFound {
+        field: String,
+        index_type: IndexType,
+    },
+    #[error("Building the {index_type} index on field {field:?} failed")]
+    IndexFailed {
+        field: String,
+        index_type: IndexType,
+    },
+    #[error("The {index_type} index on field {field:?} was not ready within {timeout:?}")]
+    IndexTimeout {
+        field: String,
+        index_type: IndexType,
+        timeout: Duration,
+    },
+    #[error("Invalid location header: {0:?}")]
+    InvalidLocation(String),
+    #[error("Invalid base URL {url:?}: {reason}")]
+    InvalidBaseUrl { url: String, reason: String },
+    #[error("The server has authentication disabled, so this endpoint is unavailable")]
+    AuthenticationDisabled,
+    #[error("The server requires authentication, but no API key was configured")]
+    MissingApiKey,
+    #[error("The API key contains characters that can't be sent in a header")]
+    InvalidApiKey,
+    #[error("The User-Agent suffix {0:?} contains characters that can't be sent in a header")]
+    InvalidUserAgent(String),
+    /// A config file with [`Profiles`](crate::Profiles) could not be read.
+    #[error("Invalid config{}: {reason}", path.as_ref().map(|p| format!(" file {}", p.display())).unwrap_or_default())]
+    InvalidConfig {
+        path: Option<std::path::PathBuf>,
+        reason: String,
+    },
+    #[error("No profile {name:?} in the config file (known: {})", known.join(", "))]
+    UnknownProfile { name: String, known: Vec<String> },
+    #[error("Invalid URL: {0}")]
+    InvalidUrl(#[from] url::ParseError),
+    #[error("{0}")]
+    Unauthorized(Box<HttpError>),
+    #[error("{0}")]
+    Forbidden(Box<HttpError>),
+    #[error("{0}")]
+    NotFound(Box<HttpError>),
+    #[error("{0}")]
+    Conflict(Box<HttpError>),
+    #[error("{0}")]
+    PreconditionFailed(Box<HttpError>),
+    /// Any other unsuccessful response.
+    #[error("{0}")]
+    Http(Box<HttpError>),
+    /// The request could not be sent or its response could not be read.
+    #[error("{request}: {source}")]
+    Request {
+        request: Box<RequestContext>,
+        #[source]
+        source: reqwest::Error,
+    },
+    #[error("{request}: invalid JSON in response: {source}")]
+    Decode {
+        request: Box<RequestContext>,
+        #[source]
+        source: serde_json::Error,
+    },
+    /// A response body exceeded
+    /// [`AnnoRepoClientBuilder::max_response_size`](crate::AnnoRepoClientBuilder::max_response_size),
+    /// and was not read any further.
+    #[error("{request}: response larger than {max_size} bytes")]
+    ResponseTooLarge {
+        request: Box<RequestContext>,
+        max_size: u64,
+    },
+    /// A line of an NDJSON import was not a JSON value.
+    #[error("Line {line}: invalid JSON: {source}")]
+    InvalidRecord {
+        line: usize,
+        #[source]
+        source: serde_json::Error,
+    },
+    /// Annotations that failed [`validate_annotation`](crate::validate_annotation),
+    /// by index, or by line for an NDJSON import, before they were sent.
+    #[error("{}", describe_violations(.0))]
+    InvalidAnnotations(Vec<(usize, Violation)>),
+    /// Fields in a search query that no annotation in the container has; see
+    /// [`QueryFieldCheck`](crate::QueryFieldCheck).
+    #[error("The query uses fields not found in the container: {}", .0.join(", "))]
+    UnknownQueryFields(Vec<String>),
+    #[error("Not a usable backup: {0}")]
+    InvalidBackup(String),
+    /// Options that can't be used together with the operation.
+    #[error("Invalid options: {0}")]
+    InvalidOptions(String),
+    /// A restored container doesn't hold as many annotations as its backup.
+    #[error("Restored {expected} annotations, but the container holds {found}")]
+    RestoreMismatch { expected: u64, found: u64 },
+    #[error("{0:?} is not the URL of an annotation")]
+    InvalidAnnotationUrl(String),
+    #[error("{0:?} is not the URL of an annotation on this server")]
+    ForeignAnnotationUrl(String),
+    #[error("Annotation has no id to take its name from")]
+    MissingAnnotationId,
+    /// The server named a new annotation other than asked; the copy it
+    /// stored under that name has been deleted again.
+    #[error("The server named the annotation {assigned:?} instead of {requested:?}")]
+    SlugNotHonored { requested: String, assigned: String },
+    /// The server sent no ETag for an annotation, so it can't be replaced
+    /// without overwriting concurrent changes.
+    #[error("No ETag for annotation {0:?}, so it can't be replaced safely")]
+    MissingETag(String),
+    #[error("Annotation {annotation_name:?} differs between source and target")]
+    SyncConflict { annotation_name: String },
+    #[error(transparent)]
+    ReqError(#[from] reqwest::Error),
+    #[error("Server version {version} is not supported by this client (supported: {supported})")]
+    IncompatibleServer { version: String, supported: String },
+    #[error("{feature} needs server version {since} or later, the server runs {version}")]
+    UnsupportedByServer {
+        feature: &'static str,
+        version: String,
+        since: ServerVersion,
+    },
+    #[cfg(feature = "blocking")]
+    #[error("Could not start the runtime of the blocking client: {0}")]
+    Runtime(std::io::Error),
+    #[cfg(feature = "stam")]
+    #[error("STAM: {0}")]
+    Stam(#[source] stam::StamError),
+    #[error("No answer within {0:?}")]
+    Timeout(Duration),
+    #[error("Circuit breaker is open; requests fail fast for another {0:?}")]
+    CircuitOpen(Duration),
+    #[error("I/O error: {0}")]
+    Io(#[source] std::io::Error),
+    #[error("Request aborted by interceptor: {0}")]
+    Interceptor(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl Error {
+    /// The HTTP status code, if this error was caused by an unsuccessful response.
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Self::Request { source, .. } | Self::ReqError(source) => source.status(),
+            _ => self.http_error().map(|e| e.status),
+        }
+    }
+
+    /// The details of the unsuccessful response, for any of the HTTP variants.
+    pub fn http_error(&self) -> Option<&HttpError> {
+        match self {
+            Self::Unauthorized(e)
+            | Self::Forbidden(e)
+            | Self::NotFound(e)
+            | Self::Conflict(e)
+            | Self::PreconditionFailed(e)
+            | Self::Http(e) => Some(e),
+            _ => None,
+        }
+    }
+
+    /// The server's structured error message, if it sent one.
+    pub fn api_error(&self) -> Option<&ApiError> {
+        self.http_error().and_then(|e| e.api_error.as_ref())
+    }
+
+    /// Turn an unsuccessful response into the matching HTTP variant, reading the body as text.
+    pub(crate) async fn from_response(request: RequestContext, res: reqwest::Response) -> Self {
+        let status = res.status();
+        match res.text().await {
+            Ok(body) => Self::from_http_error(Box::new(HttpError {
+                request,
+                status,
+                api_error: serde_json::from_str(&body).ok(),
+                body,
+            })),
+            Err(e) => Self::request(request, e),
+        }
+    }
+
+    pub(crate) fn request(request: RequestContext, source: reqwest::Error) -> Self {
+        Self::Request {
+            request: Box::new(request),
+            source: source.without_url(),
+        }
+    }
+
+    fn from_http_error(e: Box<HttpError>) -> Self {
+        match e.status {
+            StatusCode::UNAUTHORIZED => Self::Unauthorized(e),
+            StatusCode::FORBIDDEN => Self::Forbidden(e),
+            StatusCode::NOT_FOUND => Self::NotFound(e),
+            StatusCode::CONFLICT => Self::Conflict(e),
+            StatusCode::PRECONDITION_FAILED => Self::PreconditionFailed(e),
+            _ => Self::Http(e),
+        }
+    }
+}
+
+fn describe_violations(violations: &[(usize, Violation)]) -> String {
+    match violations.first() {
+        Some((index, violation)) => format!(
+            "{} violation(s) of the Web Annotation model, the first in annotation {index}: {violation}",
+            violations.len()
+        ),
+        None => "Invalid annotations for the Web Annotation model".to_string(),
+    }
+}
+
+/// The method and URL of a request, attached to errors for diagnosis.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestContext {
+    pub method: Method,
+    pub url: Url,
+    /// The ID sent with the request; see
+    /// [`AnnoRepoClientBuilder::request_id_header`](crate::AnnoRepoClientBuilder::request_id_header).
+    pub request_id: Option<String>,
+}
+
+impl fmt::Display for RequestContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.method, self.url)?;
+        match &self.request_id {
+            Some(id) => write!(f, " (request {id})"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// An unsuccessful response from the server.
+#[derive(Debug, Clone)]
+pub struct HttpError {
+    pub request: RequestContext,
+    pub status: StatusCode,
+    /// The response body as sent by the server; may be empty.
+    pub body: String,
+    /// The body parsed as an AnnoRepo error payload, if it was one.
+    pub api_error: Option<ApiError>,
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.request, self.status)?;
+        match &self.api_error {
+            Some(api_error) => write!(f, ": {}", api_error),
+            None if self.body.is_empty() => Ok(()),
+            None => write!(f, ": {}", self.body),
+        }
+    }
+}
+
+/// The JSON error payload AnnoRepo sends for rejected requests.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ApiError {
+    pub message: String,
+    #[serde(default)]
+    pub code: Option<u16>,
+    #[serde(default)]
+    pub details: Option<Value>,
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.details {
+            Some(Value::String(details)) => write!(f, "{} ({})", self.message, details),
+            Some(details) if !details.is_null() => write!(f, "{} ({})", self.message, details),
+            _ => write!(f, "{}", self.message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> RequestContext {
+        RequestContext {
+            method: Method::POST,
+            url: Url::parse("https://annorepo.example.com/services/c/search").unwrap(),
+            request_id: None,
+        }
+    }
+
+    #[test]
+    fn http_error_display_includes_body() {
+        let error = Error::Http(Box::new(HttpError {
+            request: request(),
+            status: StatusCode::BAD_REQUEST,
+            body: "invalid query".to_string(),
+            api_error: None,
+        }));
+
+        assert_eq!(
+            error.to_string(),
+            "POST https://annorepo.example.com/services/c/search: 400 Bad Request: invalid query"
+        );
+        assert_eq!(error.status(), Some(StatusCode::BAD_REQUEST));
+    }
+
+    #[test]
+    fn common_statuses_get_their_own_variant() {
+        let error = |status| {
+            Error::from_http_error(Box::new(HttpError {
+                request: request(),
+                status,
+                body: String::new(),
+                api_error: None,
+            }))
+        };
+
+        assert!(matches!(
+            error(StatusCode::UNAUTHORIZED),
+            Error::Unauthorized(_)
+        ));
+        assert!(matches!(error(StatusCode::FORBIDDEN), Error::Forbidden(_)));
+        assert!(matches!(error(StatusCode::NOT_FOUND), Error::NotFound(_)));
+        assert!(matches!(error(StatusCode::CONFLICT), Error::Conflict(_)));
+        assert!(matches!(
+            error(StatusCode::PRECONDITION_FAILED),
+            Error::PreconditionFailed(_)
+        ));
+        assert!(matches!(error(StatusCode::BAD_GATEWAY), Error::Http(_)));
+    }
+
+    #[test]
+    fn invalid_annotations_display_without_violations() {
+        assert_eq!(
+            Error::InvalidAnnotations(Vec::new()).to_string(),
+            "Invalid annotations for the Web Annotation model"
+        );
+    }
+
+    #[test]
+    fn api_error_message_is_shown() {
+        let body = r#"{"code":400,"message":"unknown query operator","details":":isBetween"}"#;
+        let api_error: ApiError = serde_json::from_str(body).unwrap();
+        let error = Error::Http(Box::new(HttpError {
+            request: request(),
+            status: StatusCode::BAD_REQUEST,
+            body: body.to_string(),
+            api_error: Some(api_error),
+        }));
+
+        assert_eq!(error.api_error().unwrap().code, Some(400));
+        assert_eq!(
+            error.to_string(),
+            "POST https://annorepo.example.com/services/c/search: 400 Bad Request: \
+             unknown query operator (:isBetween)"
+        );
+    }
+}
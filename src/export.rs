@@ -0,0 +1,278 @@
+use crate::{AnnoRepoClient, AnnotationStream, CancellationToken, Error, Progress, Query};
+use futures::TryStreamExt;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::Arc;
+
+/// How [`AnnoRepoClient::export_ndjson_with`] reports on and stops its work.
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    /// Told about every annotation written.
+    pub progress: Option<Arc<dyn Progress>>,
+    /// Stops the export at the next annotation once cancelled.
+    pub cancellation: Option<CancellationToken>,
+}
+
+impl AnnoRepoClient {
+    /// Stream every annotation in the container.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container)))]
+    pub async fn all_annotations(&self) -> Result<AnnotationStream<'_>, Error> {
+        let search = self.create_search(HashMap::new()).await?;
+
+        Ok(AnnotationStream::search_results(
+            self,
+            &self.container,
+            search.search_id(),
+            0,
+        ))
+    }
+
+    /// Write every annotation in the container to `writer` as
+    /// newline-delimited JSON, returning the number written.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container)))]
+    pub async fn export_ndjson<W: Write>(&self, writer: W) -> Result<usize, Error> {
+        let annotations = self.all_annotations().await?;
+        write_ndjson(annotations, writer).await
+    }
+
+    /// [`AnnoRepoClient::export_ndjson`], with progress reporting and
+    /// cancellation.
+    ///
+    /// When cancelled, the annotations written so far are flushed and counted.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container)))]
+    pub async fn export_ndjson_with<W: Write>(
+        &self,
+        writer: W,
+        options: &ExportOptions,
+    ) -> Result<usize, Error> {
+        let mut annotations = self.all_annotations().await?;
+        if let Some(progress) = &options.progress {
+            let total = self.get_container_metadata().await?.annotation_count;
+            annotations = annotations.with_progress(&**progress, Some(total));
+        }
+        if let Some(cancellation) = &options.cancellation {
+            annotations = annotations.until_cancelled(cancellation.clone());
+        }
+        write_ndjson(annotations, writer).await
+    }
+
+    /// Write the results of `query` as a table of the values at `fields`;
+    /// see [`AnnotationStream::write_table`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container)))]
+    pub async fn export_table<W: Write>(
+        &self,
+        query: &Query,
+        fields: &[&str],
+        format: TableFormat,
+        writer: W,
+    ) -> Result<usize, Error> {
+        let search = self.search(query).await?;
+
+        search
+            .annotations()
+            .write_table(fields, format, writer)
+            .await
+    }
+}
+
+async fn write_ndjson<W: Write>(
+    mut annotations: AnnotationStream<'_>,
+    mut writer: W,
+) -> Result<usize, Error> {
+    let mut count = 0;
+    while let Some(annotation) = annotations.try_next().await? {
+        serde_json::to_writer(&mut writer, &annotation).map_err(|e| Error::Io(e.into()))?;
+        writer.write_all(b"\n").map_err(Error::Io)?;
+        count += 1;
+    }
+    writer.flush().map_err(Error::Io)?;
+
+    Ok(count)
+}
+
+/// The layout of [`AnnotationStream::write_table`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TableFormat {
+    #[default]
+    Csv,
+    Tsv,
+}
+
+impl TableFormat {
+    fn delimiter(self) -> char {
+        match self {
+            Self::Csv => ',',
+            Self::Tsv => '\t',
+        }
+    }
+}
+
+impl AnnotationStream<'_> {
+    /// Write one row per annotation with the values at `fields`, dotted paths
+    /// like `body.value` or `target.source`, after a header row naming them;
+    /// returns the number of annotations written.
+    ///
+    /// Arrays along a path contribute all their items, joined by `|`.
+    /// Objects are written as JSON, and missing values as empty cells.
+    pub async fn write_table<W: Write>(
+        mut self,
+        fields: &[&str],
+        format: TableFormat,
+        mut writer: W,
+    ) -> Result<usize, Error> {
+        let delimiter = format.delimiter();
+        write_row(&mut writer, fields.iter().copied(), delimiter)?;
+        let mut count = 0;
+        while let Some(annotation) = self.try_next().await? {
+            let cells: Vec<String> = fields
+                .iter()
+                .map(|field| cell(&annotation, field))
+                .collect();
+            write_row(&mut writer, cells.iter().map(String::as_str), delimiter)?;
+            count += 1;
+        }
+        writer.flush().map_err(Error::Io)?;
+
+        Ok(count)
+    }
+}
+
+/// The values at a dotted path, descending into every item of arrays.
+fn values_at<'a>(value: &'a Value, path: &str) -> Vec<&'a Value> {
+    let mut values = vec![value];
+    for key in path.split('.') {
+        values = values
+            .into_iter()
+            .flat_map(|value| match value {
+                Value::Array(items) => items.iter().collect(),
+                value => vec![value],
+            })
+            .filter_map(|value| value.get(key))
+            .collect();
+    }
+    values
+        .into_iter()
+        .flat_map(|value| match value {
+            Value::Array(items) => items.iter().collect(),
+            value => vec![value],
+        })
+        .filter(|value| !value.is_null())
+        .collect()
+}
+
+fn cell(annotation: &Value, field: &str) -> String {
+    let values: Vec<String> = values_at(annotation, field)
+        .into_iter()
+        .map(|value| match value {
+            Value::String(s) => s.clone(),
+            value => value.to_string(),
+        })
+        .collect();
+    values.join("|")
+}
+
+fn write_row<'a, W: Write>(
+    writer: &mut W,
+    cells: impl Iterator<Item = &'a str>,
+    delimiter: char,
+) -> Result<(), Error> {
+    let mut line = String::new();
+    for (i, cell) in cells.enumerate() {
+        if i > 0 {
+            line.push(delimiter);
+        }
+        if cell.contains([delimiter, '"', '\n', '\r']) {
+            line.push('"');
+            line.push_str(&cell.replace('"', "\"\""));
+            line.push('"');
+        } else {
+            line.push_str(cell);
+        }
+    }
+    line.push('\n');
+    writer.write_all(line.as_bytes()).map_err(Error::Io)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn export_writes_one_annotation_per_line() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/services/c/search"))
+            .respond_with(
+                ResponseTemplate::new(201)
+                    .insert_header("location", format!("{}/services/c/search/s1", server.uri())),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/search/s1"))
+            .and(query_param("page", "0"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [{"id": "a1"}, {"id": "a2"}]
+            })))
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        let mut output = Vec::new();
+        let count = client.export_ndjson(&mut output).await.unwrap();
+
+        assert_eq!(count, 2);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "{\"id\":\"a1\"}\n{\"id\":\"a2\"}\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn table_has_a_column_per_field() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/services/c/search"))
+            .respond_with(
+                ResponseTemplate::new(201)
+                    .insert_header("location", format!("{}/services/c/search/s1", server.uri())),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/search/s1"))
+            .and(query_param("page", "0"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [
+                    {
+                        "body": [{"value": "Amsterdam"}, {"value": "Holland, NL"}],
+                        "target": {"source": "https://example.com/1"},
+                        "created": "2024-03-01"
+                    },
+                    {"body": {"value": "say \"hi\""}, "target": "https://example.com/2"}
+                ]
+            })))
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+        let fields = ["body.value", "target.source", "created"];
+
+        let mut output = Vec::new();
+        let count = client
+            .export_table(&Query::new(), &fields, TableFormat::Csv, &mut output)
+            .await
+            .unwrap();
+
+        assert_eq!(count, 2);
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "body.value,target.source,created\n\
+             \"Amsterdam|Holland, NL\",https://example.com/1,2024-03-01\n\
+             \"say \"\"hi\"\"\",,\n"
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use crate::{AnnoRepoClient, Error, RequestContext};
+use reqwest::Method;
+
+type Attempt = Result<(RequestContext, reqwest::Response), Error>;
+
+/// Whether the primary server couldn't be reached at all, as opposed to
+/// answering with an error.
+pub(crate) fn primary_unavailable(result: &Attempt) -> bool {
+    match result {
+        #[cfg(not(target_arch = "wasm32"))]
+        Err(Error::Request { source, .. }) => source.is_connect(),
+        // Fetch doesn't say why a request failed.
+        #[cfg(target_arch = "wasm32")]
+        Err(Error::Request { .. }) => true,
+        Err(Error::CircuitOpen(_)) => true,
+        _ => false,
+    }
+}
+
+impl AnnoRepoClient {
+    /// A copy of `request` to send to the fallbacks, if it may fail over.
+    pub(crate) fn failover_request(&self, request: &reqwest::Request) -> Option<reqwest::Request> {
+        if self.fallback_base_urls.is_empty()
+            || !matches!(*request.method(), Method::GET | Method::HEAD)
+        {
+            return None;
+        }
+        request.try_clone()
+    }
+
+    /// Send `request` to the fallbacks in turn, until one can be reached.
+    pub(crate) async fn fail_over(&self, request: reqwest::Request, primary: Attempt) -> Attempt {
+        let mut result = primary;
+        for fallback in self.fallback_base_urls.iter() {
+            let Some(request) = self.rebase(&request, fallback) else {
+                break;
+            };
+            #[cfg(feature = "tracing")]
+            tracing::warn!(url = %request.url(), "primary unavailable, reading from fallback");
+            result = self.send_once(request).await;
+            if !primary_unavailable(&result) {
+                break;
+            }
+        }
+        result
+    }
+
+    /// `request` with the client's base URL replaced by `fallback`.
+    fn rebase(&self, request: &reqwest::Request, fallback: &str) -> Option<reqwest::Request> {
+        let path = request.url().as_str().strip_prefix(&*self.base_url)?;
+        let url = reqwest::Url::parse(&format!("{fallback}{path}")).ok()?;
+        let mut request = request.try_clone()?;
+        *request.url_mut() = url;
+        Some(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    /// The URL of a port nothing listens on.
+    fn unreachable_url() -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}", listener.local_addr().unwrap())
+    }
+
+    #[tokio::test]
+    async fn reads_fail_over_and_writes_do_not() {
+        let mirror = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/metadata"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"size": 3})))
+            .mount(&mirror)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(0)
+            .mount(&mirror)
+            .await;
+        let client = AnnoRepoClient::builder(unreachable_url(), "c".to_string())
+            .fallback_base_url(mirror.uri())
+            .build()
+            .unwrap();
+
+        let metadata = client.get_container_metadata().await.unwrap();
+        let error = client
+            .create_annotation(&json!({"type": "Annotation"}), None)
+            .await
+            .unwrap_err();
+
+        assert_eq!(metadata.annotation_count, 3);
+        assert!(matches!(error, Error::Request { .. }));
+    }
+}
@@ -0,0 +1,127 @@
+use crate::{AnnoRepoClient, Error, Query};
+use futures::future::try_join_all;
+use futures::stream::{self, Stream, TryStreamExt};
+use serde_json::Value;
+
+impl AnnoRepoClient {
+    /// Run `query` in each of `containers` on this client's server, and
+    /// stream the results of all of them as they arrive, each annotation
+    /// paired with the name of its container.
+    ///
+    /// The searches are created concurrently, and their pages fetched
+    /// concurrently, so the order of the results is not deterministic. The
+    /// client's [`QueryFieldCheck`](crate::QueryFieldCheck) is applied to
+    /// each container's fields.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(containers = containers.len())))]
+    pub async fn search_containers(
+        &self,
+        containers: &[&str],
+        query: &Query,
+    ) -> Result<impl Stream<Item = Result<(String, Value), Error>> + '_, Error> {
+        // All checked before any search is created.
+        try_join_all(containers.iter().map(|&container| async move {
+            self.with_container(container)
+                .check_query_fields(query)
+                .await
+        }))
+        .await?;
+        let searches = try_join_all(containers.iter().map(|&container| async move {
+            let (search_id, _) = self.create_search_in(container, query, &[]).await?;
+            Ok::<_, Error>((container.to_string(), search_id))
+        }))
+        .await?;
+        let streams = searches.into_iter().map(|(container, search_id)| {
+            self.stream_search_result_annotations(&container, &search_id, None)
+                .map_ok(move |annotation| (container.clone(), annotation))
+        });
+
+        Ok(stream::select_all(streams))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn results_are_tagged_with_their_container() {
+        let server = MockServer::start().await;
+        for container in ["letters", "diaries"] {
+            Mock::given(method("POST"))
+                .and(path(format!("/services/{container}/search")))
+                .respond_with(ResponseTemplate::new(201).insert_header(
+                    "location",
+                    format!("{}/services/{container}/search/s1", server.uri()),
+                ))
+                .expect(1)
+                .mount(&server)
+                .await;
+            Mock::given(method("GET"))
+                .and(path(format!("/services/{container}/search/s1")))
+                .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                    "items": [{"id": format!("{container}-1")}]
+                })))
+                .mount(&server)
+                .await;
+        }
+        let client = AnnoRepoClient::new(server.uri(), "letters".to_string()).unwrap();
+
+        let mut results: Vec<(String, Value)> = client
+            .search_containers(
+                &["letters", "diaries"],
+                &Query::new().field("type", "Annotation"),
+            )
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        results.sort_by(|a, b| a.0.cmp(&b.0));
+
+        assert_eq!(
+            results,
+            [
+                ("diaries".to_string(), json!({"id": "diaries-1"})),
+                ("letters".to_string(), json!({"id": "letters-1"}))
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn each_container_gets_the_field_check() {
+        let server = MockServer::start().await;
+        for (container, fields) in [
+            ("letters", json!({"body.type": 1})),
+            ("diaries", json!({"target": 1})),
+        ] {
+            Mock::given(method("GET"))
+                .and(path(format!("/services/{container}/fields")))
+                .respond_with(ResponseTemplate::new(200).set_body_json(fields))
+                .mount(&server)
+                .await;
+        }
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(0)
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::builder(server.uri(), "letters".to_string())
+            .query_field_check(crate::QueryFieldCheck::Error)
+            .build()
+            .unwrap();
+
+        let result = client
+            .search_containers(
+                &["letters", "diaries"],
+                &Query::new().field("body.type", "Person"),
+            )
+            .await;
+
+        assert!(
+            matches!(result, Err(Error::UnknownQueryFields(fields)) if fields == ["body.type"])
+        );
+    }
+}
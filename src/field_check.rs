@@ -0,0 +1,128 @@
+use crate::{AnnoRepoClient, Error, Query};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Whether searches first check the fields in their query against
+/// [`get_fields`](AnnoRepoClient::get_fields). A misspelled field doesn't
+/// fail on the server, it just matches nothing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QueryFieldCheck {
+    #[default]
+    Off,
+    /// Log unknown fields as a warning, with the `tracing` feature.
+    Warn,
+    /// Fail with [`Error::UnknownQueryFields`].
+    Error,
+}
+
+impl AnnoRepoClient {
+    /// The fields in `query` that none of the container's annotations have,
+    /// so the query can't match. Branches of an `or` only count when none of
+    /// them can match.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container)))]
+    pub async fn unknown_query_fields(&self, query: &Query) -> Result<Vec<String>, Error> {
+        let fields = self.get_fields().await?;
+        let mut unknown = unknown_fields(query.as_json(), &fields);
+        unknown.sort();
+        unknown.dedup();
+
+        Ok(unknown)
+    }
+
+    /// Apply the client's [`QueryFieldCheck`] to `query`.
+    pub(crate) async fn check_query_fields(&self, query: &Query) -> Result<(), Error> {
+        if self.query_field_check == QueryFieldCheck::Off {
+            return Ok(());
+        }
+        let unknown = self.unknown_query_fields(query).await?;
+        match self.query_field_check {
+            _ if unknown.is_empty() => Ok(()),
+            QueryFieldCheck::Error => Err(Error::UnknownQueryFields(unknown)),
+            _ => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(container = %self.container, ?unknown, "query uses unknown fields");
+                Ok(())
+            }
+        }
+    }
+}
+
+fn unknown_fields(
+    query: &serde_json::Map<String, Value>,
+    fields: &HashMap<String, u64>,
+) -> Vec<String> {
+    let mut unknown = Vec::new();
+    for (key, value) in query {
+        match (key.as_str(), value) {
+            (":or", Value::Array(branches)) => {
+                let branches: Vec<_> = branches
+                    .iter()
+                    .filter_map(Value::as_object)
+                    .map(|branch| unknown_fields(branch, fields))
+                    .collect();
+                if branches.iter().all(|branch| !branch.is_empty()) {
+                    unknown.extend(branches.into_iter().flatten());
+                }
+            }
+            (":and", Value::Array(branches)) => {
+                for branch in branches.iter().filter_map(Value::as_object) {
+                    unknown.extend(unknown_fields(branch, fields));
+                }
+            }
+            // Other operators, such as text anchor ranges, name no field.
+            (operator, _) if operator.starts_with(':') => {}
+            (field, _) if !is_known(field, fields) => unknown.push(field.to_string()),
+            _ => {}
+        }
+    }
+    unknown
+}
+
+/// Whether `field` is in `fields`, or is an object that fields lie within.
+fn is_known(field: &str, fields: &HashMap<String, u64>) -> bool {
+    fields.contains_key(field)
+        || fields.keys().any(|known| {
+            known
+                .strip_prefix(field)
+                .is_some_and(|rest| rest.starts_with('.'))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn searches_with_misspelled_fields_fail_before_they_are_sent() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/fields"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "body.type": 10,
+                "target.source": 10,
+                "creator": 4
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(0)
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::builder(server.uri(), "c".to_string())
+            .query_field_check(QueryFieldCheck::Error)
+            .build()
+            .unwrap();
+        let query = Query::new()
+            .field("body.tpye", "Person")
+            .target_source("https://example.com/text/1")
+            .and([Query::new().creator("https://example.com/users/42")]);
+
+        let error = client.search(&query).await.unwrap_err();
+
+        assert!(matches!(error, Error::UnknownQueryFields(fields) if fields == ["body.tpye"]));
+    }
+}
@@ -0,0 +1,183 @@
+use crate::time::Instant;
+use crate::{AboutInfo, AnnoRepoClient, Error};
+use futures::future::{self, Either};
+use std::time::Duration;
+
+/// The state of the server as seen by this client, from
+/// [`AnnoRepoClient::healthy`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Health {
+    /// Whether the server answered within the timeout.
+    pub reachable: bool,
+    /// Whether the server accepts the client's API key; `None` when it has
+    /// authentication disabled or wasn't reachable.
+    pub authenticated: Option<bool>,
+    pub server_version: Option<String>,
+    /// Time taken by the `/about` request.
+    pub latency: Option<Duration>,
+    /// What went wrong, for an unhealthy server.
+    pub error: Option<String>,
+}
+
+impl Health {
+    /// Reachable, and accepting the API key if it needs one.
+    pub fn is_healthy(&self) -> bool {
+        self.reachable && self.authenticated != Some(false)
+    }
+}
+
+impl AnnoRepoClient {
+    /// Check that the server answers within `timeout`, returning how long it
+    /// took. Not retried, and never answered from a cache.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn ping(&self, timeout: Duration) -> Result<Duration, Error> {
+        let start = Instant::now();
+        self.probe::<AboutInfo>("about", timeout).await?;
+
+        Ok(start.elapsed())
+    }
+
+    /// Check that the server is reachable within `timeout` and accepts the
+    /// client's API key, for readiness probes. Never fails: problems are
+    /// reported in the result.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn healthy(&self, timeout: Duration) -> Health {
+        let start = Instant::now();
+        let about = match self.probe::<AboutInfo>("about", timeout).await {
+            Ok(about) => about,
+            Err(e) => {
+                return Health {
+                    reachable: false,
+                    authenticated: None,
+                    server_version: None,
+                    latency: None,
+                    error: Some(e.to_string()),
+                }
+            }
+        };
+        let latency = start.elapsed();
+
+        let (authenticated, error) = match (about.with_authentication, self.has_api_key) {
+            (false, _) => (None, None),
+            (true, false) => (Some(false), Some(Error::MissingApiKey.to_string())),
+            // Lists the containers the key gives access to, so it needs a valid key.
+            (true, true) => match self
+                .probe::<serde_json::Value>("my/containers", timeout)
+                .await
+            {
+                Ok(_) => (Some(true), None),
+                Err(e @ (Error::Unauthorized(_) | Error::Forbidden(_))) => {
+                    (Some(false), Some(e.to_string()))
+                }
+                Err(e) => (None, Some(e.to_string())),
+            },
+        };
+
+        Health {
+            reachable: true,
+            authenticated,
+            server_version: Some(about.version),
+            latency: Some(latency),
+            error,
+        }
+    }
+
+    /// GET `endpoint` once, failing with [`Error::Timeout`] after `timeout`.
+    async fn probe<T: serde::de::DeserializeOwned>(
+        &self,
+        endpoint: &str,
+        timeout: Duration,
+    ) -> Result<T, Error> {
+        let url = format!("{}/{endpoint}", self.base_url);
+        let request = self.client.get(url).build().map_err(Error::ReqError)?;
+        let get = async {
+            let (context, res) = self.execute_once(request).await?;
+            if !res.status().is_success() {
+                return Err(Error::from_response(context, res).await);
+            }
+            crate::response::Response::new(context, res, self.max_response_size)
+                .json()
+                .await
+        };
+
+        match future::select(Box::pin(get), Box::pin(crate::time::sleep(timeout))).await {
+            Either::Left((result, _)) => result,
+            Either::Right(_) => Err(Error::Timeout(timeout)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn server_with_authentication() -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/about"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "appName": "AnnoRepo",
+                "version": "0.7.2",
+                "startedAt": "2024-03-01T09:00:00Z",
+                "withAuthentication": true
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/my/containers"))
+            .and(header("authorization", "Bearer good"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({})))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/my/containers"))
+            .respond_with(ResponseTemplate::new(401))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    fn client(server: &MockServer, api_key: &str) -> AnnoRepoClient {
+        AnnoRepoClient::builder(server.uri(), "c".to_string())
+            .api_key(api_key)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn healthy_checks_the_api_key() {
+        let server = server_with_authentication().await;
+
+        let good = client(&server, "good")
+            .healthy(Duration::from_secs(5))
+            .await;
+        let bad = client(&server, "bad").healthy(Duration::from_secs(5)).await;
+
+        assert!(good.is_healthy());
+        assert_eq!(good.server_version.as_deref(), Some("0.7.2"));
+        assert_eq!(good.authenticated, Some(true));
+        assert!(!bad.is_healthy());
+        assert!(bad.reachable);
+        assert_eq!(bad.authenticated, Some(false));
+    }
+
+    #[tokio::test]
+    async fn slow_servers_time_out() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/about"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        let result = client.ping(Duration::from_millis(50)).await;
+        let health = client.healthy(Duration::from_millis(50)).await;
+
+        assert!(matches!(result, Err(Error::Timeout(_))));
+        assert!(!health.reachable);
+    }
+}
@@ -0,0 +1,190 @@
+use crate::{AnnoRepoClient, CreatedAnnotation, Error};
+use futures::{StreamExt, TryStreamExt};
+use serde::Serialize;
+
+/// The header [`AnnoRepoClient::create_annotation_idempotent`] sends its
+/// key in, for proxies and servers that deduplicate requests on it.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+impl AnnoRepoClient {
+    /// Create an annotation named `key`, once however often this is called:
+    /// when the container already has an annotation named `key`, such as one
+    /// stored by an earlier call whose response was lost to a timeout, that
+    /// one is returned instead. Safe to retry after any failure.
+    ///
+    /// `key` must be unique per annotation, e.g. its id in the source system
+    /// or its [`content_hash`](crate::content_hash), and valid as a name. It
+    /// is asked for as the name with a `Slug`; should the server pick
+    /// another, the annotation is deleted again, as it couldn't be found by
+    /// the key, and this fails with [`Error::SlugNotHonored`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container, key)))]
+    pub async fn create_annotation_idempotent<T: Serialize>(
+        &self,
+        annotation: &T,
+        key: &str,
+    ) -> Result<CreatedAnnotation, Error> {
+        if let Some(existing) = self.existing_annotation(key).await? {
+            return Ok(existing);
+        }
+
+        let request = self
+            .client
+            .post(self.container_url())
+            .header("Slug", key)
+            .header(IDEMPOTENCY_KEY_HEADER, key)
+            .json(annotation);
+        let created = self.send_create(request, Some(key)).await?;
+        if created.slug_honored {
+            return Ok(created);
+        }
+
+        // Either another call with the same key stored its annotation in the
+        // meantime, or the server just didn't use the key as the name.
+        self.delete_created(&created).await?;
+        match self.existing_annotation(key).await? {
+            Some(existing) => Ok(existing),
+            None => Err(Error::SlugNotHonored {
+                requested: key.to_string(),
+                assigned: created.annotation_name,
+            }),
+        }
+    }
+
+    /// [`create_annotation_idempotent`](Self::create_annotation_idempotent)
+    /// for each of `annotations`, `concurrency` at a time, with the key `key`
+    /// gives it, returning where each was stored in the same order.
+    ///
+    /// Passing [`content_hash`](crate::content_hash) as `key` makes re-running
+    /// an ingest add only the annotations that weren't stored yet.
+    ///
+    /// One request per annotation: the batch endpoint of
+    /// [`upload_annotations`](Self::upload_annotations) names annotations
+    /// itself, without taking a `Slug` per annotation, so it can't use keys.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container, count = annotations.len())))]
+    pub async fn create_annotations_idempotent<T, F>(
+        &self,
+        annotations: &[T],
+        key: F,
+        concurrency: usize,
+    ) -> Result<Vec<CreatedAnnotation>, Error>
+    where
+        T: Serialize,
+        F: Fn(&T) -> String,
+    {
+        futures::stream::iter(annotations)
+            .map(|annotation| {
+                let key = key(annotation);
+                async move { self.create_annotation_idempotent(annotation, &key).await }
+            })
+            .buffered(concurrency.max(1))
+            .try_collect()
+            .await
+    }
+
+    async fn existing_annotation(&self, name: &str) -> Result<Option<CreatedAnnotation>, Error> {
+        let status = self.annotation_exists(name).await?;
+
+        Ok(status.exists.then(|| CreatedAnnotation {
+            annotation_name: name.to_string(),
+            url: self.annotation_url(name),
+            etag: status.etag,
+            slug_honored: true,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content_hash;
+    use serde_json::json;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn annotations_stored_earlier_are_not_created_again() {
+        let server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .and(path("/w3c/c/k1"))
+            .respond_with(ResponseTemplate::new(200).insert_header("etag", "\"1\""))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(0)
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        let created = client
+            .create_annotation_idempotent(&json!({"type": "Annotation"}), "k1")
+            .await
+            .unwrap();
+
+        assert_eq!(created.url, format!("{}/w3c/c/k1", server.uri()));
+        assert_eq!(created.etag.as_deref(), Some("\"1\""));
+    }
+
+    #[tokio::test]
+    async fn new_annotations_are_named_after_their_key() {
+        let server = MockServer::start().await;
+        let annotation = json!({"type": "Annotation"});
+        let key = content_hash(&annotation);
+        Mock::given(method("HEAD"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/w3c/c/"))
+            .and(header("slug", key.as_str()))
+            .and(header("idempotency-key", key.as_str()))
+            .respond_with(
+                ResponseTemplate::new(201)
+                    .insert_header("location", format!("{}/w3c/c/{key}", server.uri())),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        let created = client
+            .create_annotations_idempotent(&[annotation], content_hash, 2)
+            .await
+            .unwrap();
+
+        assert_eq!(created[0].annotation_name, key);
+        assert!(created[0].slug_honored);
+    }
+
+    #[tokio::test]
+    async fn annotations_named_otherwise_are_deleted_again() {
+        let server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(
+                ResponseTemplate::new(201)
+                    .insert_header("location", format!("{}/w3c/c/x9", server.uri())),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/w3c/c/x9"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        let result = client
+            .create_annotation_idempotent(&json!({"type": "Annotation"}), "k1")
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(Error::SlugNotHonored { requested, assigned }) if requested == "k1" && assigned == "x9"
+        ));
+    }
+}
@@ -0,0 +1,118 @@
+use crate::{AnnoRepoClient, AnnotationExt, AnnotationStream, Error};
+use futures::TryStreamExt;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+const PRESENTATION_3_CONTEXT: &str = "http://iiif.io/api/presentation/3/context.json";
+
+/// Group annotations into IIIF Presentation 3 `AnnotationPage`s, one per
+/// targeted canvas, keyed by the canvas IRI.
+///
+/// Every IRI from [`AnnotationExt::target_sources`] counts as a canvas, with
+/// any fragment (`#xywh=…`) removed; an annotation on several canvases is put
+/// on each of their pages. `page_id` gives the `id` of the page for a canvas.
+pub fn iiif_annotation_pages<I, F>(annotations: I, page_id: F) -> BTreeMap<String, Value>
+where
+    I: IntoIterator<Item = Value>,
+    F: Fn(&str) -> String,
+{
+    let mut by_canvas: BTreeMap<String, Vec<Value>> = BTreeMap::new();
+    for mut annotation in annotations {
+        let mut canvases: Vec<String> = annotation
+            .target_sources()
+            .into_iter()
+            .map(|iri| iri.split_once('#').map_or(iri, |(canvas, _)| canvas))
+            .map(str::to_string)
+            .collect();
+        canvases.sort();
+        canvases.dedup();
+        // The page's context covers the Web Annotation one.
+        if let Value::Object(fields) = &mut annotation {
+            fields.remove("@context");
+        }
+        for canvas in canvases {
+            by_canvas
+                .entry(canvas)
+                .or_default()
+                .push(annotation.clone());
+        }
+    }
+
+    by_canvas
+        .into_iter()
+        .map(|(canvas, items)| {
+            let page = json!({
+                "@context": PRESENTATION_3_CONTEXT,
+                "id": page_id(&canvas),
+                "type": "AnnotationPage",
+                "items": items
+            });
+            (canvas, page)
+        })
+        .collect()
+}
+
+impl AnnotationStream<'_> {
+    /// Collect the annotations into IIIF annotation pages; see
+    /// [`iiif_annotation_pages`].
+    pub async fn into_iiif_pages<F>(self, page_id: F) -> Result<BTreeMap<String, Value>, Error>
+    where
+        F: Fn(&str) -> String,
+    {
+        let annotations: Vec<Value> = self.try_collect().await?;
+
+        Ok(iiif_annotation_pages(annotations, page_id))
+    }
+}
+
+impl AnnoRepoClient {
+    /// Every annotation in the container as IIIF annotation pages; see
+    /// [`iiif_annotation_pages`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container)))]
+    pub async fn export_iiif<F>(&self, page_id: F) -> Result<BTreeMap<String, Value>, Error>
+    where
+        F: Fn(&str) -> String,
+    {
+        self.all_annotations().await?.into_iiif_pages(page_id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn annotations_are_grouped_by_canvas() {
+        let annotations = [
+            json!({
+                "@context": "http://www.w3.org/ns/anno.jsonld",
+                "id": "a1",
+                "type": "Annotation",
+                "target": "https://example.com/canvas/1#xywh=0,0,10,10"
+            }),
+            json!({
+                "id": "a2",
+                "type": "Annotation",
+                "target": [
+                    "https://example.com/canvas/1#xywh=5,5,10,10",
+                    {"type": "SpecificResource", "source": "https://example.com/canvas/2"}
+                ]
+            }),
+        ];
+
+        let pages = iiif_annotation_pages(annotations, |canvas| format!("{canvas}/page"));
+
+        assert_eq!(pages.len(), 2);
+        let page = &pages["https://example.com/canvas/1"];
+        assert_eq!(page["@context"], PRESENTATION_3_CONTEXT);
+        assert_eq!(page["id"], "https://example.com/canvas/1/page");
+        assert_eq!(page["type"], "AnnotationPage");
+        assert_eq!(page["items"][0]["id"], "a1");
+        assert!(page["items"][0].get("@context").is_none());
+        assert_eq!(page["items"][1]["id"], "a2");
+        assert_eq!(
+            pages["https://example.com/canvas/2"]["items"][0]["id"],
+            "a2"
+        );
+    }
+}
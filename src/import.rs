@@ -0,0 +1,143 @@
+use crate::cancel::is_cancelled;
+use crate::{validate_annotation, AnnoRepoClient, Error, Phase, ProgressUpdate, UploadOptions};
+use serde_json::Value;
+use std::io::BufRead;
+
+impl AnnoRepoClient {
+    /// Add the annotations in newline-delimited JSON read from `reader`, as
+    /// written by [`AnnoRepoClient::export_ndjson`], returning the number
+    /// added. Blank lines are skipped.
+    ///
+    /// Only `batch_size * concurrency` annotations are held in memory at a
+    /// time. Stops at the first malformed line or failed batch; annotations
+    /// already sent stay stored. When cancelled, stops after the batches in
+    /// flight, returning the number added until then.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container)))]
+    pub async fn import_ndjson<R: BufRead>(
+        &self,
+        reader: R,
+        options: &UploadOptions,
+    ) -> Result<usize, Error> {
+        let chunk_size = options.batch_size.max(1) * options.concurrency.max(1);
+        // Progress is reported for the whole import, not per chunk.
+        // Records are validated as they are read, to report their lines.
+        let chunk_options = UploadOptions {
+            progress: None,
+            validate: false,
+            ..options.clone()
+        };
+        let report = |done: usize| {
+            if let Some(progress) = &options.progress {
+                progress.update(ProgressUpdate {
+                    phase: Phase::Importing,
+                    done: done as u64,
+                    total: None,
+                });
+            }
+        };
+        let mut chunk: Vec<Value> = Vec::with_capacity(chunk_size);
+        let mut count = 0;
+        for (index, line) in reader.lines().enumerate() {
+            let line = line.map_err(Error::Io)?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let annotation =
+                serde_json::from_str(&line).map_err(|source| Error::InvalidRecord {
+                    line: index + 1,
+                    source,
+                })?;
+            if options.validate {
+                let violations = validate_annotation(&annotation);
+                if !violations.is_empty() {
+                    return Err(Error::InvalidAnnotations(
+                        violations.into_iter().map(|v| (index + 1, v)).collect(),
+                    ));
+                }
+            }
+            chunk.push(annotation);
+            if chunk.len() == chunk_size {
+                count += self.upload_chunk(&chunk, &chunk_options).await?;
+                chunk.clear();
+                report(count);
+                if is_cancelled(&options.cancellation) {
+                    return Ok(count);
+                }
+            }
+        }
+        if !chunk.is_empty() {
+            count += self.upload_chunk(&chunk, &chunk_options).await?;
+            report(count);
+        }
+
+        Ok(count)
+    }
+
+    /// The number of annotations uploaded, or that would be in a dry run.
+    async fn upload_chunk(&self, chunk: &[Value], options: &UploadOptions) -> Result<usize, Error> {
+        let uploaded = self.upload_annotations(chunk, options).await?.len();
+        Ok(if options.dry_run {
+            chunk.len()
+        } else {
+            uploaded
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{body_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn import_uploads_each_line_in_batches() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/batch/c/annotations"))
+            .and(body_json(json!([{"id": "a1"}, {"id": "a2"}])))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                {"containerName": "c", "annotationName": "a1"},
+                {"containerName": "c", "annotationName": "a2"}
+            ])))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/batch/c/annotations"))
+            .and(body_json(json!([{"id": "a3"}])))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                {"containerName": "c", "annotationName": "a3"}
+            ])))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+        let input = "{\"id\":\"a1\"}\n{\"id\":\"a2\"}\n\n{\"id\":\"a3\"}\n";
+        let options = UploadOptions {
+            batch_size: 2,
+            concurrency: 1,
+            ..UploadOptions::default()
+        };
+
+        let count = client
+            .import_ndjson(input.as_bytes(), &options)
+            .await
+            .unwrap();
+
+        assert_eq!(count, 3);
+    }
+
+    #[tokio::test]
+    async fn malformed_lines_are_reported_with_their_number() {
+        let client = AnnoRepoClient::new("http://localhost:1", "c").unwrap();
+        let input = "{\"id\":\"a1\"}\n\n{\"id\":\n";
+
+        let result = client
+            .import_ndjson(input.as_bytes(), &UploadOptions::default())
+            .await;
+
+        assert!(matches!(result, Err(Error::InvalidRecord { line: 3, .. })));
+    }
+}
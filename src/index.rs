@@ -0,0 +1,415 @@
+use crate::encode::segment;
+use crate::model::{extra_field_names, UnknownFields};
+use crate::time::Instant;
+use crate::{AnnoRepoClient, Error};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::fmt;
+use std::time::Duration;
+
+const INITIAL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+const MAX_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The kinds of index AnnoRepo can create on an annotation field.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum IndexType {
+    Hashed,
+    Ascending,
+    Descending,
+    Text,
+    /// An index type this client doesn't know about yet.
+    Other(String),
+}
+
+impl IndexType {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Hashed => "hashed",
+            Self::Ascending => "ascending",
+            Self::Descending => "descending",
+            Self::Text => "text",
+            Self::Other(name) => name,
+        }
+    }
+}
+
+impl From<String> for IndexType {
+    fn from(name: String) -> Self {
+        match name.to_ascii_lowercase().as_str() {
+            "hashed" => Self::Hashed,
+            "ascending" => Self::Ascending,
+            "descending" => Self::Descending,
+            "text" => Self::Text,
+            _ => Self::Other(name),
+        }
+    }
+}
+
+impl From<IndexType> for String {
+    fn from(index_type: IndexType) -> Self {
+        index_type.as_str().to_string()
+    }
+}
+
+impl fmt::Display for IndexType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Build state of an index; AnnoRepo creates indexes in the background.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum IndexStatus {
+    Pending,
+    Running,
+    Done,
+    Failed,
+    #[serde(other)]
+    Unknown,
+}
+
+/// An index as listed by `/services/{container}/indexes`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IndexInfo {
+    pub field: String,
+    #[serde(rename = "type")]
+    pub index_type: IndexType,
+    /// Missing on servers that build indexes synchronously.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<IndexStatus>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// An index definition together with the URL of its resource, as returned
+/// by [`AnnoRepoClient::list_indexes`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct IndexConfig {
+    pub field: String,
+    #[serde(rename = "type")]
+    pub index_type: IndexType,
+    pub url: String,
+}
+
+impl IndexConfig {
+    /// What identifies an index: compare these to reconcile index sets.
+    pub fn key(&self) -> (&str, &IndexType) {
+        (&self.field, &self.index_type)
+    }
+}
+
+impl UnknownFields for IndexInfo {
+    fn unknown_fields(&self) -> Vec<String> {
+        extra_field_names(&self.extra)
+    }
+}
+
+impl AnnoRepoClient {
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container)))]
+    pub async fn get_indexes(&self) -> Result<Vec<IndexInfo>, Error> {
+        if let Some(indexes) = self.metadata_cache.as_ref().and_then(|c| c.indexes.get()) {
+            return Ok(indexes);
+        }
+        let url = self.resolve_service("indexes");
+
+        let indexes = self.client_get_typed(&url).await?;
+        if let Some(cache) = &self.metadata_cache {
+            cache.indexes.set(&indexes);
+        }
+        Ok(indexes)
+    }
+
+    /// The container's indexes with the URLs of their resources.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container)))]
+    pub async fn list_indexes(&self) -> Result<Vec<IndexConfig>, Error> {
+        let indexes = self.get_indexes().await?;
+
+        Ok(indexes
+            .into_iter()
+            .map(|info| IndexConfig {
+                url: info
+                    .url
+                    .unwrap_or_else(|| self.index_url(&info.field, &info.index_type)),
+                field: info.field,
+                index_type: info.index_type,
+            })
+            .collect())
+    }
+
+    /// Create an index on `field`, returning the URL of the new index resource.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container, field, index_type = %index_type)))]
+    pub async fn create_index(&self, field: &str, index_type: IndexType) -> Result<String, Error> {
+        let url = self.index_url(field, &index_type);
+
+        let res = self.send(self.client.put(&url)).await;
+        self.invalidate_container_metadata();
+
+        Ok(res?.location()?.map(str::to_string).unwrap_or(url))
+    }
+
+    /// Drop the `index_type` index on `field`.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container, field, index_type = %index_type)))]
+    pub async fn delete_index(&self, field: &str, index_type: IndexType) -> Result<(), Error> {
+        let url = self.index_url(field, &index_type);
+
+        let res = self.send(self.client.delete(url)).await;
+        self.invalidate_container_metadata();
+
+        match res {
+            Ok(_) => Ok(()),
+            Err(Error::NotFound(_)) => Err(Error::IndexNotFound {
+                field: field.to_string(),
+                index_type,
+            }),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The build state of an index. Servers that don't report a state build
+    /// indexes synchronously, so an index without one is reported as done.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container, field, index_type = %index_type)))]
+    pub async fn index_status(
+        &self,
+        field: &str,
+        index_type: IndexType,
+    ) -> Result<IndexStatus, Error> {
+        let url = self.index_url(field, &index_type);
+
+        match self.client_get_typed::<IndexInfo>(&url).await {
+            Ok(info) => Ok(info.status.unwrap_or(IndexStatus::Done)),
+            Err(Error::NotFound(_)) => Err(Error::IndexNotFound {
+                field: field.to_string(),
+                index_type,
+            }),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Poll, with increasing intervals, until the index is built.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container, field, index_type = %index_type, ?timeout)))]
+    pub async fn wait_for_index(
+        &self,
+        field: &str,
+        index_type: IndexType,
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        let deadline = Instant::now() + timeout;
+        let mut interval = INITIAL_POLL_INTERVAL;
+        loop {
+            match self.index_status(field, index_type.clone()).await? {
+                IndexStatus::Done => return Ok(()),
+                IndexStatus::Failed => {
+                    return Err(Error::IndexFailed {
+                        field: field.to_string(),
+                        index_type,
+                    })
+                }
+                IndexStatus::Pending | IndexStatus::Running | IndexStatus::Unknown => {}
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(Error::IndexTimeout {
+                    field: field.to_string(),
+                    index_type,
+                    timeout,
+                });
+            }
+            crate::time::sleep(interval.min(deadline - now)).await;
+            interval = (interval * 2).min(MAX_POLL_INTERVAL);
+        }
+    }
+
+    fn index_url(&self, field: &str, index_type: &IndexType) -> String {
+        format!(
+            "{}/{}",
+            self.resolve_service_param("indexes", field),
+            segment(index_type.as_str())
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn index_listing_is_deserialized() {
+        let indexes: Vec<IndexInfo> = serde_json::from_value(json!([
+            {"field": "body.type", "type": "hashed", "status": "DONE"},
+            {"field": "body.text", "type": "text", "status": "RUNNING"},
+            {"field": "target.source", "type": "wildcard"}
+        ]))
+        .unwrap();
+
+        assert_eq!(indexes[0].index_type, IndexType::Hashed);
+        assert_eq!(indexes[0].status, Some(IndexStatus::Done));
+        assert_eq!(indexes[1].status, Some(IndexStatus::Running));
+        assert_eq!(
+            indexes[2].index_type,
+            IndexType::Other("wildcard".to_string())
+        );
+        assert_eq!(indexes[2].status, None);
+    }
+
+    #[test]
+    fn index_type_round_trips_through_strings() {
+        for index_type in [
+            IndexType::Hashed,
+            IndexType::Ascending,
+            IndexType::Descending,
+            IndexType::Text,
+        ] {
+            assert_eq!(IndexType::from(index_type.to_string()), index_type);
+        }
+    }
+
+    #[tokio::test]
+    async fn list_indexes_fills_in_missing_urls() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/indexes"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                {"field": "body.type", "type": "hashed", "url": "https://elsewhere/indexes/1"},
+                {"field": "body.text", "type": "text"}
+            ])))
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        let indexes = client.list_indexes().await.unwrap();
+
+        assert_eq!(indexes[0].url, "https://elsewhere/indexes/1");
+        assert_eq!(
+            indexes[1].url,
+            format!("{}/services/c/indexes/body.text/text", server.uri())
+        );
+        assert_eq!(indexes[1].key(), ("body.text", &IndexType::Text));
+    }
+
+    #[tokio::test]
+    async fn cached_indexes_are_refreshed_after_a_write() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/indexes"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+            .expect(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .respond_with(ResponseTemplate::new(201))
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::builder(server.uri(), "c".to_string())
+            .metadata_cache_ttl(Duration::from_secs(60))
+            .build()
+            .unwrap();
+
+        client.get_indexes().await.unwrap();
+        client.get_indexes().await.unwrap();
+        client
+            .create_index("body.type", IndexType::Hashed)
+            .await
+            .unwrap();
+        client.get_indexes().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn create_index_returns_the_index_location() {
+        let server = MockServer::start().await;
+        let location = format!("{}/services/c/indexes/body.type/hashed", server.uri());
+        Mock::given(method("PUT"))
+            .and(path("/services/c/indexes/body.type/hashed"))
+            .respond_with(ResponseTemplate::new(201).insert_header("location", location.as_str()))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        let created = client
+            .create_index("body.type", IndexType::Hashed)
+            .await
+            .unwrap();
+
+        assert_eq!(created, location);
+    }
+
+    #[tokio::test]
+    async fn wait_for_index_polls_until_done() {
+        let server = MockServer::start().await;
+        let running = json!({"field": "body.text", "type": "text", "status": "RUNNING"});
+        let done = json!({"field": "body.text", "type": "text", "status": "DONE"});
+        Mock::given(method("GET"))
+            .and(path("/services/c/indexes/body.text/text"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(running))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/indexes/body.text/text"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(done))
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        client
+            .wait_for_index("body.text", IndexType::Text, Duration::from_secs(5))
+            .await
+            .unwrap();
+
+        assert_eq!(server.received_requests().await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn wait_for_index_gives_up_after_the_timeout() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(
+                    json!({"field": "body.text", "type": "text", "status": "PENDING"}),
+                ),
+            )
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        let result = client
+            .wait_for_index("body.text", IndexType::Text, Duration::from_millis(150))
+            .await;
+
+        assert!(matches!(result, Err(Error::IndexTimeout { .. })));
+    }
+
+    #[tokio::test]
+    async fn deleting_a_missing_index_is_reported_as_such() {
+        let server = MockServer::start().await;
+        Mock::given(method("DELETE"))
+            .and(path("/services/c/indexes/body.type/hashed"))
+            .respond_with(ResponseTemplate::new(204))
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        client
+            .delete_index("body.type", IndexType::Hashed)
+            .await
+            .unwrap();
+        let result = client.delete_index("body.type", IndexType::Text).await;
+
+        assert!(matches!(
+            result,
+            Err(Error::IndexNotFound { field, index_type: IndexType::Text }) if field == "body.type"
+        ));
+    }
+}
@@ -0,0 +1,99 @@
+use reqwest::{Method, Request, StatusCode, Url};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Hook invoked around every HTTP request made by the client.
+///
+/// Both methods have empty default implementations, so an interceptor only
+/// needs to implement the side it is interested in.
+pub trait Interceptor: Send + Sync {
+    /// Called with the fully built request just before it is sent. Headers may
+    /// be added or changed; returning an error aborts the request.
+    fn before_request(
+        &self,
+        _request: &mut Request,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        Ok(())
+    }
+
+    /// Called once the request has completed, successfully or not.
+    fn after_response(&self, _info: &ResponseInfo) {}
+}
+
+/// Summary of a completed request, passed to [`Interceptor::after_response`].
+#[derive(Debug)]
+pub struct ResponseInfo<'a> {
+    pub method: &'a Method,
+    pub url: &'a Url,
+    /// `None` when no response was received (connection failure, timeout).
+    pub status: Option<StatusCode>,
+    pub duration: Duration,
+}
+
+/// Shared by the clones of a client.
+#[derive(Clone, Default)]
+pub(crate) struct Interceptors(Arc<Vec<Arc<dyn Interceptor>>>);
+
+impl Interceptors {
+    pub(crate) fn push(&mut self, interceptor: Arc<dyn Interceptor>) {
+        Arc::make_mut(&mut self.0).push(interceptor);
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &Arc<dyn Interceptor>> {
+        self.0.iter()
+    }
+}
+
+impl fmt::Debug for Interceptors {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Interceptors({})", self.0.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AnnoRepoClient, Error};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct Abort {
+        seen: AtomicUsize,
+        responses: AtomicUsize,
+    }
+
+    impl Interceptor for Arc<Abort> {
+        fn before_request(
+            &self,
+            request: &mut Request,
+        ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            self.seen.fetch_add(1, Ordering::SeqCst);
+            assert_eq!(request.method(), Method::GET);
+            assert_eq!(
+                request.url().as_str(),
+                "https://annorepo.example.com/services/c/fields"
+            );
+            Err("injected fault".into())
+        }
+
+        fn after_response(&self, _info: &ResponseInfo) {
+            self.responses.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test]
+    async fn before_request_can_abort() {
+        let abort = Arc::new(Abort::default());
+        let client = AnnoRepoClient::builder("https://annorepo.example.com", "c")
+            .interceptor(abort.clone())
+            .build()
+            .unwrap();
+
+        let result = client.get_fields().await;
+
+        assert!(matches!(result, Err(Error::Interceptor(e)) if e.to_string() == "injected fault"));
+        assert_eq!(abort.seen.load(Ordering::SeqCst), 1);
+        assert_eq!(abort.responses.load(Ordering::SeqCst), 0);
+    }
+}
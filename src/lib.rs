@@ -1,168 +1,362 @@
+use crate::encode::segment;
+use crate::time::Instant;
+use cache::{AnnotationCache, EtagCache, MetadataCache};
+use interceptor::Interceptors;
+use page::{PageDecoder, ResultPage};
+use response::Response;
+use retry::CircuitBreaker;
 use serde_json::Value;
 use serde_json::Value::Array;
 use std::collections::HashMap;
-use std::collections::VecDeque;
-use std::fmt;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+
+mod about;
+mod accept;
+mod accessors;
+mod admin;
+mod aggregate;
+mod annotation_url;
+mod annotations;
+mod api;
+mod backup;
+#[cfg(all(feature = "blocking", target_arch = "wasm32"))]
+compile_error!("the `blocking` feature is not available on wasm32");
+
+#[cfg(feature = "blocking")]
+mod blocking;
+mod builder;
+mod cache;
+mod cancel;
+mod checksum;
+mod container;
+#[cfg(feature = "chrono")]
+mod dates;
+mod diff;
+mod distinct;
+mod encode;
+mod error;
+mod export;
+mod failover;
+mod fanout;
+mod field_check;
+mod health;
+mod idempotent;
+mod iiif;
+mod import;
+mod index;
+mod interceptor;
+mod link;
+mod macros;
+mod meta;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod model;
+mod modified;
+#[cfg(feature = "opentelemetry")]
+mod otel;
+mod page;
+pub mod prelude;
+#[cfg(all(feature = "profiles", not(target_arch = "wasm32")))]
+mod profile;
+mod progress;
+mod query;
+mod raw;
+mod request_id;
+mod response;
+mod retry;
+mod saved;
+mod schema;
+#[cfg(feature = "stam")]
+mod stam;
+mod stream;
+mod sync;
+#[cfg(all(feature = "test-support", not(target_arch = "wasm32")))]
+pub mod test_support;
+mod textrepo;
+mod time;
+mod timestamp;
+mod update;
+mod validate;
+mod version;
+mod watch;
+#[cfg(all(feature = "wire-log", not(target_arch = "wasm32")))]
+mod wire;
+
+pub use about::{AboutInfo, Capabilities};
+pub use accept::{Negotiated, ANNO_JSONLD};
+pub use accessors::{AnnotationExt, TextAnchorRange};
+pub use admin::{RejectedUserEntry, ServerSettings, UserAddResults, UserEntry};
+pub use aggregate::Counts;
+pub use annotation_url::AnnotationUrl;
+pub use annotations::{
+    AnnotationIdentifier, AnnotationStatus, CreatedAnnotation, UploadOptions, UpsertOutcome,
+};
+pub use api::{AnnoRepoApi, MaybeSendSync};
+pub use backup::{BackupHeader, RestoreOptions, RestoreReport, BACKUP_FORMAT};
+#[cfg(feature = "blocking")]
+pub use blocking::{AnnoRepoBlockingClient, BlockingAnnotations};
+pub use builder::AnnoRepoClientBuilder;
+pub use cancel::CancellationToken;
+pub use checksum::{content_hash, Checksums, VerifyReport};
+pub use container::{ContainerMetadata, ContainerPreference};
+#[cfg(feature = "chrono")]
+pub use dates::format_timestamp;
+pub use diff::{diff_containers, ContainerDiff};
+pub use distinct::DistinctValue;
+pub use error::{ApiError, Error, HttpError, RequestContext};
+pub use export::{ExportOptions, TableFormat};
+pub use field_check::QueryFieldCheck;
+pub use health::Health;
+pub use idempotent::IDEMPOTENCY_KEY_HEADER;
+pub use iiif::iiif_annotation_pages;
+pub use index::{IndexConfig, IndexInfo, IndexStatus, IndexType};
+pub use interceptor::{Interceptor, ResponseInfo};
+pub use link::{Link, Links};
+pub use meta::WithMeta;
+pub use model::{Annotation, DeserializationMode, OneOrMany, UnknownFields};
+#[cfg(all(feature = "profiles", not(target_arch = "wasm32")))]
+pub use profile::{Profile, Profiles, CONFIG_ENV};
+pub use progress::{Phase, Progress, ProgressUpdate};
+pub use query::{Query, Sort, SortDirection};
+pub use retry::{CircuitBreakerConfig, RetryPolicy};
+pub use saved::SavedQuery;
+pub use schema::{FieldSchema, FieldStats, ValueType};
+#[cfg(feature = "stam")]
+pub use stam::annotate_stam;
+pub use stream::AnnotationStream;
+pub use sync::{ConflictPolicy, SyncMode, SyncOptions, SyncReport};
+pub use textrepo::TextRepoClient;
+pub use update::{UpdateOptions, UpdateReport};
+pub use validate::{validate_annotation, Violation};
+pub use version::{ServerVersion, MAX_SERVER_VERSION, MIN_SERVER_VERSION, REGEX_SERVER_VERSION};
+pub use watch::WatchOptions;
 
 const APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
 
 const LOCATION_HEADER: &str = "location";
 
-#[derive(Debug)]
-pub enum Error {
-    UrlNotFound,
-    MalformedAnnotationPage(Value),
-    ReqError(reqwest::Error),
-}
-
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Self::UrlNotFound => write!(f, "URL not found"),
-            Self::MalformedAnnotationPage(json) => {
-                write!(f, "Malformed annotation page: {:?}", json)
-            }
-            Self::ReqError(e) => write!(f, "{}", e),
-        }
-    }
-}
-
-impl std::error::Error for Error {}
-
-#[derive(Debug)]
+/// A client for one container on an AnnoRepo server.
+///
+/// Cloning is cheap, and clones share their connection pool, caches and
+/// circuit breaker, so a single client can be kept in application state and
+/// cloned into every request handler or task. Outside wasm32, the client is
+/// `Send + Sync`.
+#[derive(Debug, Clone)]
 pub struct AnnoRepoClient {
-    base_url: String,
-    container: String,
-    // api_key: String,
+    base_url: Arc<str>,
+    fallback_base_urls: Arc<[String]>,
+    container: Arc<str>,
+    has_api_key: bool,
     client: reqwest::Client,
+    interceptors: Interceptors,
+    retry_policy: Option<RetryPolicy>,
+    circuit_breaker: Option<Arc<CircuitBreaker>>,
+    deserialization_mode: DeserializationMode,
+    query_field_check: QueryFieldCheck,
+    metadata_cache: Option<Arc<MetadataCache>>,
+    etag_cache: Option<Arc<EtagCache>>,
+    annotation_cache: Option<Arc<AnnotationCache>>,
+    request_id_header: Option<reqwest::header::HeaderName>,
+    gzip_uploads: bool,
+    max_response_size: Option<u64>,
+    #[cfg(all(feature = "wire-log", not(target_arch = "wasm32")))]
+    wire_log: Option<usize>,
+    capabilities: Arc<Mutex<Option<Capabilities>>>,
 }
 
 impl AnnoRepoClient {
     pub fn new<S: Into<String>>(base_url: S, container: S) -> Result<Self, Error> {
-        let annorepo_client = Self {
-            base_url: base_url.into(),
-            container: container.into(),
-            // api_key: "".into(),
-            client: reqwest::ClientBuilder::new()
-                .user_agent(APP_USER_AGENT)
-                .connection_verbose(true)
-                .build()
-                .unwrap(),
-        };
+        Self::builder(base_url, container).build()
+    }
 
-        Ok(annorepo_client)
+    pub fn builder<S: Into<String>>(base_url: S, container: S) -> AnnoRepoClientBuilder {
+        AnnoRepoClientBuilder::new(base_url.into(), container.into())
     }
 
-    pub async fn get_about(&self) -> Result<Value, reqwest::Error> {
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn get_about(&self) -> Result<AboutInfo, Error> {
+        if let Some(about) = self.metadata_cache.as_ref().and_then(|c| c.about.get()) {
+            return Ok(about);
+        }
         let url = format!("{}/about", self.base_url);
-        Ok(self.client.get(url).send().await?.json().await?)
+
+        let about = self.client_get_typed(&url).await?;
+        if let Some(cache) = &self.metadata_cache {
+            cache.about.set(&about);
+        }
+        Ok(about)
     }
 
-    pub async fn get_fields(&self) -> Result<Value, reqwest::Error> {
+    /// The fields used in the container's annotations, with the number of
+    /// annotations each occurs in.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container)))]
+    pub async fn get_fields(&self) -> Result<HashMap<String, u64>, Error> {
+        if let Some(fields) = self.metadata_cache.as_ref().and_then(|c| c.fields.get()) {
+            return Ok(fields);
+        }
         let url = self.resolve_service("fields");
 
-        Ok(self.client_get_json(&url).await?)
+        let fields = self.client_get_json(&url).await?;
+        if let Some(cache) = &self.metadata_cache {
+            cache.fields.set(&fields);
+        }
+        Ok(fields)
     }
 
-    pub async fn get_indexes(&self) -> Result<Value, reqwest::Error> {
-        let url = self.resolve_service("indexes");
+    /// The names of the fields used in the container, sorted.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container)))]
+    pub async fn get_field_names(&self) -> Result<Vec<String>, Error> {
+        let mut names: Vec<String> = self.get_fields().await?.into_keys().collect();
+        names.sort();
 
-        Ok(self.client_get_json(&url).await?)
+        Ok(names)
     }
 
-    pub async fn get_distinct_values(&self, field: &str) -> Result<Value, reqwest::Error> {
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container, field)))]
+    pub async fn get_distinct_values(&self, field: &str) -> Result<Value, Error> {
         let url = self.resolve_service_param("distinct-values", field);
 
-        Ok(self.client_get_json(&url).await?)
+        self.client_get_json(&url).await
     }
 
-    pub async fn create_search(&self, query: HashMap<&str, &str>) -> Result<SearchInfo, Error> {
-        let url = self.resolve_service("search");
-
-        let res = self
-            .client
-            .post(url)
-            .json(&query)
-            .send()
-            .await
-            .map_err(|e| Error::ReqError(e))?;
-
-        if let Some(header) = res.headers().get(LOCATION_HEADER) {
-            let location = header.to_str().expect("Header must be valid unicode");
-            let search_id = location.rsplit_once('/').unwrap().1;
-
-            Ok(SearchInfo::new(
-                self,
-                search_id.to_string(),
-                location.to_string(),
-            ))?
-        } else {
-            Err(Error::UrlNotFound)
-        }
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container)))]
+    pub async fn create_search(&self, query: HashMap<&str, &str>) -> Result<SearchInfo<'_>, Error> {
+        self.search(&Query::from(query)).await
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = container_name, search_id)))]
     pub async fn read_search_info(
         &self,
         container_name: &str,
         search_id: &str,
-    ) -> Result<Value, reqwest::Error> {
-        let url = format!(
-            "{base}/services/{container_name}/search/{search_id}/info",
-            base = &self.base_url
-        );
-        Ok(self.client_get_json(&url).await?)
+    ) -> Result<Value, Error> {
+        let url = format!("{}/info", self.search_url(container_name, search_id));
+        self.client_get_json(&url).await
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = container_name, search_id, ?page)))]
     pub async fn read_search_result_page(
         &self,
         container_name: &str,
         search_id: &str,
         page: Option<u32>,
-    ) -> Result<Value, reqwest::Error> {
-        let search_url = format!(
-            "{base}/services/{container_name}/search/{search_id}",
-            base = &self.base_url
-        );
-        let params = [("page", page.unwrap_or(0).to_string())];
-        let url = reqwest::Url::parse_with_params(&search_url, &params).unwrap();
-        println!("read_search_result_page: url={:?}", url);
-
-        Ok(self.client.get(url).send().await?.json().await?)
+    ) -> Result<Value, Error> {
+        let page = self
+            .fetch_result_page(
+                container_name,
+                search_id,
+                page.unwrap_or(0),
+                &mut PageDecoder::default(),
+            )
+            .await?;
+
+        Ok(page.into_value())
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = container_name, search_id, ?start_page)))]
     pub async fn read_search_result_annotations(
         &self,
         container_name: &str,
         search_id: &str,
         start_page: Option<u32>,
-    ) -> Result<AnnoIter, Error> {
-        Ok(AnnoIter::new(
-            self,
-            container_name,
-            search_id,
-            start_page.unwrap_or(0),
-        ))?
-        .await
+    ) -> Result<AnnoIter<'_>, Error> {
+        AnnoIter::new(self, container_name, search_id, start_page.unwrap_or(0)).await
     }
 
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = container_name, search_id, ?start_page)))]
     pub async fn foreach_search_result_annotation(
         &self,
         container_name: &str,
         search_id: &str,
         start_page: Option<u32>,
-        f: &dyn Fn(&Value) -> (),
+        f: &dyn Fn(&Value),
     ) -> Result<(), Error> {
         let annotation_page = &self
             .read_search_result_page(container_name, search_id, start_page)
-            .await
-            .unwrap();
+            .await?;
         if let Array(annos) = &annotation_page["items"] {
             for anno in annos {
-                f(&anno);
+                f(anno);
             }
             Ok(())
         } else {
-            Err(Error::MalformedAnnotationPage(annotation_page.clone()))
+            Err(Error::MalformedAnnotationPage {
+                url: self.search_url(container_name, search_id),
+                page: annotation_page.clone(),
+            })
+        }
+    }
+
+    /// Called after every write to the container, successful or not.
+    fn invalidate_container_metadata(&self) {
+        if let Some(cache) = &self.metadata_cache {
+            cache.invalidate_container();
+        }
+    }
+
+    async fn fetch_result_page(
+        &self,
+        container_name: &str,
+        search_id: &str,
+        page: u32,
+        decoder: &mut PageDecoder,
+    ) -> Result<ResultPage, Error> {
+        let search_url = self.search_url(container_name, search_id);
+        let params = [("page", page.to_string())];
+        let url = reqwest::Url::parse_with_params(&search_url, &params)?;
+
+        self.send(self.client.get(url))
+            .await?
+            .result_page(decoder)
+            .await
+    }
+
+    /// The items on a page of search results, and whether there are more pages.
+    ///
+    /// Reusing `decoder` for the pages of a search saves reallocating its
+    /// buffers for every page.
+    pub(crate) async fn read_result_items(
+        &self,
+        container_name: &str,
+        search_id: &str,
+        page: u32,
+        decoder: &mut PageDecoder,
+    ) -> Result<(Vec<Value>, bool), Error> {
+        let page = self
+            .fetch_result_page(container_name, search_id, page, decoder)
+            .await?;
+        if page.rest.contains_key("items") {
+            return Err(Error::MalformedAnnotationPage {
+                url: self.search_url(container_name, search_id),
+                page: page.into_value(),
+            });
+        }
+        let has_next = !page.items.is_empty() && page.has_next();
+
+        Ok((page.items, has_next))
+    }
+
+    fn search_url(&self, container_name: &str, search_id: &str) -> String {
+        format!(
+            "{base}/services/{container}/search/{search_id}",
+            base = self.base_url,
+            container = segment(container_name),
+            search_id = segment(search_id)
+        )
+    }
+
+    /// A client for another container on the same server, sharing this
+    /// one's connections and configuration.
+    pub(crate) fn with_container(&self, container: &str) -> Self {
+        Self {
+            container: container.into(),
+            // What it holds is specific to the container.
+            metadata_cache: self
+                .metadata_cache
+                .as_ref()
+                .map(|cache| Arc::new(MetadataCache::new(cache.ttl()))),
+            ..self.clone()
         }
     }
 
@@ -170,7 +364,7 @@ impl AnnoRepoClient {
         format!(
             "{base}/services/{container}/{endpoint}",
             base = self.base_url,
-            container = self.container
+            container = segment(&self.container)
         )
     }
 
@@ -178,25 +372,225 @@ impl AnnoRepoClient {
         format!(
             "{base}/services/{container}/{endpoint}/{param}",
             base = self.base_url,
-            container = self.container
+            container = segment(&self.container),
+            param = segment(param)
         )
     }
 
-    async fn client_get_json<T>(&self, url: &str) -> Result<T, reqwest::Error>
+    async fn client_get_json<T>(&self, url: &str) -> Result<T, Error>
     where
         T: serde::de::DeserializeOwned,
     {
-        Ok(self.client.get(url).send().await?.json().await?)
+        let (request, body) = self.get_body(url).await?;
+        response::decode(&request, &body)
+    }
+
+    async fn client_get_typed<T>(&self, url: &str) -> Result<T, Error>
+    where
+        T: serde::de::DeserializeOwned + UnknownFields,
+    {
+        let (request, body) = self.get_body(url).await?;
+        let value = response::decode(&request, &body)?;
+        response::check_fields(request, value, self.deserialization_mode)
+    }
+
+    /// GET `url`, through the ETag cache if there is one.
+    async fn get_body(&self, url: &str) -> Result<(RequestContext, Vec<u8>), Error> {
+        let Some(cache) = &self.etag_cache else {
+            return self.send(self.client.get(url)).await?.body().await;
+        };
+        let mut request = self.client.get(url);
+        let etag = cache.etag(url);
+        if let Some(etag) = &etag {
+            request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+        }
+
+        let (context, res) = self.execute(request).await?;
+        if res.status() == reqwest::StatusCode::NOT_MODIFIED {
+            return match etag.and_then(|etag| cache.body(url, &etag)) {
+                Some(body) => Ok((context, body)),
+                // Evicted while the request was in flight.
+                None => self.send(self.client.get(url)).await?.body().await,
+            };
+        }
+        if !res.status().is_success() {
+            return Err(Error::from_response(context, res).await);
+        }
+        let res = Response::new(context, res, self.max_response_size);
+        let etag = res
+            .headers()
+            .get(reqwest::header::ETAG)
+            .and_then(|etag| etag.to_str().ok())
+            .map(str::to_string);
+        let (context, body) = res.body().await?;
+        match etag {
+            Some(etag) => cache.insert(url, etag, body.clone()),
+            None => cache.remove(url),
+        }
+
+        Ok((context, body))
+    }
+
+    /// Execute the request and turn any non-success status into an [`Error`].
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<Response, Error> {
+        let (context, res) = self.execute(request).await?;
+        if res.status().is_success() {
+            Ok(Response::new(context, res, self.max_response_size))
+        } else {
+            Err(Error::from_response(context, res).await)
+        }
+    }
+
+    async fn execute(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<(RequestContext, reqwest::Response), Error> {
+        let mut request = request.build().map_err(Error::ReqError)?;
+        if let Some(name) = &self.request_id_header {
+            if !request.headers().contains_key(name) {
+                let id = request_id::new_request_id();
+                if let Ok(value) = reqwest::header::HeaderValue::from_str(&id) {
+                    request.headers_mut().insert(name.clone(), value);
+                }
+            }
+        }
+        let failover = self.failover_request(&request);
+        let mut attempt = 0;
+        let result = loop {
+            // Requests with a streaming body can't be cloned, and so can't be retried.
+            let retry = self
+                .retry_policy
+                .as_ref()
+                .filter(|policy| policy.allows(request.method(), attempt))
+                .and_then(|_| request.try_clone());
+
+            let result = self.execute_once(request).await;
+
+            match (retry, &self.retry_policy) {
+                (Some(next), Some(policy)) if retry::is_retryable(&result) => {
+                    crate::time::sleep(policy.backoff(attempt)).await;
+                    attempt += 1;
+                    request = next;
+                }
+                _ => break result,
+            }
+        };
+
+        match failover {
+            Some(request) if failover::primary_unavailable(&result) => {
+                self.fail_over(request, result).await
+            }
+            _ => result,
+        }
+    }
+
+    async fn execute_once(
+        &self,
+        request: reqwest::Request,
+    ) -> Result<(RequestContext, reqwest::Response), Error> {
+        let Some(breaker) = &self.circuit_breaker else {
+            return self.send_once(request).await;
+        };
+        // A call failed fast says nothing about the server, so isn't recorded.
+        breaker.check()?;
+        let result = self.send_once(request).await;
+        breaker.record(&result);
+        result
+    }
+
+    /// Send `request` once, past the interceptors, without the circuit
+    /// breaker.
+    async fn send_once(
+        &self,
+        mut request: reqwest::Request,
+    ) -> Result<(RequestContext, reqwest::Response), Error> {
+        for interceptor in self.interceptors.iter() {
+            interceptor
+                .before_request(&mut request)
+                .map_err(Error::Interceptor)?;
+        }
+
+        let context = RequestContext {
+            method: request.method().clone(),
+            url: request.url().clone(),
+            request_id: self
+                .request_id_header
+                .as_ref()
+                .and_then(|name| request.headers().get(name))
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string),
+        };
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(
+            "annorepo_request",
+            otel.name = %context.method,
+            otel.kind = "client",
+            http.request.method = %context.method,
+            url.full = %context.url,
+            request_id = context.request_id.as_deref(),
+            http.response.status_code = tracing::field::Empty,
+        );
+        #[cfg(feature = "opentelemetry")]
+        otel::inject(&span, request.headers_mut());
+        #[cfg(all(feature = "wire-log", not(target_arch = "wasm32")))]
+        if let Some(max_body) = self.wire_log {
+            let _entered = span.enter();
+            wire::log_request(&request, max_body);
+        }
+
+        let start = Instant::now();
+        #[cfg(feature = "tracing")]
+        let result =
+            tracing::Instrument::instrument(self.client.execute(request), span.clone()).await;
+        #[cfg(not(feature = "tracing"))]
+        let result = self.client.execute(request).await;
+
+        let info = ResponseInfo {
+            method: &context.method,
+            url: &context.url,
+            status: result.as_ref().ok().map(|res| res.status()),
+            duration: start.elapsed(),
+        };
+        for interceptor in self.interceptors.iter() {
+            interceptor.after_response(&info);
+        }
+        #[cfg(feature = "tracing")]
+        if let Some(status) = info.status {
+            span.record("http.response.status_code", status.as_u16());
+        }
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            parent: &span,
+            method = %info.method,
+            url = %info.url,
+            status = info.status.map(|status| status.as_u16()),
+            duration_ms = info.duration.as_millis() as u64,
+            "AnnoRepo request"
+        );
+        #[cfg(feature = "metrics")]
+        metrics::record(&self.base_url, &info);
+
+        #[cfg(all(feature = "wire-log", not(target_arch = "wasm32")))]
+        let result = match self.wire_log {
+            Some(max_body) => {
+                let _entered = span.enter();
+                result.map(|res| wire::log_response(res, max_body))
+            }
+            None => result,
+        };
+
+        match result {
+            Ok(res) => Ok((context, res)),
+            Err(e) => Err(Error::request(context, e)),
+        }
     }
 }
 
+/// The annotations on one page of search results.
 #[derive(Debug)]
 pub struct AnnoIter<'a> {
-    client: &'a AnnoRepoClient,
-    url: String,
-    cur_page: u32,
-    cur_anno: usize,
-    annotations: VecDeque<Value>,
+    annotations: std::vec::IntoIter<Value>,
+    client: PhantomData<&'a AnnoRepoClient>,
 }
 
 impl<'a> AnnoIter<'a> {
@@ -206,48 +600,36 @@ impl<'a> AnnoIter<'a> {
         search_id: &str,
         start_page: u32,
     ) -> Result<Self, Error> {
-        let search_url = format!(
-            "{base}/services/{container_name}/search/{search_id}",
-            base = client.base_url
-        );
-        let mut annotation_page = client
-            .read_search_result_page(container_name, search_id, Some(start_page))
-            .await
-            .unwrap();
-        let item = annotation_page["items"].take();
-        // if let Array(annos) = annotation_page["items"].take() {
-        if let Array(annos) = item {
-            Ok(Self {
-                client,
-                url: search_url,
-                cur_page: start_page,
-                cur_anno: 0,
-                annotations: annos.into(),
-            })
-        } else {
-            Err(Error::MalformedAnnotationPage(annotation_page))
-        }
+        let (annotations, _) = client
+            .read_result_items(
+                container_name,
+                search_id,
+                start_page,
+                &mut PageDecoder::default(),
+            )
+            .await?;
+
+        Ok(Self {
+            annotations: annotations.into_iter(),
+            client: PhantomData,
+        })
     }
 }
 
-impl<'a> Iterator for AnnoIter<'a> {
+impl Iterator for AnnoIter<'_> {
     type Item = Value;
 
     fn next(&mut self) -> Option<Self::Item> {
-        println!("cur={}, size={}", self.cur_anno, self.annotations.len());
-        // if self.cur_anno < self.annotations.len() {
-        //     let anno = self.annotations.get(self.cur_anno).unwrap().clone();
-        //     self.cur_anno += 1;
-        //     return Some(anno);
-        // }
-        while let Some(anno) = self.annotations.pop_front() {
-            println!("cur={}, left={}", anno, self.annotations.len());
-            return Some(anno);
-        }
-        None
+        self.annotations.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.annotations.size_hint()
     }
 }
 
+impl ExactSizeIterator for AnnoIter<'_> {}
+
 #[derive(Debug)]
 pub struct SearchInfo<'a> {
     client: &'a AnnoRepoClient,
@@ -281,7 +663,13 @@ impl<'a> SearchInfo<'a> {
 
 #[cfg(test)]
 mod tests {
-    use crate::AnnoRepoClient;
+    use crate::{AnnoRepoClient, CircuitBreakerConfig, DeserializationMode, Error, RetryPolicy};
+    use reqwest::StatusCode;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::time::Duration;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
 
     #[test]
     fn client_is_setup_properly() {
@@ -289,7 +677,267 @@ mod tests {
         let container = "example-container-1.0a";
         let client = AnnoRepoClient::new(base_url, container).unwrap();
 
-        assert_eq!(client.base_url, base_url);
-        assert_eq!(client.container, container);
+        assert_eq!(&*client.base_url, base_url);
+        assert_eq!(&*client.container, container);
+    }
+
+    #[tokio::test]
+    async fn clones_share_their_state() {
+        fn assert_shareable<T: Clone + Send + Sync + 'static>() {}
+        assert_shareable::<AnnoRepoClient>();
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/fields"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"body.type": 3})))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::builder(server.uri(), "c".to_string())
+            .metadata_cache_ttl(Duration::from_secs(60))
+            .build()
+            .unwrap();
+        let clone = client.clone();
+
+        client.get_fields().await.unwrap();
+        let handle = tokio::spawn(async move { clone.get_fields().await });
+
+        assert_eq!(handle.await.unwrap().unwrap()["body.type"], 3);
+    }
+
+    #[tokio::test]
+    async fn error_status_is_checked_before_deserializing() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/fields"))
+            .respond_with(ResponseTemplate::new(401).set_body_string("<html>Unauthorized</html>"))
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        let result = client.get_fields().await;
+
+        match result {
+            Err(Error::Unauthorized(e)) => {
+                assert_eq!(e.status, StatusCode::UNAUTHORIZED);
+                assert_eq!(e.body, "<html>Unauthorized</html>");
+                assert_eq!(e.request.method, "GET");
+                assert_eq!(e.request.url.path(), "/services/c/fields");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn create_search_rejects_malformed_location() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/services/c/search"))
+            .respond_with(ResponseTemplate::new(201).insert_header("location", "no-slashes-here"))
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        let result = client.create_search(HashMap::new()).await;
+
+        assert!(matches!(result, Err(Error::InvalidLocation(l)) if l == "no-slashes-here"));
+    }
+
+    fn quick_retries() -> RetryPolicy {
+        RetryPolicy {
+            initial_backoff: Duration::from_millis(1),
+            ..RetryPolicy::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn idempotent_requests_are_retried() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/fields"))
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/fields"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"body.type": 3})))
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::builder(server.uri(), "c".to_string())
+            .retry_policy(quick_retries())
+            .build()
+            .unwrap();
+
+        let fields = client.get_fields().await.unwrap();
+
+        assert_eq!(fields, HashMap::from([("body.type".to_string(), 3)]));
+    }
+
+    #[tokio::test]
+    async fn field_names_are_sorted() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/fields"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "target.source": 10,
+                "body.type": 10,
+                "body.purpose": 4
+            })))
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        let names = client.get_field_names().await.unwrap();
+
+        assert_eq!(names, ["body.purpose", "body.type", "target.source"]);
+    }
+
+    #[tokio::test]
+    async fn post_is_not_retried_by_default() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/services/c/search"))
+            .respond_with(ResponseTemplate::new(503))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::builder(server.uri(), "c".to_string())
+            .retry_policy(quick_retries())
+            .build()
+            .unwrap();
+
+        let result = client.create_search(HashMap::new()).await;
+
+        assert_eq!(
+            result.unwrap_err().status(),
+            Some(StatusCode::SERVICE_UNAVAILABLE)
+        );
+    }
+
+    #[tokio::test]
+    async fn retries_reuse_the_request_id() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/fields"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::builder(server.uri(), "c".to_string())
+            .retry_policy(quick_retries())
+            .request_id_header(reqwest::header::HeaderName::from_static("x-request-id"))
+            .build()
+            .unwrap();
+
+        let error = client.get_fields().await.unwrap_err();
+
+        let requests = server.received_requests().await.unwrap();
+        assert!(requests.len() > 1);
+        let id = &requests[0].headers["x-request-id"];
+        assert!(requests.iter().all(|r| r.headers["x-request-id"] == id));
+        match error {
+            Error::Http(e) => {
+                assert_eq!(e.request.request_id.as_deref(), Some(id.to_str().unwrap()))
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn circuit_opens_after_repeated_server_errors() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(2)
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::builder(server.uri(), "c".to_string())
+            .circuit_breaker(CircuitBreakerConfig {
+                failure_threshold: 2,
+                cooldown: Duration::from_secs(60),
+            })
+            .build()
+            .unwrap();
+
+        for _ in 0..2 {
+            assert!(matches!(client.get_fields().await, Err(Error::Http(_))));
+        }
+
+        for _ in 0..3 {
+            assert!(matches!(
+                client.get_fields().await,
+                Err(Error::CircuitOpen(_))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn strict_mode_rejects_unknown_fields() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/about"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "appName": "AnnoRepo",
+                "version": "0.7.2",
+                "startedAt": "2024-03-01T09:00:00Z",
+                "withAuthentication": false,
+                "grpcPort": 8000
+            })))
+            .mount(&server)
+            .await;
+        let lenient = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+        let strict = AnnoRepoClient::builder(server.uri(), "c".to_string())
+            .deserialization_mode(DeserializationMode::Strict)
+            .build()
+            .unwrap();
+
+        let about = lenient.get_about().await.unwrap();
+        assert_eq!(about.extra["grpcPort"], 8000);
+
+        match strict.get_about().await {
+            Err(Error::UnknownFields { fields, .. }) => assert_eq!(fields, ["grpcPort"]),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn oversized_responses_are_not_read() {
+        let server = MockServer::start().await;
+        let items: Vec<_> = (0..100).map(|i| json!({"id": format!("a{i}")})).collect();
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"items": items})))
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::builder(server.uri(), "c".to_string())
+            .max_response_size(1024)
+            .build()
+            .unwrap();
+
+        let page = client.read_search_result_page("c", "s1", None).await;
+        let fields = client.get_fields().await;
+
+        assert!(matches!(
+            page,
+            Err(Error::ResponseTooLarge { max_size: 1024, .. })
+        ));
+        assert!(matches!(fields, Err(Error::ResponseTooLarge { .. })));
+    }
+
+    #[tokio::test]
+    async fn names_are_encoded_in_urls() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(
+                "/services/brieven%202024/distinct-values/body.value%20nl",
+            ))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!(["ja"])))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "brieven 2024".to_string()).unwrap();
+
+        let values = client.get_distinct_values("body.value nl").await.unwrap();
+
+        assert_eq!(values, json!(["ja"]));
     }
 }
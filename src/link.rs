@@ -0,0 +1,207 @@
+//! `Link` headers (RFC 8288), by which LDP servers announce resource types,
+//! constraints and paging.
+
+use crate::{AnnoRepoClient, Error};
+use reqwest::header::{HeaderMap, LINK};
+use std::collections::BTreeMap;
+
+/// One link of a `Link` header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    pub uri: String,
+    /// The relation types of the link; a link can have several.
+    pub rel: Vec<String>,
+    /// The other parameters, by lowercase name, unquoted.
+    pub params: BTreeMap<String, String>,
+}
+
+impl Link {
+    fn has_rel(&self, rel: &str) -> bool {
+        self.rel.iter().any(|r| r.eq_ignore_ascii_case(rel))
+    }
+}
+
+/// The links of a response.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Links(pub Vec<Link>);
+
+impl Links {
+    /// The links of every `Link` header in `headers`, skipping malformed ones.
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        Self(
+            headers
+                .get_all(LINK)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .flat_map(parse)
+                .collect(),
+        )
+    }
+
+    /// The targets of the links with relation type `rel`.
+    pub fn targets(&self, rel: &str) -> Vec<&str> {
+        self.0
+            .iter()
+            .filter(|link| link.has_rel(rel))
+            .map(|link| link.uri.as_str())
+            .collect()
+    }
+
+    /// The target of the first link with relation type `rel`.
+    pub fn get(&self, rel: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|link| link.has_rel(rel))
+            .map(|link| link.uri.as_str())
+    }
+
+    /// The `type` links, such as `http://www.w3.org/ns/ldp#BasicContainer`.
+    pub fn types(&self) -> Vec<&str> {
+        self.targets("type")
+    }
+
+    /// Where the constraints on creating resources are described.
+    pub fn constrained_by(&self) -> Option<&str> {
+        self.get("http://www.w3.org/ns/ldp#constrainedBy")
+    }
+
+    pub fn first(&self) -> Option<&str> {
+        self.get("first")
+    }
+
+    pub fn last(&self) -> Option<&str> {
+        self.get("last")
+    }
+
+    pub fn next(&self) -> Option<&str> {
+        self.get("next")
+    }
+
+    pub fn prev(&self) -> Option<&str> {
+        self.get("prev")
+    }
+}
+
+/// Split a header value into links, honouring quoted strings.
+fn parse(value: &str) -> Vec<Link> {
+    split_unquoted(value, ',')
+        .into_iter()
+        .filter_map(|link| {
+            let mut parts = split_unquoted(link, ';').into_iter();
+            let uri = parts.next()?.trim();
+            let uri = uri.strip_prefix('<')?.strip_suffix('>')?.to_string();
+            let mut rel = Vec::new();
+            let mut params = BTreeMap::new();
+            for param in parts {
+                let (name, value) = param.split_once('=').unwrap_or((param, ""));
+                let name = name.trim().to_ascii_lowercase();
+                let value = unquote(value.trim());
+                if name == "rel" {
+                    rel.extend(value.split_whitespace().map(str::to_string));
+                } else {
+                    params.insert(name, value);
+                }
+            }
+            Some(Link { uri, rel, params })
+        })
+        .collect()
+}
+
+fn split_unquoted(value: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut in_uri = false;
+    let mut escaped = false;
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' if !in_uri => quoted = !quoted,
+            '<' if !quoted => in_uri = true,
+            '>' if !quoted => in_uri = false,
+            c if c == separator && !quoted && !in_uri => {
+                parts.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+fn unquote(value: &str) -> String {
+    match value.strip_prefix('"').and_then(|v| v.strip_suffix('"')) {
+        Some(inner) => inner.replace("\\\"", "\"").replace("\\\\", "\\"),
+        None => value.to_string(),
+    }
+}
+
+impl AnnoRepoClient {
+    /// The links the server sends for the container.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container)))]
+    pub async fn get_container_links(&self) -> Result<Links, Error> {
+        let url = self.container_url();
+        self.head_links(url).await
+    }
+
+    /// The links the server sends for an annotation.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container, annotation_name)))]
+    pub async fn get_annotation_links(&self, annotation_name: &str) -> Result<Links, Error> {
+        let url = self.annotation_url(annotation_name);
+        self.head_links(url).await
+    }
+
+    async fn head_links(&self, url: String) -> Result<Links, Error> {
+        let res = self.send(self.client.head(url)).await?;
+        Ok(Links::from_headers(res.headers()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn links_are_split_outside_quotes_and_uris() {
+        let links = parse(concat!(
+            r#"<http://www.w3.org/ns/ldp#BasicContainer>; rel="type", "#,
+            r#"<http://example.com/c?page=1,2>; rel="next"; title="a; b, c", "#,
+            r#"<http://example.com/constraints>; rel="http://www.w3.org/ns/ldp#constrainedBy""#,
+        ));
+
+        assert_eq!(links.len(), 3);
+        assert_eq!(links[1].uri, "http://example.com/c?page=1,2");
+        assert_eq!(links[1].rel, ["next"]);
+        assert_eq!(links[1].params["title"], "a; b, c");
+    }
+
+    #[tokio::test]
+    async fn container_links_are_read_from_the_headers() {
+        let server = MockServer::start().await;
+        Mock::given(method("HEAD"))
+            .and(path("/w3c/c/"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .append_header("link", r#"<http://www.w3.org/ns/ldp#BasicContainer>; rel="type""#)
+                    .append_header(
+                        "link",
+                        r#"<http://www.w3.org/TR/annotation-protocol/>; rel="http://www.w3.org/ns/ldp#constrainedBy""#,
+                    ),
+            )
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        let links = client.get_container_links().await.unwrap();
+
+        assert_eq!(links.types(), ["http://www.w3.org/ns/ldp#BasicContainer"]);
+        assert_eq!(
+            links.constrained_by(),
+            Some("http://www.w3.org/TR/annotation-protocol/")
+        );
+    }
+}
@@ -0,0 +1,154 @@
+/// Build a [`Query`](crate::Query) from JSON-like syntax, checking its
+/// structure and operator names at compile time.
+///
+/// A field is matched against a value, or against an object of conditions
+/// keyed by operator. The operators are `"!="`, `"<"`, `"<="`, `">"`,
+/// `">="`, `"in"`, `"not in"`, `"regex"` and `"contains"`, or their names in
+/// the query language, such as `":isGreaterThanOrEqualTo"`. `":or"` and
+/// `":and"` take an array of nested queries.
+///
+/// ```
+/// use annorepo_client::{anno_query, Query};
+///
+/// let query = anno_query!({
+///     "body.purpose": "tagging",
+///     "start": { ">=": 100, "<": 200 },
+///     ":or": [{ "body.value": "letter" }, { "body.value": "postcard" }]
+/// });
+///
+/// assert_eq!(
+///     query,
+///     Query::new()
+///         .field("body.purpose", "tagging")
+///         .greater_than_or_equal("start", 100)
+///         .less_than("start", 200)
+///         .or([
+///             Query::new().field("body.value", "letter"),
+///             Query::new().field("body.value", "postcard"),
+///         ])
+/// );
+/// ```
+///
+/// An unknown operator doesn't compile:
+///
+/// ```compile_fail
+/// use annorepo_client::anno_query;
+///
+/// let query = anno_query!({ "start": { "=>": 100 } });
+/// ```
+#[macro_export]
+macro_rules! anno_query {
+    ({ $($fields:tt)* }) => {
+        $crate::__anno_query!(@fields $crate::Query::new(); $($fields)*)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __anno_query {
+    (@fields $query:expr;) => { $query };
+    (@fields $query:expr; ":or" : [ $({ $($sub:tt)* }),* $(,)? ] $(, $($rest:tt)*)?) => {
+        $crate::__anno_query!(
+            @fields $query.or([$($crate::anno_query!({ $($sub)* })),*]); $($($rest)*)?
+        )
+    };
+    (@fields $query:expr; ":and" : [ $({ $($sub:tt)* }),* $(,)? ] $(, $($rest:tt)*)?) => {
+        $crate::__anno_query!(
+            @fields $query.and([$($crate::anno_query!({ $($sub)* })),*]); $($($rest)*)?
+        )
+    };
+    (@fields $query:expr; $field:literal : { $($conditions:tt)* } $(, $($rest:tt)*)?) => {
+        $crate::__anno_query!(
+            @fields $crate::__anno_query!(@conditions $query, $field; $($conditions)*);
+            $($($rest)*)?
+        )
+    };
+    (@fields $query:expr; $field:literal : $value:expr $(, $($rest:tt)*)?) => {
+        $crate::__anno_query!(@fields $query.field($field, $value); $($($rest)*)?)
+    };
+
+    (@conditions $query:expr, $field:literal;) => { $query };
+    (@conditions $query:expr, $field:literal; "!=" : $($rest:tt)*) => {
+        $crate::__anno_query!(@condition is_not, $query, $field; $($rest)*)
+    };
+    (@conditions $query:expr, $field:literal; ":isNot" : $($rest:tt)*) => {
+        $crate::__anno_query!(@condition is_not, $query, $field; $($rest)*)
+    };
+    (@conditions $query:expr, $field:literal; "<" : $($rest:tt)*) => {
+        $crate::__anno_query!(@condition less_than, $query, $field; $($rest)*)
+    };
+    (@conditions $query:expr, $field:literal; ":isLessThan" : $($rest:tt)*) => {
+        $crate::__anno_query!(@condition less_than, $query, $field; $($rest)*)
+    };
+    (@conditions $query:expr, $field:literal; "<=" : $($rest:tt)*) => {
+        $crate::__anno_query!(@condition less_than_or_equal, $query, $field; $($rest)*)
+    };
+    (@conditions $query:expr, $field:literal; ":isLessThanOrEqualTo" : $($rest:tt)*) => {
+        $crate::__anno_query!(@condition less_than_or_equal, $query, $field; $($rest)*)
+    };
+    (@conditions $query:expr, $field:literal; ">" : $($rest:tt)*) => {
+        $crate::__anno_query!(@condition greater_than, $query, $field; $($rest)*)
+    };
+    (@conditions $query:expr, $field:literal; ":isGreaterThan" : $($rest:tt)*) => {
+        $crate::__anno_query!(@condition greater_than, $query, $field; $($rest)*)
+    };
+    (@conditions $query:expr, $field:literal; ">=" : $($rest:tt)*) => {
+        $crate::__anno_query!(@condition greater_than_or_equal, $query, $field; $($rest)*)
+    };
+    (@conditions $query:expr, $field:literal; ":isGreaterThanOrEqualTo" : $($rest:tt)*) => {
+        $crate::__anno_query!(@condition greater_than_or_equal, $query, $field; $($rest)*)
+    };
+    (@conditions $query:expr, $field:literal; "in" : $($rest:tt)*) => {
+        $crate::__anno_query!(@condition is_in, $query, $field; $($rest)*)
+    };
+    (@conditions $query:expr, $field:literal; ":isIn" : $($rest:tt)*) => {
+        $crate::__anno_query!(@condition is_in, $query, $field; $($rest)*)
+    };
+    (@conditions $query:expr, $field:literal; "not in" : $($rest:tt)*) => {
+        $crate::__anno_query!(@condition is_not_in, $query, $field; $($rest)*)
+    };
+    (@conditions $query:expr, $field:literal; ":isNotIn" : $($rest:tt)*) => {
+        $crate::__anno_query!(@condition is_not_in, $query, $field; $($rest)*)
+    };
+    (@conditions $query:expr, $field:literal; "regex" : $($rest:tt)*) => {
+        $crate::__anno_query!(@condition matches_regex, $query, $field; $($rest)*)
+    };
+    (@conditions $query:expr, $field:literal; ":matchesRegex" : $($rest:tt)*) => {
+        $crate::__anno_query!(@condition matches_regex, $query, $field; $($rest)*)
+    };
+    (@conditions $query:expr, $field:literal; "contains" : $($rest:tt)*) => {
+        $crate::__anno_query!(@condition contains, $query, $field; $($rest)*)
+    };
+    (@conditions $query:expr, $field:literal; $operator:literal : $($rest:tt)*) => {
+        compile_error!(concat!("unknown query operator ", stringify!($operator)))
+    };
+
+    (@condition $method:ident, $query:expr, $field:literal; $value:expr $(, $($rest:tt)*)?) => {
+        $crate::__anno_query!(@conditions $query.$method($field, $value), $field; $($($rest)*)?)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Query;
+    use serde_json::json;
+
+    #[test]
+    fn macro_expands_to_the_builder() {
+        let query = anno_query!({
+            "body.type": "Person",
+            "motivation": { "in": ["tagging", "classifying"], ":isNot": "linking" },
+            ":and": [{ "created": { ">": "2024-03-01T00:00:00.000Z" } }],
+        });
+
+        assert_eq!(
+            query,
+            Query::new()
+                .field("body.type", "Person")
+                .is_in("motivation", ["tagging", "classifying"])
+                .is_not("motivation", "linking")
+                .and([Query::new().greater_than("created", "2024-03-01T00:00:00.000Z")])
+        );
+        assert_eq!(query.as_json()["body.type"], json!("Person"));
+    }
+}
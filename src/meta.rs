@@ -0,0 +1,129 @@
+use crate::response::Response;
+use crate::{AnnoRepoClient, Annotation, Error, Links};
+use reqwest::header::{HeaderMap, CONTENT_LOCATION, ETAG, LOCATION};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+
+/// A deserialized response body, with the status and headers it came with.
+#[derive(Debug, Clone)]
+pub struct WithMeta<T> {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: T,
+}
+
+impl<T> WithMeta<T> {
+    pub fn etag(&self) -> Option<&str> {
+        self.header(ETAG.as_str())
+    }
+
+    pub fn location(&self) -> Option<&str> {
+        self.header(LOCATION.as_str())
+    }
+
+    pub fn content_location(&self) -> Option<&str> {
+        self.header(CONTENT_LOCATION.as_str())
+    }
+
+    /// The `Link` headers.
+    pub fn links(&self) -> Links {
+        Links::from_headers(&self.headers)
+    }
+
+    /// A header by name, if it is valid text.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).and_then(|value| value.to_str().ok())
+    }
+
+    pub fn into_body(self) -> T {
+        self.body
+    }
+}
+
+impl AnnoRepoClient {
+    /// [`get_annotation`](Self::get_annotation), with the status and headers
+    /// of the response. Bypasses the caches, which don't keep those.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container, annotation_name)))]
+    pub async fn get_annotation_with_meta(
+        &self,
+        annotation_name: &str,
+    ) -> Result<WithMeta<Annotation>, Error> {
+        let url = self.annotation_url(annotation_name);
+
+        let res = self.send(self.client.get(url)).await?;
+        let (status, headers) = meta(&res);
+        let body = res.typed(self.deserialization_mode).await?;
+
+        Ok(WithMeta {
+            status,
+            headers,
+            body,
+        })
+    }
+
+    /// Send a request, such as one from [`request`](Self::request), and
+    /// deserialize its JSON body, keeping the status and headers. Unlike
+    /// [`send_raw`](Self::send_raw), an unsuccessful status is an error.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn send_with_meta<T: DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<WithMeta<T>, Error> {
+        let res = self.send(request).await?;
+        let (status, headers) = meta(&res);
+        let body = res.json().await?;
+
+        Ok(WithMeta {
+            status,
+            headers,
+            body,
+        })
+    }
+}
+
+fn meta(res: &Response) -> (StatusCode, HeaderMap) {
+    (res.status(), res.headers().clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn headers_are_kept_with_the_body() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/w3c/c/a1"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("etag", "\"v2\"")
+                    .insert_header("link", r#"<http://www.w3.org/ns/ldp#Resource>; rel="type""#)
+                    .set_body_json(json!({
+                        "@context": "http://www.w3.org/ns/anno.jsonld",
+                        "id": "https://annorepo.example.com/w3c/c/a1",
+                        "type": "Annotation",
+                        "body": "https://example.com/body",
+                        "target": "https://example.com/target"
+                    })),
+            )
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        let annotation = client.get_annotation_with_meta("a1").await.unwrap();
+
+        assert_eq!(annotation.status, StatusCode::OK);
+        assert_eq!(annotation.etag(), Some("\"v2\""));
+        assert_eq!(
+            annotation.links().types(),
+            ["http://www.w3.org/ns/ldp#Resource"]
+        );
+        assert_eq!(
+            annotation.body.id.as_deref(),
+            Some("https://annorepo.example.com/w3c/c/a1")
+        );
+    }
+}
@@ -0,0 +1,99 @@
+//! Request metrics reported through the [`metrics`] facade, with the
+//! `metrics` feature. Nothing is recorded until the application installs a
+//! recorder, such as `metrics-exporter-prometheus`.
+//!
+//! - `annorepo_client_requests_total`: counter, by `method`, `endpoint` and
+//!   `status`
+//! - `annorepo_client_errors_total`: counter, by `method`, `endpoint` and
+//!   `kind` (`status` for error responses, `transport` for failed requests)
+//! - `annorepo_client_request_duration_seconds`: histogram, by `method` and
+//!   `endpoint`
+
+use crate::ResponseInfo;
+
+pub(crate) fn record(base_url: &str, info: &ResponseInfo) {
+    let method = info.method.to_string();
+    let endpoint = endpoint(base_url, info.url.as_str());
+    let status = match info.status {
+        Some(status) => status.as_str().to_string(),
+        None => "none".to_string(),
+    };
+
+    metrics::counter!(
+        "annorepo_client_requests_total",
+        "method" => method.clone(),
+        "endpoint" => endpoint,
+        "status" => status
+    )
+    .increment(1);
+    metrics::histogram!(
+        "annorepo_client_request_duration_seconds",
+        "method" => method.clone(),
+        "endpoint" => endpoint
+    )
+    .record(info.duration.as_secs_f64());
+
+    let kind = match info.status {
+        None => Some("transport"),
+        Some(status) if status.is_client_error() || status.is_server_error() => Some("status"),
+        Some(_) => None,
+    };
+    if let Some(kind) = kind {
+        metrics::counter!(
+            "annorepo_client_errors_total",
+            "method" => method,
+            "endpoint" => endpoint,
+            "kind" => kind
+        )
+        .increment(1);
+    }
+}
+
+/// The endpoint `url` belongs to, without container names, annotation names
+/// or search ids, to keep the number of label values small.
+fn endpoint(base_url: &str, url: &str) -> &'static str {
+    let path = url.strip_prefix(base_url).unwrap_or(url);
+    let path = path.split(['?', '#']).next().unwrap_or_default();
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    match segments.as_slice() {
+        ["about"] => "about",
+        ["w3c", _] => "w3c/container",
+        ["w3c", _, _] => "w3c/annotation",
+        ["w3c", ..] => "w3c",
+        ["services", _, "fields"] => "services/fields",
+        ["services", _, "metadata"] => "services/metadata",
+        ["services", _, "indexes", ..] => "services/indexes",
+        ["services", _, "search", ..] => "services/search",
+        ["services", _, "distinct-values", ..] => "services/distinct-values",
+        ["services", ..] => "services",
+        ["batch", ..] => "batch",
+        ["admin", ..] => "admin",
+        ["my", ..] => "my",
+        _ => "other",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn endpoints_leave_out_names_and_ids() {
+        let base = "http://localhost:8080";
+
+        assert_eq!(endpoint(base, "http://localhost:8080/about"), "about");
+        assert_eq!(
+            endpoint(base, "http://localhost:8080/w3c/c/"),
+            "w3c/container"
+        );
+        assert_eq!(
+            endpoint(base, "http://localhost:8080/w3c/c/a1"),
+            "w3c/annotation"
+        );
+        assert_eq!(
+            endpoint(base, "http://localhost:8080/services/c/search/s1?page=2"),
+            "services/search"
+        );
+        assert_eq!(endpoint(base, "http://example.org/other"), "other");
+    }
+}
@@ -0,0 +1,194 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// How typed responses treat fields the client doesn't know about.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DeserializationMode {
+    /// Keep unknown fields in the model's `extra` map.
+    #[default]
+    Lenient,
+    /// Fail with [`Error::UnknownFields`](crate::Error::UnknownFields) when a
+    /// response has fields the model doesn't declare.
+    Strict,
+}
+
+/// Models that collect undeclared fields instead of dropping them.
+pub trait UnknownFields {
+    /// Names of the undeclared fields, as paths from the model's root.
+    fn unknown_fields(&self) -> Vec<String>;
+}
+
+impl<T: UnknownFields> UnknownFields for Vec<T> {
+    fn unknown_fields(&self) -> Vec<String> {
+        self.iter()
+            .enumerate()
+            .flat_map(|(i, item)| {
+                item.unknown_fields()
+                    .into_iter()
+                    .map(move |field| format!("[{i}].{field}"))
+            })
+            .collect()
+    }
+}
+
+pub(crate) fn extra_field_names(extra: &Map<String, Value>) -> Vec<String> {
+    extra.keys().cloned().collect()
+}
+
+/// A JSON-LD value that may be given either as a single item or as an array.
+///
+/// Which of the two it was is kept, so a value serializes back exactly the way
+/// it was read. Use it for your own models of annotation bodies and targets.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum OneOrMany<T> {
+    // Tried first, so that an array is never taken as a single `Value`.
+    Many(Vec<T>),
+    One(T),
+}
+
+impl<T> OneOrMany<T> {
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        match self {
+            Self::One(item) => std::slice::from_ref(item).iter(),
+            Self::Many(items) => items.iter(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            Self::One(_) => 1,
+            Self::Many(items) => items.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn first(&self) -> Option<&T> {
+        self.iter().next()
+    }
+
+    pub fn into_vec(self) -> Vec<T> {
+        match self {
+            Self::One(item) => vec![item],
+            Self::Many(items) => items,
+        }
+    }
+}
+
+impl<T> Default for OneOrMany<T> {
+    fn default() -> Self {
+        Self::Many(Vec::new())
+    }
+}
+
+impl<T> From<T> for OneOrMany<T> {
+    fn from(item: T) -> Self {
+        Self::One(item)
+    }
+}
+
+impl<T> From<Vec<T>> for OneOrMany<T> {
+    fn from(items: Vec<T>) -> Self {
+        Self::Many(items)
+    }
+}
+
+impl<'a, T> IntoIterator for &'a OneOrMany<T> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// A W3C Web Annotation.
+///
+/// Bodies and targets are kept as JSON, as their shape varies too much
+/// between projects to model here; any other fields end up in `extra`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    #[serde(rename = "@context", default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<OneOrMany<Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(rename = "type")]
+    pub annotation_type: OneOrMany<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub motivation: Option<OneOrMany<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<OneOrMany<Value>>,
+    pub target: OneOrMany<Value>,
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+impl UnknownFields for Annotation {
+    fn unknown_fields(&self) -> Vec<String> {
+        extra_field_names(&self.extra)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn scalar_and_array_values_round_trip() {
+        for annotation in [
+            json!({
+                "@context": "http://www.w3.org/ns/anno.jsonld",
+                "id": "https://annorepo.example.com/w3c/c/a1",
+                "type": "Annotation",
+                "body": {"type": "TextualBody", "value": "hello"},
+                "target": "https://example.com/page/1"
+            }),
+            json!({
+                "@context": [
+                    "http://www.w3.org/ns/anno.jsonld",
+                    {"tt": "https://example.com/ns/"}
+                ],
+                "type": ["Annotation", "tt:Entity"],
+                "motivation": ["tagging"],
+                "body": [{"purpose": "tagging", "value": "person"}],
+                "target": [
+                    "https://example.com/page/1",
+                    {"source": "https://example.com/page/2", "selector": {"type": "TextPositionSelector"}}
+                ],
+                "created": "2024-03-01T09:00:00Z",
+                "generator": {"id": "https://example.com/tool", "type": "Software"}
+            }),
+        ] {
+            let parsed: Annotation = serde_json::from_value(annotation.clone()).unwrap();
+            assert_eq!(serde_json::to_value(&parsed).unwrap(), annotation);
+        }
+    }
+
+    #[test]
+    fn undeclared_fields_are_reported() {
+        let annotations: Vec<Annotation> = serde_json::from_value(json!([
+            {"type": "Annotation", "target": "https://example.com/1"},
+            {"type": "Annotation", "target": "https://example.com/2", "created": "2024-03-01"}
+        ]))
+        .unwrap();
+
+        assert_eq!(annotations.unknown_fields(), ["[1].created"]);
+    }
+
+    #[test]
+    fn one_or_many_iterates_over_both_forms() {
+        let one: OneOrMany<u32> = serde_json::from_value(json!(1)).unwrap();
+        let many: OneOrMany<u32> = serde_json::from_value(json!([1, 2])).unwrap();
+
+        assert_eq!(one.iter().collect::<Vec<_>>(), [&1]);
+        assert_eq!(many.len(), 2);
+        assert_eq!(many.into_vec(), [1, 2]);
+
+        let values: OneOrMany<Value> = serde_json::from_value(json!([1, "a"])).unwrap();
+        assert_eq!(values, OneOrMany::Many(vec![json!(1), json!("a")]));
+    }
+}
@@ -0,0 +1,125 @@
+use crate::timestamp::compare_timestamps;
+use crate::{AnnoRepoClient, AnnotationStream, Error, IndexType, Query};
+use std::time::Duration;
+
+/// The W3C fields recording when an annotation was created and last changed.
+const TIMESTAMP_FIELDS: [&str; 2] = ["created", "modified"];
+
+/// How long [`AnnoRepoClient::modified_since`] waits for a missing index.
+const INDEX_TIMEOUT: Duration = Duration::from_secs(300);
+
+impl AnnoRepoClient {
+    /// Stream the annotations created or modified at or after `since`, an
+    /// ISO 8601 timestamp such as the time of the previous harvest.
+    ///
+    /// Creates ascending indexes on `created` and `modified` first if the
+    /// container doesn't have them, waiting until they are built.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container, since)))]
+    pub async fn modified_since(&self, since: &str) -> Result<AnnotationStream<'_>, Error> {
+        self.ensure_timestamp_indexes().await?;
+
+        Ok(self.search(&modified_query(since)).await?.annotations())
+    }
+
+    pub(crate) async fn ensure_timestamp_indexes(&self) -> Result<(), Error> {
+        let indexes = self.list_indexes().await?;
+        for field in TIMESTAMP_FIELDS {
+            let key = (field, &IndexType::Ascending);
+            if !indexes.iter().any(|index| index.key() == key) {
+                self.create_index(field, IndexType::Ascending).await?;
+                self.wait_for_index(field, IndexType::Ascending, INDEX_TIMEOUT)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn modified_query(since: &str) -> Query {
+    Query::new().or(TIMESTAMP_FIELDS.map(|field| Query::new().greater_than_or_equal(field, since)))
+}
+
+/// The later of an annotation's `created` and `modified` timestamps.
+pub(crate) fn last_changed(annotation: &serde_json::Value) -> Option<&str> {
+    TIMESTAMP_FIELDS
+        .iter()
+        .filter_map(|field| annotation[field].as_str())
+        .max_by(|a, b| compare_timestamps(a, b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::TryStreamExt;
+    use serde_json::{json, Value};
+    use wiremock::matchers::{body_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn last_change_is_the_latest_instant() {
+        let annotation = json!({
+            "created": "2024-03-01T09:30:00Z",
+            "modified": "2024-03-01T11:00:00+02:00"
+        });
+
+        assert_eq!(last_changed(&annotation), Some("2024-03-01T09:30:00Z"));
+    }
+
+    #[tokio::test]
+    async fn missing_timestamp_indexes_are_created_first() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/indexes"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([{
+                "field": "created",
+                "type": "ascending",
+                "url": format!("{}/services/c/indexes/created/ascending", server.uri())
+            }])))
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/services/c/indexes/modified/ascending"))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/indexes/modified/ascending"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "field": "modified",
+                "type": "ascending"
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/services/c/search"))
+            .and(body_json(json!({":or": [
+                {"created": {":isGreaterThanOrEqualTo": "2024-03-01T00:00:00Z"}},
+                {"modified": {":isGreaterThanOrEqualTo": "2024-03-01T00:00:00Z"}}
+            ]})))
+            .respond_with(
+                ResponseTemplate::new(201)
+                    .insert_header("location", format!("{}/services/c/search/s1", server.uri())),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/search/s1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [{"id": "a1"}]
+            })))
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        let changed: Vec<Value> = client
+            .modified_since("2024-03-01T00:00:00Z")
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert_eq!(changed, [json!({"id": "a1"})]);
+    }
+}
@@ -0,0 +1,498 @@
+//! Incremental decoding of search result pages.
+//!
+//! Pages can hold thousands of large annotations. Rather than collecting the
+//! whole body and then parsing it, the decoder parses each item of the
+//! `items` array as soon as its bytes have arrived and drops those bytes, so
+//! the raw page and its parsed form are never in memory at the same time.
+
+use serde::de::Error as _;
+use serde_json::{Map, Value};
+
+/// A decoded page: its items and every other top-level field.
+#[derive(Debug, Default)]
+pub(crate) struct ResultPage {
+    pub(crate) items: Vec<Value>,
+    pub(crate) rest: Map<String, Value>,
+}
+
+impl ResultPage {
+    pub(crate) fn has_next(&self) -> bool {
+        self.rest.contains_key("next")
+    }
+
+    pub(crate) fn into_value(self) -> Value {
+        let mut page = self.rest;
+        page.entry("items").or_insert(Value::Array(self.items));
+        Value::Object(page)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    ExpectObject,
+    /// Right after `{`, where the object may also end.
+    ExpectFirstKey,
+    /// After a `,`.
+    ExpectKey,
+    InKey,
+    ExpectColon,
+    ExpectValue,
+    InValue,
+    /// After a value, where a `,` or the end of the object has to follow.
+    AfterValue,
+    /// Right after the `[` of `items`, where the array may also end.
+    ExpectFirstItem,
+    ExpectItem,
+    InItem,
+    AfterItem,
+    Done,
+}
+
+#[derive(Debug)]
+pub(crate) struct PageDecoder {
+    buffer: Vec<u8>,
+    pos: usize,
+    /// Start of the key or value being read, which has to stay buffered.
+    token_start: Option<usize>,
+    state: State,
+    scanner: ValueScanner,
+    key: String,
+    has_items: bool,
+    page: ResultPage,
+}
+
+impl Default for PageDecoder {
+    fn default() -> Self {
+        Self {
+            buffer: Vec::new(),
+            pos: 0,
+            token_start: None,
+            state: State::ExpectObject,
+            scanner: ValueScanner::default(),
+            key: String::new(),
+            has_items: false,
+            page: ResultPage::default(),
+        }
+    }
+}
+
+impl PageDecoder {
+    pub(crate) fn feed(&mut self, chunk: &[u8]) -> Result<(), serde_json::Error> {
+        self.buffer.extend_from_slice(chunk);
+        while self.pos < self.buffer.len() {
+            let byte = self.buffer[self.pos];
+            match self.state {
+                State::InKey => {
+                    if let Some(end) = self.scanner.scan(&self.buffer, &mut self.pos) {
+                        let start = self.take_token();
+                        self.key = serde_json::from_slice(&self.buffer[start..end])?;
+                        self.state = State::ExpectColon;
+                    }
+                }
+                State::InValue | State::InItem => {
+                    if let Some(end) = self.scanner.scan(&self.buffer, &mut self.pos) {
+                        let start = self.take_token();
+                        let value = serde_json::from_slice(&self.buffer[start..end])?;
+                        if self.state == State::InItem {
+                            self.page.items.push(value);
+                            self.state = State::AfterItem;
+                        } else {
+                            self.page.rest.insert(std::mem::take(&mut self.key), value);
+                            self.state = State::AfterValue;
+                        }
+                    }
+                }
+                _ if byte.is_ascii_whitespace() => self.pos += 1,
+                State::ExpectObject => {
+                    self.expect(byte, b'{')?;
+                    self.state = State::ExpectFirstKey;
+                }
+                State::ExpectFirstKey | State::ExpectKey => match byte {
+                    b'}' if self.state == State::ExpectFirstKey => {
+                        self.pos += 1;
+                        self.state = State::Done;
+                    }
+                    b'"' => self.start_token(State::InKey),
+                    _ => return Err(unexpected(byte, "an object key")),
+                },
+                State::AfterValue => match byte {
+                    b'}' => {
+                        self.pos += 1;
+                        self.state = State::Done;
+                    }
+                    b',' => {
+                        self.pos += 1;
+                        self.state = State::ExpectKey;
+                    }
+                    _ => return Err(unexpected(byte, "`,` or `}`")),
+                },
+                State::ExpectColon => {
+                    self.expect(byte, b':')?;
+                    self.state = State::ExpectValue;
+                }
+                State::ExpectValue if byte == b'[' && self.key == "items" => {
+                    self.pos += 1;
+                    self.has_items = true;
+                    self.state = State::ExpectFirstItem;
+                }
+                State::ExpectValue => self.start_token(State::InValue),
+                State::ExpectFirstItem | State::ExpectItem => match byte {
+                    b']' if self.state == State::ExpectFirstItem => self.end_items(),
+                    b',' | b']' => return Err(unexpected(byte, "an item")),
+                    _ => self.start_token(State::InItem),
+                },
+                State::AfterItem => match byte {
+                    b']' => self.end_items(),
+                    b',' => {
+                        self.pos += 1;
+                        self.state = State::ExpectItem;
+                    }
+                    _ => return Err(unexpected(byte, "`,` or `]`")),
+                },
+                State::Done => return Err(unexpected(byte, "the end of the page")),
+            }
+        }
+        self.compact();
+
+        Ok(())
+    }
+
+    /// The decoded page, with `items` in `rest` if it wasn't an array, so that it
+    /// can be reported as malformed.
+    ///
+    /// The decoder is left ready for the next page, keeping its buffer.
+    pub(crate) fn finish(&mut self) -> Result<ResultPage, serde_json::Error> {
+        let state = self.state;
+        let has_items = self.has_items;
+        let capacity = self.page.items.len();
+        let page = std::mem::take(&mut self.page);
+        self.reset(capacity);
+        if state != State::Done {
+            return Err(serde_json::Error::custom("page ended unexpectedly"));
+        }
+        if !has_items && !page.rest.contains_key("items") {
+            return Err(serde_json::Error::missing_field("items"));
+        }
+
+        Ok(page)
+    }
+
+    /// Start over, expecting a page of about `item_count` items.
+    fn reset(&mut self, item_count: usize) {
+        self.buffer.clear();
+        self.pos = 0;
+        self.token_start = None;
+        self.state = State::ExpectObject;
+        self.scanner = ValueScanner::default();
+        self.key.clear();
+        self.has_items = false;
+        self.page.items.reserve(item_count);
+    }
+
+    fn end_items(&mut self) {
+        self.pos += 1;
+        self.key.clear();
+        self.state = State::AfterValue;
+    }
+
+    fn expect(&mut self, byte: u8, expected: u8) -> Result<(), serde_json::Error> {
+        if byte != expected {
+            return Err(unexpected(byte, &format!("`{}`", expected as char)));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn start_token(&mut self, state: State) {
+        self.token_start = Some(self.pos);
+        self.state = state;
+    }
+
+    fn take_token(&mut self) -> usize {
+        self.token_start.take().unwrap_or(self.pos)
+    }
+
+    /// Drop the bytes that have been decoded.
+    fn compact(&mut self) {
+        let consumed = self.token_start.unwrap_or(self.pos);
+        self.buffer.drain(..consumed);
+        self.pos -= consumed;
+        if let Some(start) = &mut self.token_start {
+            *start -= consumed;
+        }
+    }
+}
+
+/// Decodes a top-level JSON array, handing out each item as soon as its
+/// bytes have arrived.
+#[derive(Debug, Default)]
+pub(crate) struct ArrayDecoder {
+    buffer: Vec<u8>,
+    pos: usize,
+    token_start: Option<usize>,
+    started: bool,
+    /// Whether the last thing read was an item, so that a `,` or `]` has
+    /// to follow, or a `,`, so that an item has to.
+    after_item: bool,
+    after_comma: bool,
+    done: bool,
+    scanner: ValueScanner,
+}
+
+impl ArrayDecoder {
+    /// The items completed by `chunk`.
+    pub(crate) fn feed(&mut self, chunk: &[u8]) -> Result<Vec<Value>, serde_json::Error> {
+        self.buffer.extend_from_slice(chunk);
+        let mut items = Vec::new();
+        while self.pos < self.buffer.len() {
+            let byte = self.buffer[self.pos];
+            if let Some(start) = self.token_start {
+                if let Some(end) = self.scanner.scan(&self.buffer, &mut self.pos) {
+                    items.push(serde_json::from_slice(&self.buffer[start..end])?);
+                    self.token_start = None;
+                    self.after_item = true;
+                }
+            } else if byte.is_ascii_whitespace() {
+                self.pos += 1;
+            } else if self.done {
+                return Err(unexpected(byte, "the end of the array"));
+            } else if !self.started {
+                if byte != b'[' {
+                    return Err(unexpected(byte, "`[`"));
+                }
+                self.started = true;
+                self.pos += 1;
+            } else {
+                match byte {
+                    b']' if !self.after_comma => {
+                        self.done = true;
+                        self.pos += 1;
+                    }
+                    b',' if self.after_item => {
+                        self.after_item = false;
+                        self.after_comma = true;
+                        self.pos += 1;
+                    }
+                    _ if self.after_item => return Err(unexpected(byte, "`,` or `]`")),
+                    b',' | b']' => return Err(unexpected(byte, "an item")),
+                    _ => {
+                        self.after_comma = false;
+                        self.token_start = Some(self.pos);
+                    }
+                }
+            }
+        }
+        let consumed = self.token_start.unwrap_or(self.pos);
+        self.buffer.drain(..consumed);
+        self.pos -= consumed;
+        if let Some(start) = &mut self.token_start {
+            *start -= consumed;
+        }
+
+        Ok(items)
+    }
+
+    pub(crate) fn finish(&self) -> Result<(), serde_json::Error> {
+        if self.done {
+            Ok(())
+        } else {
+            Err(serde_json::Error::custom("array ended unexpectedly"))
+        }
+    }
+}
+
+fn unexpected(byte: u8, expected: &str) -> serde_json::Error {
+    serde_json::Error::custom(format!(
+        "unexpected `{}` in page, expected {expected}",
+        byte as char
+    ))
+}
+
+/// Finds where a JSON value ends, across chunk boundaries.
+#[derive(Debug, Default)]
+struct ValueScanner {
+    started: bool,
+    scalar: bool,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+}
+
+impl ValueScanner {
+    /// Advance `pos` through `buffer`, returning the end of the value once it
+    /// has been seen completely.
+    fn scan(&mut self, buffer: &[u8], pos: &mut usize) -> Option<usize> {
+        while *pos < buffer.len() {
+            let byte = buffer[*pos];
+            if !self.started {
+                self.started = true;
+                match byte {
+                    b'{' | b'[' => self.depth = 1,
+                    b'"' => self.in_string = true,
+                    _ => self.scalar = true,
+                }
+            } else if self.scalar {
+                if matches!(byte, b',' | b'}' | b']') || byte.is_ascii_whitespace() {
+                    return self.end(*pos);
+                }
+            } else if self.in_string {
+                if self.escaped {
+                    self.escaped = false;
+                } else if byte == b'\\' {
+                    self.escaped = true;
+                } else if byte == b'"' {
+                    self.in_string = false;
+                    if self.depth == 0 {
+                        *pos += 1;
+                        return self.end(*pos);
+                    }
+                }
+            } else {
+                match byte {
+                    b'"' => self.in_string = true,
+                    b'{' | b'[' => self.depth += 1,
+                    b'}' | b']' => {
+                        self.depth -= 1;
+                        if self.depth == 0 {
+                            *pos += 1;
+                            return self.end(*pos);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            *pos += 1;
+        }
+        None
+    }
+
+    fn end(&mut self, end: usize) -> Option<usize> {
+        *self = Self::default();
+        Some(end)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn decode_in_chunks(page: &[u8], chunk_size: usize) -> Result<ResultPage, serde_json::Error> {
+        let mut decoder = PageDecoder::default();
+        for chunk in page.chunks(chunk_size) {
+            decoder.feed(chunk)?;
+        }
+        decoder.finish()
+    }
+
+    #[test]
+    fn arrays_are_decoded_item_by_item() {
+        let array = br#"["https://example.com/1", {"id": "a, ]"}, 3]"#;
+        for chunk_size in [1, 5, array.len()] {
+            let mut decoder = ArrayDecoder::default();
+            let mut items = Vec::new();
+            for chunk in array.chunks(chunk_size) {
+                items.extend(decoder.feed(chunk).unwrap());
+            }
+
+            decoder.finish().unwrap();
+            assert_eq!(
+                items,
+                [
+                    json!("https://example.com/1"),
+                    json!({"id": "a, ]"}),
+                    json!(3)
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn separators_are_checked() {
+        for page in [
+            &br#"{"items": [1 2]}"#[..],
+            br#"{"items": [1,,2]}"#,
+            br#"{"items": [,1]}"#,
+            br#"{"items": [1,]}"#,
+            br#"{"items": [] "next": 1}"#,
+            br#"{"a": 1 "items": []}"#,
+            br#"{,"items": []}"#,
+            br#"{"items": [],}"#,
+        ] {
+            assert!(
+                decode_in_chunks(page, 1).is_err(),
+                "{}",
+                String::from_utf8_lossy(page)
+            );
+        }
+        assert_eq!(
+            decode_in_chunks(br#"{"items": []}"#, 1).unwrap().items,
+            Vec::<Value>::new()
+        );
+        for array in [&b"[1 2]"[..], b"[1,,2]", b"[,1]", b"[1,]"] {
+            let mut decoder = ArrayDecoder::default();
+            assert!(
+                decoder.feed(array).is_err(),
+                "{}",
+                String::from_utf8_lossy(array)
+            );
+        }
+    }
+
+    #[test]
+    fn decoders_can_be_reused() {
+        let mut decoder = PageDecoder::default();
+        assert!(decoder.feed(br#"{"items": [1, 2"#).is_ok());
+        assert!(decoder.finish().is_err());
+
+        decoder.feed(br#"{"items": [3]}"#).unwrap();
+        assert_eq!(decoder.finish().unwrap().items, [json!(3)]);
+    }
+
+    #[test]
+    fn pages_decode_the_same_whatever_the_chunk_size() {
+        let page = json!({
+            "id": "https://annorepo.example.com/services/c/search/s1?page=0",
+            "startIndex": 0,
+            "items": [
+                {"id": "a1", "body": {"value": "with \"quotes\" and ] brackets {"}},
+                {"id": "a2", "target": ["https://example.com/1", {"source": "x"}]},
+                "https://example.com/a3",
+                42
+            ],
+            "next": "https://annorepo.example.com/services/c/search/s1?page=1",
+            "partOf": {"total": 4}
+        });
+        let bytes = serde_json::to_vec_pretty(&page).unwrap();
+
+        for chunk_size in [1, 2, 7, 64, bytes.len()] {
+            let decoded = decode_in_chunks(&bytes, chunk_size).unwrap();
+            assert!(decoded.has_next());
+            assert_eq!(decoded.rest["partOf"], json!({"total": 4}));
+            assert_eq!(decoded.rest["startIndex"], json!(0));
+            assert!(!decoded.rest.contains_key("items"));
+            assert_eq!(decoded.into_value(), page, "chunks of {chunk_size}");
+        }
+    }
+
+    #[test]
+    fn decoded_bytes_are_released() {
+        let mut decoder = PageDecoder::default();
+        decoder
+            .feed(br#"{"items": [{"id": "a1"}, {"id": "#)
+            .unwrap();
+
+        assert_eq!(decoder.page.items, [json!({"id": "a1"})]);
+        assert_eq!(decoder.buffer, br#"{"id": "#);
+    }
+
+    #[test]
+    fn broken_pages_are_rejected() {
+        assert!(decode_in_chunks(br#"{"items": [{"id": "a1"}"#, 4).is_err());
+        assert!(decode_in_chunks(br#"["not", "a", "page"]"#, 4).is_err());
+        assert!(decode_in_chunks(br#"{"next": "x"}"#, 4).is_err());
+
+        let not_an_array = decode_in_chunks(br#"{"items": {"id": "a1"}}"#, 4).unwrap();
+        assert_eq!(not_an_array.rest["items"], json!({"id": "a1"}));
+    }
+}
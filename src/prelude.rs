@@ -0,0 +1,11 @@
+//! The types most code using the client needs.
+//!
+//! ```
+//! use annorepo_client::prelude::*;
+//! ```
+
+pub use crate::{
+    AnnoRepoApi, AnnoRepoClient, AnnoRepoClientBuilder, Annotation, AnnotationExt, Error,
+    OneOrMany, Query,
+};
+pub use serde_json::json;
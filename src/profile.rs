@@ -0,0 +1,195 @@
+use crate::{AnnoRepoClient, AnnoRepoClientBuilder, Error};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Environment variable naming the config file, overriding
+/// [`Profiles::default_path`].
+pub const CONFIG_ENV: &str = "ANNOREPO_CONFIG";
+
+/// Named server configurations, read from a TOML file with a table per
+/// profile:
+///
+/// ```toml
+/// [prod]
+/// url = "https://annorepo.example.com"
+/// container = "letters"
+/// api_key = "..."
+/// timeout = 30          # seconds, optional
+/// connect_timeout = 5   # seconds, optional
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(transparent)]
+pub struct Profiles(BTreeMap<String, Profile>);
+
+/// One server and container, with the credentials and timeouts to use.
+#[derive(Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    pub url: String,
+    pub container: String,
+    #[serde(default)]
+    pub api_key: Option<String>,
+    /// Seconds; see [`AnnoRepoClientBuilder::timeout`].
+    #[serde(default)]
+    pub timeout: Option<f64>,
+    /// Seconds; see [`AnnoRepoClientBuilder::connect_timeout`].
+    #[serde(default)]
+    pub connect_timeout: Option<f64>,
+}
+
+impl fmt::Debug for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Profile")
+            .field("url", &self.url)
+            .field("container", &self.container)
+            .field("api_key", &self.api_key.as_ref().map(|_| "***"))
+            .field("timeout", &self.timeout)
+            .field("connect_timeout", &self.connect_timeout)
+            .finish()
+    }
+}
+
+impl Profiles {
+    /// `$ANNOREPO_CONFIG` if set, otherwise `annorepo/config.toml` in
+    /// `$XDG_CONFIG_HOME`, or in `~/.config`.
+    pub fn default_path() -> Option<PathBuf> {
+        if let Some(path) = std::env::var_os(CONFIG_ENV) {
+            return Some(path.into());
+        }
+        let config_dir = match std::env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+        };
+        Some(config_dir.join("annorepo").join("config.toml"))
+    }
+
+    /// Read the profiles in the file at [`default_path`](Self::default_path).
+    pub fn load_default() -> Result<Self, Error> {
+        let path = Self::default_path().ok_or_else(|| Error::InvalidConfig {
+            path: None,
+            reason: format!("no config file: neither {CONFIG_ENV} nor HOME is set"),
+        })?;
+        Self::load(path)
+    }
+
+    /// Read the profiles in the file at `path`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let invalid = |reason: String| Error::InvalidConfig {
+            path: Some(path.to_path_buf()),
+            reason,
+        };
+        let text = std::fs::read_to_string(path).map_err(|e| invalid(e.to_string()))?;
+
+        Self::parse(&text).map_err(|e| match e {
+            Error::InvalidConfig { reason, .. } => invalid(reason),
+            e => e,
+        })
+    }
+
+    /// Read profiles from the contents of a config file.
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        toml::from_str(text).map_err(|e| Error::InvalidConfig {
+            path: None,
+            reason: e.message().to_string(),
+        })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Profile> {
+        self.0.get(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(String::as_str)
+    }
+
+    /// A builder for the client of profile `name`, to configure further.
+    pub fn builder(&self, name: &str) -> Result<AnnoRepoClientBuilder, Error> {
+        let profile = self.get(name).ok_or_else(|| Error::UnknownProfile {
+            name: name.to_string(),
+            known: self.names().map(str::to_string).collect(),
+        })?;
+        profile.builder()
+    }
+}
+
+impl Profile {
+    /// A builder set up with the profile's server, container, API key and
+    /// timeouts.
+    pub fn builder(&self) -> Result<AnnoRepoClientBuilder, Error> {
+        let mut builder = AnnoRepoClient::builder(self.url.as_str(), self.container.as_str());
+        if let Some(api_key) = &self.api_key {
+            builder = builder.api_key(api_key.as_str());
+        }
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(seconds("timeout", timeout)?);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            builder = builder.connect_timeout(seconds("connect_timeout", timeout)?);
+        }
+        Ok(builder)
+    }
+}
+
+fn seconds(key: &str, seconds: f64) -> Result<Duration, Error> {
+    Duration::try_from_secs_f64(seconds).map_err(|_| Error::InvalidConfig {
+        path: None,
+        reason: format!("{key} must be a non-negative number of seconds, not {seconds}"),
+    })
+}
+
+impl AnnoRepoClient {
+    /// The client of profile `name` in the config file at
+    /// [`Profiles::default_path`].
+    pub fn from_profile(name: &str) -> Result<Self, Error> {
+        Profiles::load_default()?.builder(name)?.build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+        [prod]
+        url = "https://annorepo.example.com"
+        container = "letters"
+        api_key = "secret"
+        timeout = 30
+
+        [test]
+        url = "http://localhost:8080"
+        container = "letters-test"
+        connect_timeout = 0.5
+    "#;
+
+    #[test]
+    fn profiles_are_read_by_name() {
+        let profiles = Profiles::parse(CONFIG).unwrap();
+
+        assert_eq!(profiles.names().collect::<Vec<_>>(), ["prod", "test"]);
+        let prod = profiles.get("prod").unwrap();
+        assert_eq!(prod.container, "letters");
+        assert_eq!(prod.timeout, Some(30.0));
+        assert!(!format!("{prod:?}").contains("secret"));
+        let client = profiles.builder("test").unwrap().build().unwrap();
+        assert_eq!(&*client.container, "letters-test");
+    }
+
+    #[test]
+    fn unknown_profiles_and_keys_are_rejected() {
+        let profiles = Profiles::parse(CONFIG).unwrap();
+
+        let error = profiles.builder("staging").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "No profile \"staging\" in the config file (known: prod, test)"
+        );
+        let error = Profiles::parse("[prod]\nurl = \"http://x\"\ncontainer = \"c\"\nkey = \"k\"")
+            .unwrap_err();
+        assert!(matches!(error, Error::InvalidConfig { .. }));
+    }
+}
@@ -0,0 +1,41 @@
+use std::fmt;
+
+/// What a long-running operation is doing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Uploading,
+    Importing,
+    Exporting,
+    Syncing,
+}
+
+/// How far a long-running operation has come.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProgressUpdate {
+    pub phase: Phase,
+    /// Annotations handled so far.
+    pub done: u64,
+    /// Annotations to handle in all, if known up front.
+    pub total: Option<u64>,
+}
+
+/// Receives the progress of uploads, imports, exports and syncs, for
+/// example to draw a progress bar.
+///
+/// Called on the task running the operation, so it should return quickly.
+/// Closures taking a [`ProgressUpdate`] implement it.
+pub trait Progress: Send + Sync {
+    fn update(&self, update: ProgressUpdate);
+}
+
+impl<F: Fn(ProgressUpdate) + Send + Sync> Progress for F {
+    fn update(&self, update: ProgressUpdate) {
+        self(update)
+    }
+}
+
+impl fmt::Debug for dyn Progress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Progress")
+    }
+}
@@ -0,0 +1,547 @@
+use crate::encode::segment;
+use crate::{AnnoRepoClient, Error, SearchInfo, REGEX_SERVER_VERSION};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+/// A search query in the AnnoRepo query language.
+///
+/// Conditions on fields use the dotted paths of [`AnnoRepoClient::get_fields`];
+/// several conditions on the same field all have to hold.
+///
+/// ```
+/// use annorepo_client::Query;
+///
+/// let query = Query::new()
+///     .field("body.type", "Person")
+///     .greater_than_or_equal("body.start", 100);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Query(Map<String, Value>);
+
+impl Query {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Match annotations where `field` equals `value`.
+    pub fn field<V: Into<Value>>(mut self, field: &str, value: V) -> Self {
+        self.0.insert(field.to_string(), value.into());
+        self
+    }
+
+    pub fn is_not<V: Into<Value>>(self, field: &str, value: V) -> Self {
+        self.condition(field, ":isNot", value.into())
+    }
+
+    pub fn is_in<V: Into<Value>>(self, field: &str, values: impl IntoIterator<Item = V>) -> Self {
+        let values = values.into_iter().map(Into::into).collect();
+        self.condition(field, ":isIn", Value::Array(values))
+    }
+
+    pub fn is_not_in<V: Into<Value>>(
+        self,
+        field: &str,
+        values: impl IntoIterator<Item = V>,
+    ) -> Self {
+        let values = values.into_iter().map(Into::into).collect();
+        self.condition(field, ":isNotIn", Value::Array(values))
+    }
+
+    pub fn less_than<V: Into<Value>>(self, field: &str, value: V) -> Self {
+        self.condition(field, ":isLessThan", value.into())
+    }
+
+    pub fn less_than_or_equal<V: Into<Value>>(self, field: &str, value: V) -> Self {
+        self.condition(field, ":isLessThanOrEqualTo", value.into())
+    }
+
+    pub fn greater_than<V: Into<Value>>(self, field: &str, value: V) -> Self {
+        self.condition(field, ":isGreaterThan", value.into())
+    }
+
+    pub fn greater_than_or_equal<V: Into<Value>>(self, field: &str, value: V) -> Self {
+        self.condition(field, ":isGreaterThanOrEqualTo", value.into())
+    }
+
+    /// Match annotations where the string `field` matches the regular
+    /// expression `pattern`. Needs server version [`REGEX_SERVER_VERSION`];
+    /// searching an older server fails with [`Error::UnsupportedByServer`].
+    pub fn matches_regex(self, field: &str, pattern: &str) -> Self {
+        self.condition(field, REGEX_OPERATOR, pattern.into())
+    }
+
+    /// Match annotations where the string `field` contains `text`, as a
+    /// [`matches_regex`](Self::matches_regex) with `text` escaped.
+    pub fn contains(self, field: &str, text: &str) -> Self {
+        self.matches_regex(field, &escape_regex(text))
+    }
+
+    /// Match annotations targeting `source`, whether the target is the IRI
+    /// itself, a `SpecificResource` with it as `source`, or an array of
+    /// either.
+    ///
+    /// Unlike [`or`](Self::or), this keeps another `or` on the same query:
+    /// both have to hold.
+    pub fn target_source(self, source: &str) -> Self {
+        self.any_of([
+            Query::new().field("target", source),
+            Query::new().field("target.source", source),
+        ])
+    }
+
+    /// Match annotations with a body of `purpose`, such as `tagging`, and,
+    /// if given, `value`: the text of a `TextualBody` or the IRI of a
+    /// resource body.
+    ///
+    /// With several bodies, the purpose and the value may match different
+    /// ones. With a value this is an `or` kept alongside others, like
+    /// [`target_source`](Self::target_source).
+    pub fn body_purpose(self, purpose: &str, value: Option<&str>) -> Self {
+        let query = self.field("body.purpose", purpose);
+        match value {
+            Some(value) => query.any_of([
+                Query::new().field("body.value", value),
+                Query::new().field("body.id", value),
+            ]),
+            None => query,
+        }
+    }
+
+    /// Match annotations by the agent `creator`, given as an IRI or as the
+    /// `id` of a nested agent. An `or` kept alongside others, like
+    /// [`target_source`](Self::target_source).
+    pub fn creator(self, creator: &str) -> Self {
+        self.any_of([
+            Query::new().field("creator", creator),
+            Query::new().field("creator.id", creator),
+        ])
+    }
+
+    /// Match annotations with a text anchor target on `source` overlapping
+    /// `start..=end`.
+    pub fn overlaps_with_text_anchor_range(self, source: &str, start: u64, end: u64) -> Self {
+        self.range_condition(":overlapsWithTextAnchorRange", source, start, end)
+    }
+
+    /// Match annotations with a text anchor target on `source` lying within
+    /// `start..=end`.
+    pub fn is_within_text_anchor_range(self, source: &str, start: u64, end: u64) -> Self {
+        self.range_condition(":isWithinTextAnchorRange", source, start, end)
+    }
+
+    /// Match annotations matching any of `queries`.
+    pub fn or(mut self, queries: impl IntoIterator<Item = Query>) -> Self {
+        let queries = queries.into_iter().map(Query::into_value).collect();
+        self.0.insert(":or".to_string(), Value::Array(queries));
+        self
+    }
+
+    /// Match annotations matching all of `queries`.
+    pub fn and(mut self, queries: impl IntoIterator<Item = Query>) -> Self {
+        let queries = queries.into_iter().map(Query::into_value).collect();
+        self.0.insert(":and".to_string(), Value::Array(queries));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The query as sent to the server.
+    pub fn as_json(&self) -> &Map<String, Value> {
+        &self.0
+    }
+
+    pub fn into_value(self) -> Value {
+        Value::Object(self.0)
+    }
+
+    /// Whether `operator` is used anywhere in the query.
+    fn uses_operator(&self, operator: &str) -> bool {
+        fn uses(value: &Value, operator: &str) -> bool {
+            match value {
+                Value::Object(map) => map
+                    .iter()
+                    .any(|(key, value)| key == operator || uses(value, operator)),
+                Value::Array(values) => values.iter().any(|value| uses(value, operator)),
+                _ => false,
+            }
+        }
+        self.0
+            .iter()
+            .any(|(key, value)| key == operator || uses(value, operator))
+    }
+
+    fn condition(mut self, field: &str, operator: &str, value: Value) -> Self {
+        let entry = self
+            .0
+            .entry(field.to_string())
+            .or_insert_with(|| Value::Object(Map::new()));
+        if !entry.is_object() {
+            // An equality on the field is replaced, it can't be combined.
+            *entry = Value::Object(Map::new());
+        }
+        if let Value::Object(conditions) = entry {
+            conditions.insert(operator.to_string(), value);
+        }
+        self
+    }
+
+    /// [`or`](Self::or), unless the query has one already: then the new one
+    /// is added to its `:and`, so that both hold.
+    fn any_of(mut self, queries: impl IntoIterator<Item = Query>) -> Self {
+        if !self.0.contains_key(":or") {
+            return self.or(queries);
+        }
+        let any = Query::new().or(queries).into_value();
+        match self.0.get_mut(":and") {
+            Some(Value::Array(all)) => all.push(any),
+            _ => {
+                self.0.insert(":and".to_string(), Value::Array(vec![any]));
+            }
+        }
+        self
+    }
+
+    fn range_condition(mut self, operator: &str, source: &str, start: u64, end: u64) -> Self {
+        self.0.insert(
+            operator.to_string(),
+            json!({"source": source, "start": start, "end": end}),
+        );
+        self
+    }
+}
+
+/// The direction of a [`Sort`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SortDirection {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+/// Ordering of search results on a field, for
+/// [`search_sorted`](AnnoRepoClient::search_sorted).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sort {
+    pub field: String,
+    pub direction: SortDirection,
+}
+
+impl Sort {
+    pub fn ascending(field: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            direction: SortDirection::Ascending,
+        }
+    }
+
+    pub fn descending(field: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            direction: SortDirection::Descending,
+        }
+    }
+
+    fn param(&self) -> String {
+        let direction = match self.direction {
+            SortDirection::Ascending => "asc",
+            SortDirection::Descending => "desc",
+        };
+        format!("{}:{}", self.field, direction)
+    }
+}
+
+const REGEX_OPERATOR: &str = ":matchesRegex";
+
+fn escape_regex(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if "\\.+*?()|[]{}^$".contains(c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+impl From<HashMap<&str, &str>> for Query {
+    fn from(query: HashMap<&str, &str>) -> Self {
+        query
+            .into_iter()
+            .fold(Query::new(), |query, (field, value)| {
+                query.field(field, value)
+            })
+    }
+}
+
+impl AnnoRepoClient {
+    /// Create a search in the container for `query`.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container)))]
+    pub async fn search(&self, query: &Query) -> Result<SearchInfo<'_>, Error> {
+        self.search_sorted(query, &[]).await
+    }
+
+    /// Create a search whose results are ordered by `sort`, the first key
+    /// taking precedence, sent as `sort=field:asc,field:desc`.
+    ///
+    /// The order is part of the search, so every page read from it follows
+    /// it, which makes resuming from a page deterministic. Sorting needs an
+    /// index on each field; the server rejects the search otherwise, and its
+    /// error is returned as is, see [`Error::api_error`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container)))]
+    pub async fn search_sorted(
+        &self,
+        query: &Query,
+        sort: &[Sort],
+    ) -> Result<SearchInfo<'_>, Error> {
+        self.check_query_fields(query).await?;
+        let (search_id, location) = self.create_search_in(&self.container, query, sort).await?;
+
+        SearchInfo::new(self, search_id, location)
+    }
+
+    /// Create a search in `container`, returning its id and URL.
+    pub(crate) async fn create_search_in(
+        &self,
+        container: &str,
+        query: &Query,
+        sort: &[Sort],
+    ) -> Result<(String, String), Error> {
+        if query.uses_operator(REGEX_OPERATOR) {
+            self.require_server_version("Regular expression search", REGEX_SERVER_VERSION)
+                .await?;
+        }
+        let url = format!("{}/services/{}/search", self.base_url, segment(container));
+        let mut url = reqwest::Url::parse(&url)?;
+        if !sort.is_empty() {
+            let sort: Vec<_> = sort.iter().map(Sort::param).collect();
+            url.query_pairs_mut().append_pair("sort", &sort.join(","));
+        }
+
+        let res = self.send(self.client.post(url).json(query)).await?;
+
+        if let Some(location) = res.location()? {
+            let search_id = match location.rsplit_once('/') {
+                Some((_, id)) if !id.is_empty() => id,
+                _ => return Err(Error::InvalidLocation(location.to_string())),
+            };
+
+            Ok((search_id.to_string(), location.to_string()))
+        } else {
+            Err(Error::UrlNotFound)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conditions_are_serialized_in_the_query_language() {
+        let query = Query::new()
+            .field("body.type", "Person")
+            .greater_than_or_equal("body.start", 100)
+            .less_than("body.start", 200)
+            .is_in("motivation", ["tagging", "classifying"])
+            .overlaps_with_text_anchor_range("https://example.com/text/1", 4, 7);
+
+        assert_eq!(
+            serde_json::to_value(&query).unwrap(),
+            json!({
+                "body.type": "Person",
+                "body.start": {":isGreaterThanOrEqualTo": 100, ":isLessThan": 200},
+                "motivation": {":isIn": ["tagging", "classifying"]},
+                ":overlapsWithTextAnchorRange": {
+                    "source": "https://example.com/text/1",
+                    "start": 4,
+                    "end": 7
+                }
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn sorted_searches_send_their_order() {
+        use wiremock::matchers::{method, path, query_param};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/services/c/search"))
+            .and(query_param("sort", "body.start:asc,modified:desc"))
+            .respond_with(
+                ResponseTemplate::new(201)
+                    .insert_header("location", format!("{}/services/c/search/s1", server.uri())),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        let search = client
+            .search_sorted(
+                &Query::new().field("body.type", "Person"),
+                &[Sort::ascending("body.start"), Sort::descending("modified")],
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(search.search_id(), "s1");
+    }
+
+    #[test]
+    fn contains_escapes_its_text() {
+        let query = Query::new().contains("body.value", "St. Petersburg (1703)");
+
+        assert_eq!(
+            query.into_value(),
+            json!({"body.value": {":matchesRegex": r"St\. Petersburg \(1703\)"}})
+        );
+    }
+
+    #[tokio::test]
+    async fn regex_searches_fail_early_on_older_servers() {
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/about"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "appName": "AnnoRepo",
+                "version": "0.7.2",
+                "startedAt": "2024-03-01T09:00:00.000Z",
+                "withAuthentication": false
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(0)
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+        let query = Query::new().or([Query::new().matches_regex("body.value", "^Amst")]);
+
+        let error = client.search(&query).await.unwrap_err();
+
+        assert!(matches!(
+            error,
+            Error::UnsupportedByServer { since, .. } if since == REGEX_SERVER_VERSION
+        ));
+    }
+
+    #[test]
+    fn target_source_matches_iris_and_specific_resources() {
+        let query = Query::new()
+            .field("body.type", "Person")
+            .target_source("https://example.com/text/1");
+
+        assert_eq!(
+            query.into_value(),
+            json!({
+                "body.type": "Person",
+                ":or": [
+                    {"target": "https://example.com/text/1"},
+                    {"target.source": "https://example.com/text/1"}
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn target_source_keeps_an_earlier_or() {
+        let query = Query::new()
+            .or([
+                Query::new().field("body.type", "Person"),
+                Query::new().field("body.type", "Place"),
+            ])
+            .target_source("t");
+
+        assert_eq!(
+            query.into_value(),
+            json!({
+                ":or": [{"body.type": "Person"}, {"body.type": "Place"}],
+                ":and": [{":or": [{"target": "t"}, {"target.source": "t"}]}]
+            })
+        );
+    }
+
+    #[test]
+    fn body_purpose_matches_textual_and_resource_bodies() {
+        let query = Query::new().body_purpose("tagging", Some("letter"));
+
+        assert_eq!(
+            query.into_value(),
+            json!({
+                "body.purpose": "tagging",
+                ":or": [{"body.value": "letter"}, {"body.id": "letter"}]
+            })
+        );
+    }
+
+    #[test]
+    fn body_purpose_and_target_source_both_hold() {
+        let query = Query::new()
+            .target_source("t")
+            .body_purpose("tagging", Some("letter"));
+
+        assert_eq!(
+            query.into_value(),
+            json!({
+                "body.purpose": "tagging",
+                ":or": [{"target": "t"}, {"target.source": "t"}],
+                ":and": [{":or": [{"body.value": "letter"}, {"body.id": "letter"}]}]
+            })
+        );
+    }
+
+    #[test]
+    fn creator_matches_iris_and_nested_agents() {
+        let query = Query::new().creator("https://example.com/users/42");
+
+        assert_eq!(
+            query.into_value(),
+            json!({":or": [
+                {"creator": "https://example.com/users/42"},
+                {"creator.id": "https://example.com/users/42"}
+            ]})
+        );
+    }
+
+    #[test]
+    fn creator_and_target_sources_all_hold() {
+        let query = Query::new()
+            .creator("c")
+            .target_source("t1")
+            .target_source("t2");
+
+        assert_eq!(
+            query.into_value(),
+            json!({
+                ":or": [{"creator": "c"}, {"creator.id": "c"}],
+                ":and": [
+                    {":or": [{"target": "t1"}, {"target.source": "t1"}]},
+                    {":or": [{"target": "t2"}, {"target.source": "t2"}]}
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn queries_can_be_combined() {
+        let query = Query::new().or([
+            Query::new().field("body.type", "Person"),
+            Query::new().field("body.type", "Place"),
+        ]);
+
+        assert_eq!(
+            query.into_value(),
+            json!({":or": [{"body.type": "Person"}, {"body.type": "Place"}]})
+        );
+    }
+}
@@ -0,0 +1,56 @@
+use crate::{AnnoRepoClient, Error};
+use reqwest::Method;
+
+impl AnnoRepoClient {
+    /// A request to `path` on the server, relative to the base URL, for
+    /// endpoints this client doesn't cover yet. It carries the client's
+    /// API key and default headers; send it with
+    /// [`send_raw`](Self::send_raw).
+    pub fn request(&self, method: Method, path: &str) -> reqwest::RequestBuilder {
+        let url = format!("{}/{}", self.base_url, path.trim_start_matches('/'));
+        self.client.request(method, url)
+    }
+
+    /// Send a request, such as one from [`request`](Self::request), with the
+    /// client's retries, circuit breaker, interceptors and request IDs, and
+    /// return the response as is, whatever its status.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn send_raw(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, Error> {
+        let (_, res) = self.execute(request).await?;
+        Ok(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn raw_requests_are_authenticated() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/new-endpoint"))
+            .and(header("authorization", "Bearer secret"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"ok": true})))
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::builder(server.uri(), "c".to_string())
+            .api_key("secret")
+            .build()
+            .unwrap();
+
+        let res = client
+            .send_raw(client.request(Method::GET, "/services/c/new-endpoint"))
+            .await
+            .unwrap();
+
+        assert_eq!(res.status(), 200);
+        assert_eq!(res.json::<Value>().await.unwrap(), json!({"ok": true}));
+    }
+}
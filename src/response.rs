@@ -0,0 +1,206 @@
+use crate::page::{ArrayDecoder, PageDecoder, ResultPage};
+use crate::{DeserializationMode, Error, RequestContext, UnknownFields, LOCATION_HEADER};
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
+use reqwest::header::HeaderMap;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+/// Most bytes reserved for a body before they arrive, whatever its
+/// `Content-Length` says.
+const MAX_PREALLOCATION: u64 = 1 << 20;
+
+/// A successful response, together with the request it answers.
+#[derive(Debug)]
+pub(crate) struct Response {
+    request: RequestContext,
+    inner: reqwest::Response,
+    limit: SizeLimit,
+}
+
+impl Response {
+    /// Wrap `inner`, failing reads of bodies larger than `max_size` bytes.
+    pub(crate) fn new(
+        request: RequestContext,
+        inner: reqwest::Response,
+        max_size: Option<u64>,
+    ) -> Self {
+        let limit = SizeLimit {
+            max_size,
+            received: 0,
+        };
+        Self {
+            request,
+            inner,
+            limit,
+        }
+    }
+
+    pub(crate) fn status(&self) -> reqwest::StatusCode {
+        self.inner.status()
+    }
+
+    pub(crate) fn headers(&self) -> &HeaderMap {
+        self.inner.headers()
+    }
+
+    /// The `Location` header, if the server sent one.
+    pub(crate) fn location(&self) -> Result<Option<&str>, Error> {
+        match self.headers().get(LOCATION_HEADER) {
+            Some(header) => header.to_str().map(Some).map_err(|_| {
+                Error::InvalidLocation(String::from_utf8_lossy(header.as_bytes()).into())
+            }),
+            None => Ok(None),
+        }
+    }
+
+    pub(crate) async fn json<T: DeserializeOwned>(self) -> Result<T, Error> {
+        let (request, body) = self.body().await?;
+        decode(&request, &body)
+    }
+
+    /// The whole body, for reading it more than once.
+    pub(crate) async fn body(self) -> Result<(RequestContext, Vec<u8>), Error> {
+        let Self {
+            request,
+            inner,
+            mut limit,
+        } = self;
+        let declared = inner.content_length();
+        limit.check(&request, declared.unwrap_or(0))?;
+        // The declared length may be bogus, so only part of it is reserved
+        // up front.
+        let capacity = declared.unwrap_or(0).min(MAX_PREALLOCATION);
+        let mut body = Vec::with_capacity(capacity as usize);
+        let mut chunks = inner.bytes_stream();
+        while let Some(chunk) = chunks.next().await {
+            let chunk = chunk.map_err(|e| Error::request(request.clone(), e))?;
+            limit.add(&request, chunk.len())?;
+            body.extend_from_slice(&chunk);
+        }
+
+        Ok((request, body))
+    }
+
+    /// Decode a page of search results while it is being received.
+    pub(crate) async fn result_page(self, decoder: &mut PageDecoder) -> Result<ResultPage, Error> {
+        let decode_error = |request: &RequestContext, source| Error::Decode {
+            request: Box::new(request.clone()),
+            source,
+        };
+        let mut limit = self.limit;
+        limit.check(&self.request, self.inner.content_length().unwrap_or(0))?;
+        let mut chunks = self.inner.bytes_stream();
+        while let Some(chunk) = chunks.next().await {
+            let chunk = chunk.map_err(|e| Error::request(self.request.clone(), e))?;
+            limit.add(&self.request, chunk.len())?;
+            decoder
+                .feed(&chunk)
+                .map_err(|e| decode_error(&self.request, e))?;
+        }
+
+        decoder.finish().map_err(|e| decode_error(&self.request, e))
+    }
+
+    /// The items of a body that is a JSON array, decoded while it is being
+    /// received.
+    pub(crate) fn array_items(self) -> impl Stream<Item = Result<Value, Error>> {
+        let declared = self.inner.content_length().unwrap_or(0);
+        let state = (
+            self.inner.bytes_stream(),
+            ArrayDecoder::default(),
+            self.request,
+            self.limit,
+        );
+        stream::try_unfold(
+            state,
+            move |(mut chunks, mut decoder, request, mut limit)| async move {
+                limit.check(&request, declared)?;
+                let decode_error = |request: &RequestContext, source| Error::Decode {
+                    request: Box::new(request.clone()),
+                    source,
+                };
+                while let Some(chunk) = chunks.next().await {
+                    let chunk = chunk.map_err(|e| Error::request(request.clone(), e))?;
+                    limit.add(&request, chunk.len())?;
+                    let items = decoder
+                        .feed(&chunk)
+                        .map_err(|e| decode_error(&request, e))?;
+                    if !items.is_empty() {
+                        let items = stream::iter(items.into_iter().map(Ok));
+                        return Ok::<_, Error>(Some((items, (chunks, decoder, request, limit))));
+                    }
+                }
+                decoder.finish().map_err(|e| decode_error(&request, e))?;
+
+                Ok(None)
+            },
+        )
+        .try_flatten()
+    }
+
+    /// Deserialize into a model, rejecting undeclared fields in strict mode.
+    pub(crate) async fn typed<T>(self, mode: DeserializationMode) -> Result<T, Error>
+    where
+        T: DeserializeOwned + UnknownFields,
+    {
+        let request = self.request.clone();
+        let value: T = self.json().await?;
+        check_fields(request, value, mode)
+    }
+}
+
+/// The number of bytes read from a body, against its maximum.
+#[derive(Debug, Clone, Copy)]
+struct SizeLimit {
+    max_size: Option<u64>,
+    received: u64,
+}
+
+impl SizeLimit {
+    fn add(&mut self, request: &RequestContext, len: usize) -> Result<(), Error> {
+        self.received += len as u64;
+        self.check(request, 0)
+    }
+
+    /// Fail if the body is, or is going to be, larger than allowed.
+    fn check(&self, request: &RequestContext, expected: u64) -> Result<(), Error> {
+        match self.max_size {
+            Some(max_size) if self.received.max(expected) > max_size => {
+                Err(Error::ResponseTooLarge {
+                    request: Box::new(request.clone()),
+                    max_size,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+pub(crate) fn decode<T: DeserializeOwned>(
+    request: &RequestContext,
+    body: &[u8],
+) -> Result<T, Error> {
+    serde_json::from_slice(body).map_err(|source| Error::Decode {
+        request: Box::new(request.clone()),
+        source,
+    })
+}
+
+/// Pass `value` on, unless it has undeclared fields in strict mode.
+pub(crate) fn check_fields<T: UnknownFields>(
+    request: RequestContext,
+    value: T,
+    mode: DeserializationMode,
+) -> Result<T, Error> {
+    if mode == DeserializationMode::Strict {
+        let fields = value.unknown_fields();
+        if !fields.is_empty() {
+            return Err(Error::UnknownFields {
+                request: Box::new(request),
+                fields,
+            });
+        }
+    }
+
+    Ok(value)
+}
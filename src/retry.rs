@@ -0,0 +1,175 @@
+use crate::time::Instant;
+use crate::{Error, RequestContext};
+use reqwest::{Method, StatusCode};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// When and how often failed requests are retried.
+///
+/// Only idempotent requests (GET, HEAD, PUT, DELETE, OPTIONS) are retried,
+/// unless `retry_non_idempotent` is set: retrying a POST that did reach the
+/// server may create a duplicate annotation or search.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    /// Delay before the first retry; doubled for every following one.
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub retry_non_idempotent: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(5),
+            retry_non_idempotent: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub(crate) fn allows(&self, method: &Method, attempt: u32) -> bool {
+        attempt < self.max_retries && (self.retry_non_idempotent || is_idempotent(method))
+    }
+
+    pub(crate) fn backoff(&self, attempt: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_backoff)
+    }
+}
+
+fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS | Method::TRACE
+    )
+}
+
+/// Whether the outcome of an attempt is worth retrying: connection problems,
+/// timeouts and the statuses servers use for temporary unavailability.
+pub(crate) fn is_retryable(result: &Result<(RequestContext, reqwest::Response), Error>) -> bool {
+    match result {
+        Ok((_, res)) => matches!(
+            res.status(),
+            StatusCode::TOO_MANY_REQUESTS
+                | StatusCode::BAD_GATEWAY
+                | StatusCode::SERVICE_UNAVAILABLE
+                | StatusCode::GATEWAY_TIMEOUT
+        ),
+        #[cfg(not(target_arch = "wasm32"))]
+        Err(Error::Request { source, .. }) => source.is_connect() || source.is_timeout(),
+        // Fetch doesn't say why a request failed.
+        #[cfg(target_arch = "wasm32")]
+        Err(Error::Request { .. }) => true,
+        Err(_) => false,
+    }
+}
+
+/// Settings for the circuit breaker enabled with
+/// [`AnnoRepoClientBuilder::circuit_breaker`](crate::AnnoRepoClientBuilder::circuit_breaker).
+#[derive(Debug, Clone)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive server errors after which the circuit opens.
+    pub failure_threshold: u32,
+    /// How long requests fail fast once the circuit is open. After that,
+    /// requests are sent again, and a single further failure reopens it.
+    pub cooldown: Duration,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    state: Mutex<CircuitState>,
+}
+
+#[derive(Debug, Default)]
+struct CircuitState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    pub(crate) fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            state: Mutex::default(),
+        }
+    }
+
+    /// Fail fast while the circuit is open.
+    pub(crate) fn check(&self) -> Result<(), Error> {
+        let state = self.state.lock().unwrap();
+        match state.open_until {
+            Some(until) if until > Instant::now() => {
+                Err(Error::CircuitOpen(until - Instant::now()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Count a request that reached the network; errors raised before or
+    /// after it, by interceptors or decoding, leave the state as it is.
+    pub(crate) fn record(&self, result: &Result<(RequestContext, reqwest::Response), Error>) {
+        let failed = match result {
+            Ok((_, res)) => res.status().is_server_error(),
+            Err(Error::Request { .. }) => true,
+            Err(_) => return,
+        };
+
+        let mut state = self.state.lock().unwrap();
+        if failed {
+            state.consecutive_failures += 1;
+            if state.consecutive_failures >= self.config.failure_threshold {
+                state.open_until = Some(Instant::now() + self.config.cooldown);
+            }
+        } else {
+            *state = CircuitState::default();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn post_is_only_retried_when_allowed() {
+        let policy = RetryPolicy::default();
+        assert!(policy.allows(&Method::GET, 0));
+        assert!(policy.allows(&Method::PUT, 2));
+        assert!(!policy.allows(&Method::GET, 3));
+        assert!(!policy.allows(&Method::POST, 0));
+
+        let policy = RetryPolicy {
+            retry_non_idempotent: true,
+            ..RetryPolicy::default()
+        };
+        assert!(policy.allows(&Method::POST, 0));
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        let policy = RetryPolicy {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_millis(350),
+            ..RetryPolicy::default()
+        };
+
+        assert_eq!(policy.backoff(0), Duration::from_millis(100));
+        assert_eq!(policy.backoff(1), Duration::from_millis(200));
+        assert_eq!(policy.backoff(2), Duration::from_millis(350));
+        assert_eq!(policy.backoff(40), Duration::from_millis(350));
+    }
+}
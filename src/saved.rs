@@ -0,0 +1,110 @@
+use crate::{AnnoRepoClient, Error, Query, SearchInfo};
+use serde::{Deserialize, Serialize};
+
+/// A query kept by an application, e.g. in a config file or a database, to
+/// be run again later.
+///
+/// The query is stored in the AnnoRepo query language, not in a format of
+/// this crate, so saved queries stay readable across its versions. The
+/// fields are those of a server-side custom query, see
+/// [`create_custom_query`](AnnoRepoClient::create_custom_query).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedQuery {
+    pub name: String,
+    pub query: Query,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Whether other users may run the query once it is on the server.
+    #[serde(default)]
+    pub public: bool,
+}
+
+impl SavedQuery {
+    pub fn new(name: impl Into<String>, query: Query) -> Self {
+        Self {
+            name: name.into(),
+            query,
+            label: None,
+            description: None,
+            public: false,
+        }
+    }
+}
+
+impl AnnoRepoClient {
+    /// Run a saved query as a search in the container.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container, name = %saved.name)))]
+    pub async fn search_saved(&self, saved: &SavedQuery) -> Result<SearchInfo<'_>, Error> {
+        self.search(&saved.query).await
+    }
+
+    /// Store a saved query on the server as a custom query, returning its
+    /// URL.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(name = %saved.name)))]
+    pub async fn create_custom_query(&self, saved: &SavedQuery) -> Result<String, Error> {
+        let url = format!("{}/global/custom-query", self.base_url);
+
+        let res = self.send(self.client.post(&url).json(saved)).await?;
+
+        let location = res.location()?.map(str::to_string);
+        Ok(location.unwrap_or_else(|| format!("{url}/{}", saved.name)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{body_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn saved_queries_are_read_back_from_their_stored_form() {
+        let stored = json!({
+            "name": "people",
+            "query": {"body.type": "Person", "body.start": {":isLessThan": 200}},
+            "label": "People"
+        });
+
+        let saved: SavedQuery = serde_json::from_value(stored.clone()).unwrap();
+
+        assert_eq!(
+            saved.query,
+            Query::new()
+                .field("body.type", "Person")
+                .less_than("body.start", 200)
+        );
+        assert!(!saved.public);
+        let mut expected = stored;
+        expected["public"] = json!(false);
+        assert_eq!(serde_json::to_value(&saved).unwrap(), expected);
+    }
+
+    #[tokio::test]
+    async fn saved_queries_become_custom_queries() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/global/custom-query"))
+            .and(body_json(json!({
+                "name": "people",
+                "query": {"body.type": "Person"},
+                "public": false
+            })))
+            .respond_with(ResponseTemplate::new(201).insert_header(
+                "location",
+                format!("{}/global/custom-query/people", server.uri()),
+            ))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+        let saved = SavedQuery::new("people", Query::new().field("body.type", "Person"));
+
+        let url = client.create_custom_query(&saved).await.unwrap();
+
+        assert_eq!(url, format!("{}/global/custom-query/people", server.uri()));
+    }
+}
@@ -0,0 +1,244 @@
+use crate::page::PageDecoder;
+use crate::{AnnoRepoClient, Error};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+const MAX_EXAMPLES: usize = 3;
+
+/// JSON type of a value seen for a field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValueType {
+    Null,
+    Bool,
+    Number,
+    String,
+    Array,
+    Object,
+}
+
+impl ValueType {
+    fn of(value: &Value) -> Self {
+        match value {
+            Value::Null => Self::Null,
+            Value::Bool(_) => Self::Bool,
+            Value::Number(_) => Self::Number,
+            Value::String(_) => Self::String,
+            Value::Array(_) => Self::Array,
+            Value::Object(_) => Self::Object,
+        }
+    }
+}
+
+/// What was observed for a single field path in a sample of annotations.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct FieldStats {
+    /// Number of sampled annotations containing the field.
+    pub occurrences: usize,
+    pub value_types: BTreeSet<ValueType>,
+    /// Number of distinct scalar values seen.
+    pub cardinality: usize,
+    /// Up to three distinct scalar values.
+    pub examples: Vec<Value>,
+}
+
+/// A report of the fields found in a sample of annotations.
+///
+/// Field paths use the dotted notation of search queries (`body.purpose`);
+/// array elements are reported under the path of the array itself.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct FieldSchema {
+    pub sample_size: usize,
+    pub fields: BTreeMap<String, FieldStats>,
+}
+
+impl FieldSchema {
+    pub fn from_annotations<'a, I>(annotations: I) -> Self
+    where
+        I: IntoIterator<Item = &'a Value>,
+    {
+        let mut builder = SchemaBuilder::default();
+        for annotation in annotations {
+            builder.add(annotation);
+        }
+        builder.finish()
+    }
+}
+
+#[derive(Default)]
+struct SchemaBuilder {
+    sample_size: usize,
+    fields: BTreeMap<String, FieldStats>,
+    distinct_values: HashMap<String, HashSet<String>>,
+}
+
+impl SchemaBuilder {
+    fn add(&mut self, annotation: &Value) {
+        self.sample_size += 1;
+        let mut seen = HashSet::new();
+        if let Value::Object(map) = annotation {
+            for (key, value) in map {
+                self.visit(key.clone(), value, &mut seen);
+            }
+        }
+        for path in seen {
+            self.fields.entry(path).or_default().occurrences += 1;
+        }
+    }
+
+    fn visit(&mut self, path: String, value: &Value, seen: &mut HashSet<String>) {
+        let stats = self.fields.entry(path.clone()).or_default();
+        stats.value_types.insert(ValueType::of(value));
+        match value {
+            Value::Object(map) => {
+                for (key, child) in map {
+                    self.visit(format!("{path}.{key}"), child, seen);
+                }
+            }
+            Value::Array(items) => {
+                for item in items {
+                    match item {
+                        Value::Object(map) => {
+                            for (key, child) in map {
+                                self.visit(format!("{path}.{key}"), child, seen);
+                            }
+                        }
+                        Value::Array(_) => {}
+                        scalar => self.record_scalar(&path, scalar),
+                    }
+                }
+            }
+            scalar => self.record_scalar(&path, scalar),
+        }
+        seen.insert(path);
+    }
+
+    fn record_scalar(&mut self, path: &str, value: &Value) {
+        let distinct = self.distinct_values.entry(path.to_string()).or_default();
+        if distinct.insert(value.to_string()) {
+            let stats = self.fields.entry(path.to_string()).or_default();
+            stats.cardinality = distinct.len();
+            if stats.examples.len() < MAX_EXAMPLES {
+                stats.examples.push(value.clone());
+            }
+        }
+    }
+
+    fn finish(self) -> FieldSchema {
+        FieldSchema {
+            sample_size: self.sample_size,
+            fields: self.fields,
+        }
+    }
+}
+
+impl AnnoRepoClient {
+    /// Sample up to `sample_size` annotations from the container and report
+    /// the fields they use.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container, sample_size)))]
+    pub async fn infer_schema(&self, sample_size: usize) -> Result<FieldSchema, Error> {
+        let search = self.create_search(HashMap::new()).await?;
+        let mut builder = SchemaBuilder::default();
+        let mut decoder = PageDecoder::default();
+        let mut page = 0;
+        while builder.sample_size < sample_size {
+            let (items, has_next) = self
+                .read_result_items(&self.container, search.search_id(), page, &mut decoder)
+                .await?;
+            for item in items.iter().take(sample_size - builder.sample_size) {
+                builder.add(item);
+            }
+            if !has_next {
+                break;
+            }
+            page += 1;
+        }
+
+        Ok(builder.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn fields_are_collected_from_nested_objects_and_arrays() {
+        let annotations = [
+            json!({
+                "type": "Annotation",
+                "body": [
+                    {"purpose": "tagging", "value": "person"},
+                    {"purpose": "tagging", "value": "place"}
+                ],
+                "target": "https://example.com/text/1"
+            }),
+            json!({
+                "type": "Annotation",
+                "body": {"purpose": "commenting", "value": "nice"},
+                "target": {"source": "https://example.com/text/2"}
+            }),
+        ];
+
+        let schema = FieldSchema::from_annotations(&annotations);
+
+        assert_eq!(schema.sample_size, 2);
+        let purpose = &schema.fields["body.purpose"];
+        assert_eq!(purpose.occurrences, 2);
+        assert_eq!(purpose.cardinality, 2);
+        assert_eq!(purpose.examples, [json!("tagging"), json!("commenting")]);
+        assert_eq!(
+            schema.fields["body"].value_types,
+            BTreeSet::from([ValueType::Array, ValueType::Object])
+        );
+        assert_eq!(
+            schema.fields["target"].value_types,
+            BTreeSet::from([ValueType::String, ValueType::Object])
+        );
+        assert_eq!(schema.fields["target.source"].occurrences, 1);
+        assert_eq!(schema.fields["body.value"].cardinality, 3);
+    }
+
+    #[tokio::test]
+    async fn infer_schema_stops_at_the_sample_size() {
+        use wiremock::matchers::{method, path, query_param};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/services/c/search"))
+            .respond_with(
+                ResponseTemplate::new(201)
+                    .insert_header("location", format!("{}/services/c/search/s1", server.uri())),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/search/s1"))
+            .and(query_param("page", "0"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [{"type": "Annotation"}, {"type": "Annotation"}],
+                "next": format!("{}/services/c/search/s1?page=1", server.uri())
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/search/s1"))
+            .and(query_param("page", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [{"type": "Annotation", "motivation": "tagging"}, {"type": "Annotation"}]
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        let schema = client.infer_schema(3).await.unwrap();
+
+        assert_eq!(schema.sample_size, 3);
+        assert_eq!(schema.fields["type"].occurrences, 3);
+        assert_eq!(schema.fields["motivation"].occurrences, 1);
+    }
+}
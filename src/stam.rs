@@ -0,0 +1,245 @@
+use crate::accessors::{items, resource_iri};
+use crate::{AnnoRepoClient, AnnotationIdentifier, Error, Query, UploadOptions};
+use ::stam::{
+    AnnotationBuilder, AnnotationHandle, AnnotationStore, DataValue, Offset, SelectorBuilder,
+    WebAnnoConfig,
+};
+use futures::TryStreamExt;
+use serde_json::Value;
+
+/// The STAM data set the Web Annotation vocabulary goes in, as in STAM's own
+/// Web Annotation conversion.
+const NS_ANNO: &str = "http://www.w3.org/ns/anno/";
+
+/// Fields that describe the annotation itself rather than its body.
+const SKIPPED_FIELDS: [&str; 4] = ["@context", "id", "type", "target"];
+
+/// Add a Web Annotation to `store`, returning the handle of the STAM
+/// annotation.
+///
+/// The targets must be on text resources in the store, with the IRI of the
+/// target source as their id: a target with a `TextPositionSelector` selects
+/// that range of the text, any other target the whole resource. The fields
+/// of an object body, and the other fields of the annotation, become data in
+/// the `http://www.w3.org/ns/anno/` set, so they survive a round trip through
+/// [`AnnoRepoClient::upload_stam`].
+pub fn annotate_stam(
+    store: &mut AnnotationStore,
+    annotation: &Value,
+) -> Result<AnnotationHandle, Error> {
+    let mut selectors: Vec<SelectorBuilder> = items(&annotation["target"])
+        .filter_map(|target| {
+            let source = resource_iri(target)?.to_string();
+            Some(match text_position(target) {
+                Some((start, end)) => {
+                    SelectorBuilder::textselector(source, Offset::simple(start, end))
+                }
+                None => SelectorBuilder::resourceselector(source),
+            })
+        })
+        .collect();
+    let target = match selectors.len() {
+        0 => return Err(Error::Stam(::stam::StamError::NoTarget("(annotate_stam)"))),
+        1 => selectors.remove(0),
+        _ => SelectorBuilder::multiselector(selectors),
+    };
+
+    let mut builder = AnnotationBuilder::new().with_target(target);
+    if let Some(id) = annotation["id"].as_str() {
+        builder = builder.with_id(id);
+    }
+    let mut data: Vec<(&str, &Value)> = Vec::new();
+    if let Value::Object(fields) = annotation {
+        for (key, value) in fields {
+            match (key.as_str(), value) {
+                ("body", Value::Object(body)) => {
+                    data.extend(body.iter().map(|(key, value)| (key.as_str(), value)));
+                }
+                (key, _) if SKIPPED_FIELDS.contains(&key) => {}
+                (key, value) => data.push((key, value)),
+            }
+        }
+    }
+    for (key, value) in data {
+        builder = builder.with_data(NS_ANNO, key.to_string(), data_value(value));
+    }
+
+    store.annotate(builder).map_err(Error::Stam)
+}
+
+impl AnnoRepoClient {
+    /// Add the results of `query` to `store`, returning the number added;
+    /// see [`annotate_stam`].
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container)))]
+    pub async fn export_stam(
+        &self,
+        query: &Query,
+        store: &mut AnnotationStore,
+    ) -> Result<usize, Error> {
+        let search = self.search(query).await?;
+        let mut annotations = search.annotations();
+        let mut count = 0;
+        while let Some(annotation) = annotations.try_next().await? {
+            annotate_stam(store, &annotation)?;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+
+    /// Add the annotations in `store` to the container, converted to Web
+    /// Annotations by STAM according to `config`.
+    ///
+    /// Annotations on data rather than text can't be converted, and are left
+    /// out.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container)))]
+    pub async fn upload_stam(
+        &self,
+        store: &AnnotationStore,
+        config: &WebAnnoConfig,
+        options: &UploadOptions,
+    ) -> Result<Vec<AnnotationIdentifier>, Error> {
+        let annotations = store
+            .annotations()
+            .map(|annotation| annotation.to_webannotation(config))
+            .filter(|json| !json.is_empty())
+            .map(|json| serde_json::from_str(&json).map_err(|e| Error::Io(e.into())))
+            .collect::<Result<Vec<Value>, Error>>()?;
+
+        self.upload_annotations(&annotations, options).await
+    }
+}
+
+fn text_position(target: &Value) -> Option<(usize, usize)> {
+    let selector =
+        items(&target["selector"]).find(|selector| selector["type"] == "TextPositionSelector")?;
+
+    Some((
+        selector["start"].as_u64()? as usize,
+        selector["end"].as_u64()? as usize,
+    ))
+}
+
+fn data_value(value: &Value) -> DataValue {
+    match value {
+        Value::Null => DataValue::Null,
+        Value::Bool(b) => DataValue::Bool(*b),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => DataValue::Int(i as isize),
+            None => DataValue::Float(n.as_f64().unwrap_or(f64::NAN)),
+        },
+        Value::String(s) => DataValue::String(s.clone()),
+        Value::Array(items) => DataValue::List(items.iter().map(data_value).collect()),
+        Value::Object(fields) => DataValue::Map(
+            fields
+                .iter()
+                .map(|(key, value)| (key.clone(), data_value(value)))
+                .collect(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::stam::{TextResourceBuilder, WebAnnoConfig};
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+    const TEXT: &str = "https://example.com/texts/1";
+
+    fn store() -> AnnotationStore {
+        AnnotationStore::new(::stam::Config::default())
+            .with_id("test")
+            .with_resource(
+                TextResourceBuilder::new()
+                    .with_id(TEXT)
+                    .with_text("Hello world"),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn text_position_targets_select_the_text() {
+        let mut store = store();
+        let annotation = json!({
+            "id": "https://annorepo.example.com/w3c/c/a1",
+            "type": "Annotation",
+            "motivation": "tagging",
+            "body": {"type": "TextualBody", "value": "greeting"},
+            "target": {
+                "source": TEXT,
+                "selector": {"type": "TextPositionSelector", "start": 0, "end": 5}
+            }
+        });
+
+        let handle = annotate_stam(&mut store, &annotation).unwrap();
+
+        let annotation = store.annotation(handle).unwrap();
+        assert_eq!(
+            annotation.id(),
+            Some("https://annorepo.example.com/w3c/c/a1")
+        );
+        assert_eq!(annotation.text_simple(), Some("Hello"));
+        let data: Vec<(String, String)> = annotation
+            .data()
+            .map(|data| (data.key().as_str().to_string(), data.value().to_string()))
+            .collect();
+        assert!(data.contains(&("motivation".to_string(), "tagging".to_string())));
+        assert!(data.contains(&("value".to_string(), "greeting".to_string())));
+    }
+
+    #[test]
+    fn unknown_resources_are_rejected() {
+        let mut store = store();
+        let annotation = json!({"type": "Annotation", "target": "https://example.com/other"});
+
+        assert!(matches!(
+            annotate_stam(&mut store, &annotation),
+            Err(Error::Stam(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn stam_annotations_are_uploaded_as_web_annotations() {
+        let mut store = store();
+        annotate_stam(
+            &mut store,
+            &json!({
+                "id": "a1",
+                "type": "Annotation",
+                "body": {"value": "world"},
+                "target": {
+                    "source": TEXT,
+                    "selector": {"type": "TextPositionSelector", "start": 6, "end": 11}
+                }
+            }),
+        )
+        .unwrap();
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/batch/c/annotations"))
+            .and(|request: &Request| {
+                let batch: Vec<Value> = request.body_json().unwrap();
+                batch.len() == 1
+                    && batch[0]["target"][0]["source"] == TEXT
+                    && batch[0]["target"][0]["selector"]["start"] == 6
+                    && batch[0]["body"]["value"] == "world"
+            })
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                {"containerName": "c", "annotationName": "a1"}
+            ])))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        let identifiers = client
+            .upload_stam(&store, &WebAnnoConfig::default(), &UploadOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(identifiers.len(), 1);
+    }
+}
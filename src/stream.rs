@@ -0,0 +1,472 @@
+use crate::page::PageDecoder;
+use crate::{
+    AnnoIter, AnnoRepoClient, CancellationToken, ContainerPreference, Error, Phase, Progress,
+    ProgressUpdate, SearchInfo,
+};
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
+use serde_json::{Map, Value};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+// Futures of the wasm backend of reqwest aren't `Send`.
+#[cfg(not(target_arch = "wasm32"))]
+type BoxStream<'a, T> = futures::stream::BoxStream<'a, T>;
+#[cfg(target_arch = "wasm32")]
+type BoxStream<'a, T> = futures::stream::LocalBoxStream<'a, T>;
+
+#[cfg(not(target_arch = "wasm32"))]
+fn boxed<'a, S: Stream + Send + 'a>(stream: S) -> BoxStream<'a, S::Item> {
+    stream.boxed()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn boxed<'a, S: Stream + 'a>(stream: S) -> BoxStream<'a, S::Item> {
+    stream.boxed_local()
+}
+
+/// The tokens of [`AnnotationStream::until_cancelled`], checked before each
+/// page is fetched.
+type Cancellations = Arc<Mutex<Vec<CancellationToken>>>;
+
+fn any_cancelled(cancellations: &Cancellations) -> bool {
+    cancellations
+        .lock()
+        .unwrap()
+        .iter()
+        .any(CancellationToken::is_cancelled)
+}
+
+/// A stream of annotations that fetches result pages as it goes.
+pub struct AnnotationStream<'a> {
+    inner: BoxStream<'a, Result<Value, Error>>,
+    cancellations: Cancellations,
+}
+
+impl<'a> AnnotationStream<'a> {
+    pub(crate) fn search_results(
+        client: &'a AnnoRepoClient,
+        container_name: &str,
+        search_id: &str,
+        start_page: u32,
+    ) -> Self {
+        let state = (
+            container_name.to_string(),
+            search_id.to_string(),
+            Some(start_page),
+            PageDecoder::default(),
+        );
+        let cancellations = Cancellations::default();
+        let stop = cancellations.clone();
+        let pages = stream::try_unfold(
+            state,
+            move |(container_name, search_id, page, mut decoder)| {
+                let page = page.filter(|_| !any_cancelled(&stop));
+                async move {
+                    let Some(page) = page else {
+                        return Ok::<_, Error>(None);
+                    };
+                    let (items, has_next) = client
+                        .read_result_items(&container_name, &search_id, page, &mut decoder)
+                        .await?;
+                    let next = has_next.then_some(page + 1);
+
+                    Ok(Some((items, (container_name, search_id, next, decoder))))
+                }
+            },
+        );
+
+        Self {
+            inner: boxed(
+                pages
+                    .map_ok(|items| stream::iter(items.into_iter().map(Ok)))
+                    .try_flatten(),
+            ),
+            cancellations,
+        }
+    }
+
+    pub(crate) fn container_listing(
+        client: &'a AnnoRepoClient,
+        start_page: u32,
+        preference: ContainerPreference,
+    ) -> Self {
+        let cancellations = Cancellations::default();
+        let stop = cancellations.clone();
+        let pages = stream::try_unfold(
+            (Some(start_page), PageDecoder::default()),
+            move |(page, mut decoder)| {
+                let page = page.filter(|_| !any_cancelled(&stop));
+                async move {
+                    let Some(page) = page else {
+                        return Ok::<_, Error>(None);
+                    };
+                    let (items, has_next) = client
+                        .read_container_items(page, preference, &mut decoder)
+                        .await?;
+                    let next = has_next.then_some(page + 1);
+
+                    Ok(Some((items, (next, decoder))))
+                }
+            },
+        );
+
+        Self {
+            inner: boxed(
+                pages
+                    .map_ok(|items| stream::iter(items.into_iter().map(Ok)))
+                    .try_flatten(),
+            ),
+            cancellations,
+        }
+    }
+
+    /// Yield only the value at a JSON pointer (`/body/0/value`) of each
+    /// annotation, skipping annotations that don't have it.
+    ///
+    /// The value is moved out of the annotation, not cloned.
+    pub fn select<P: Into<String>>(self, pointer: P) -> AnnotationStream<'a> {
+        let pointer = pointer.into();
+        AnnotationStream {
+            inner: boxed(self.inner.try_filter_map(move |mut annotation| {
+                let selected = take_pointer(&mut annotation, &pointer);
+                async move { Ok(selected) }
+            })),
+            cancellations: self.cancellations,
+        }
+    }
+
+    /// Reduce each annotation to the given fields, as dotted paths like
+    /// `target.source`, keeping their place in the annotation. Paths through
+    /// arrays apply to each element.
+    ///
+    /// AnnoRepo can't project results itself, so the full annotations are
+    /// still fetched, but not held on to.
+    pub fn project<I, F>(self, fields: I) -> AnnotationStream<'a>
+    where
+        I: IntoIterator<Item = F>,
+        F: AsRef<str>,
+    {
+        let paths = field_paths(fields);
+        AnnotationStream {
+            inner: boxed(
+                self.inner
+                    .map_ok(move |annotation| project_annotation(annotation, &paths)),
+            ),
+            cancellations: self.cancellations,
+        }
+    }
+}
+
+impl<'a> AnnotationStream<'a> {
+    /// Report every annotation yielded to `progress`, as exported out of
+    /// `total`.
+    pub fn with_progress(self, progress: &'a dyn Progress, total: Option<u64>) -> Self {
+        let mut done = 0;
+        AnnotationStream {
+            inner: boxed(self.inner.inspect_ok(move |_| {
+                done += 1;
+                progress.update(ProgressUpdate {
+                    phase: Phase::Exporting,
+                    done,
+                    total,
+                });
+            })),
+            cancellations: self.cancellations,
+        }
+    }
+}
+
+impl AnnotationStream<'_> {
+    /// End the stream once `cancellation` is cancelled, instead of fetching
+    /// the remaining pages.
+    pub fn until_cancelled(self, cancellation: CancellationToken) -> Self {
+        self.cancellations
+            .lock()
+            .unwrap()
+            .push(cancellation.clone());
+        AnnotationStream {
+            inner: boxed(
+                self.inner
+                    .take_while(move |_| futures::future::ready(!cancellation.is_cancelled())),
+            ),
+            cancellations: self.cancellations,
+        }
+    }
+}
+
+impl Stream for AnnotationStream<'_> {
+    type Item = Result<Value, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.poll_next_unpin(cx)
+    }
+}
+
+impl std::fmt::Debug for AnnotationStream<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("AnnotationStream").finish_non_exhaustive()
+    }
+}
+
+impl<'a> AnnoIter<'a> {
+    /// Yield only the value at a JSON pointer of each annotation; see
+    /// [`AnnotationStream::select`].
+    pub fn select<P: Into<String>>(self, pointer: P) -> impl Iterator<Item = Value> + 'a {
+        let pointer = pointer.into();
+        self.filter_map(move |mut annotation| take_pointer(&mut annotation, &pointer))
+    }
+
+    /// Reduce each annotation to the given fields; see
+    /// [`AnnotationStream::project`].
+    pub fn project<I, F>(self, fields: I) -> impl Iterator<Item = Value> + 'a
+    where
+        I: IntoIterator<Item = F>,
+        F: AsRef<str>,
+    {
+        let paths = field_paths(fields);
+        self.map(move |annotation| project_annotation(annotation, &paths))
+    }
+}
+
+impl<'a> SearchInfo<'a> {
+    /// Stream all annotations found by this search.
+    pub fn annotations(&self) -> AnnotationStream<'a> {
+        AnnotationStream::search_results(self.client, &self.client.container, &self.search_id, 0)
+    }
+}
+
+fn take_pointer(value: &mut Value, pointer: &str) -> Option<Value> {
+    value.pointer_mut(pointer).map(Value::take)
+}
+
+fn field_paths<I, F>(fields: I) -> Vec<Vec<String>>
+where
+    I: IntoIterator<Item = F>,
+    F: AsRef<str>,
+{
+    fields
+        .into_iter()
+        .map(|field| field.as_ref().split('.').map(str::to_string).collect())
+        .collect()
+}
+
+fn project_annotation(annotation: Value, paths: &[Vec<String>]) -> Value {
+    let paths: Vec<&[String]> = paths.iter().map(Vec::as_slice).collect();
+    project(annotation, &paths).unwrap_or_else(|| Value::Object(Map::new()))
+}
+
+/// The parts of `value` on `paths`, or `None` if it has none of them.
+fn project(value: Value, paths: &[&[String]]) -> Option<Value> {
+    match value {
+        Value::Array(items) => {
+            let items: Vec<_> = items
+                .into_iter()
+                .filter_map(|item| project(item, paths))
+                .collect();
+            (!items.is_empty()).then_some(Value::Array(items))
+        }
+        Value::Object(fields) => {
+            let mut projected = Map::new();
+            for (key, value) in fields {
+                let rest: Vec<&[String]> = paths
+                    .iter()
+                    .filter(|path| path.first() == Some(&key))
+                    .map(|path| &path[1..])
+                    .collect();
+                if rest.iter().any(|path| path.is_empty()) {
+                    projected.insert(key, value);
+                } else if let Some(value) = project(value, &rest) {
+                    projected.insert(key, value);
+                }
+            }
+            (!projected.is_empty()).then_some(Value::Object(projected))
+        }
+        _ => None,
+    }
+}
+
+impl AnnoRepoClient {
+    /// Stream the annotations of a search, fetching the pages one by one from
+    /// `start_page` onwards.
+    pub fn stream_search_result_annotations(
+        &self,
+        container_name: &str,
+        search_id: &str,
+        start_page: Option<u32>,
+    ) -> AnnotationStream<'_> {
+        AnnotationStream::search_results(self, container_name, search_id, start_page.unwrap_or(0))
+    }
+
+    /// Stream the annotations in the container by paging through its
+    /// listing from `start_page` onwards, without creating a search.
+    pub fn stream_container_annotations(&self, start_page: Option<u32>) -> AnnotationStream<'_> {
+        AnnotationStream::container_listing(
+            self,
+            start_page.unwrap_or(0),
+            ContainerPreference::ContainedDescriptions,
+        )
+    }
+
+    /// Stream the IRIs of the annotations in the container, which is much
+    /// cheaper than [`stream_container_annotations`](Self::stream_container_annotations)
+    /// when only their names are needed.
+    pub fn stream_container_iris(&self, start_page: Option<u32>) -> AnnotationStream<'_> {
+        AnnotationStream::container_listing(
+            self,
+            start_page.unwrap_or(0),
+            ContainerPreference::ContainedIris,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn two_page_search() -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/search/s1"))
+            .and(query_param("page", "0"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [
+                    {"id": "a1", "body": {"value": "one"}},
+                    {"id": "a2"}
+                ],
+                "next": "/services/c/search/s1?page=1"
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/search/s1"))
+            .and(query_param("page", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [{"id": "a3", "body": {"value": "three"}}]
+            })))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    #[tokio::test]
+    async fn stream_follows_pages() {
+        let server = two_page_search().await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        let ids: Vec<Value> = client
+            .stream_search_result_annotations("c", "s1", None)
+            .select("/id")
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert_eq!(ids, [json!("a1"), json!("a2"), json!("a3")]);
+    }
+
+    #[tokio::test]
+    async fn cancelled_streams_fetch_no_more_pages() {
+        let server = two_page_search().await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+        let cancellation = CancellationToken::new();
+        let mut annotations = client
+            .stream_search_result_annotations("c", "s1", None)
+            .until_cancelled(cancellation.clone());
+
+        annotations.try_next().await.unwrap();
+        annotations.try_next().await.unwrap();
+        cancellation.cancel();
+
+        assert!(annotations.try_next().await.unwrap().is_none());
+        let requests = server.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn select_skips_annotations_without_the_value() {
+        let server = two_page_search().await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        let values: Vec<Value> = client
+            .stream_search_result_annotations("c", "s1", None)
+            .select("/body/value")
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert_eq!(values, [json!("one"), json!("three")]);
+    }
+
+    #[tokio::test]
+    async fn projection_keeps_only_the_fields() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/search/s1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [
+                    {
+                        "id": "a1",
+                        "body": {"value": "one"},
+                        "target": [
+                            {"source": "https://example.com/text/1", "selector": {}},
+                            "https://example.com/canvas/1"
+                        ]
+                    },
+                    {"id": "a2", "body": {"value": "two"}}
+                ]
+            })))
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        let annotations: Vec<Value> = client
+            .stream_search_result_annotations("c", "s1", None)
+            .project(["id", "target.source"])
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert_eq!(
+            annotations,
+            [
+                json!({"id": "a1", "target": [{"source": "https://example.com/text/1"}]}),
+                json!({"id": "a2"})
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn container_stream_follows_the_listing() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/w3c/c/"))
+            .and(query_param("page", "0"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [{"id": "a1"}, {"id": "a2"}],
+                "next": "/w3c/c/?page=1"
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/w3c/c/"))
+            .and(query_param("page", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [{"id": "a3"}]
+            })))
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        let ids: Vec<Value> = client
+            .stream_container_annotations(None)
+            .select("/id")
+            .try_collect()
+            .await
+            .unwrap();
+
+        assert_eq!(ids, [json!("a1"), json!("a2"), json!("a3")]);
+    }
+}
@@ -0,0 +1,488 @@
+use crate::encode::last_segment;
+use crate::timestamp::compare_timestamps;
+use crate::{
+    content_hash, response, AnnoRepoClient, CancellationToken, Error, Phase, Progress,
+    ProgressUpdate, Query,
+};
+use futures::TryStreamExt;
+use reqwest::header::{ETAG, IF_MATCH};
+use serde_json::Value;
+use std::sync::Arc;
+
+/// Which annotations [`AnnoRepoClient::sync_to`] copies.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyncMode {
+    /// Every annotation in the source container.
+    Full,
+    /// Only the annotations whose `field` is at least `since`, or all of
+    /// them when `since` is `None`.
+    ///
+    /// Pass the [`SyncReport::latest`] of the previous sync as `since` to
+    /// copy only what was added or changed after it.
+    Incremental { field: String, since: Option<Value> },
+}
+
+/// What [`AnnoRepoClient::sync_to`] does with an annotation the target
+/// already has a different version of.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Keep the target's version.
+    #[default]
+    Skip,
+    /// Replace the target's version with the source's, if it hasn't changed
+    /// since it was compared; fails with [`Error::MissingETag`] when the
+    /// target sends no ETag to check that with.
+    Overwrite,
+    /// Stop with [`Error::SyncConflict`].
+    Fail,
+}
+
+/// How [`AnnoRepoClient::sync_to`] copies annotations.
+#[derive(Debug, Clone)]
+pub struct SyncOptions {
+    pub mode: SyncMode,
+    pub on_conflict: ConflictPolicy,
+    /// Annotations copied at the same time.
+    pub concurrency: usize,
+    /// Told about every annotation synced.
+    pub progress: Option<Arc<dyn Progress>>,
+    /// Stops taking on more annotations once cancelled.
+    pub cancellation: Option<CancellationToken>,
+}
+
+impl Default for SyncOptions {
+    fn default() -> Self {
+        Self {
+            mode: SyncMode::Full,
+            on_conflict: ConflictPolicy::default(),
+            concurrency: 4,
+            progress: None,
+            cancellation: None,
+        }
+    }
+}
+
+/// What a sync did.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SyncReport {
+    /// Annotations the target didn't have yet.
+    pub created: usize,
+    /// Annotations the target had a different version of, replaced.
+    pub overwritten: usize,
+    /// Annotations the target had a different version of, left alone.
+    pub skipped: usize,
+    /// Annotations the target already had as they are.
+    pub unchanged: usize,
+    /// In incremental mode, the highest value of its field among the
+    /// annotations synced: the `since` of the next sync.
+    pub latest: Option<Value>,
+}
+
+impl SyncReport {
+    /// Annotations synced, whatever happened to them.
+    pub fn done(&self) -> usize {
+        self.created + self.overwritten + self.skipped + self.unchanged
+    }
+}
+
+enum Outcome {
+    Created,
+    Overwritten,
+    Skipped,
+    Unchanged,
+}
+
+impl AnnoRepoClient {
+    /// Copy the annotations in this client's container to the container of
+    /// `target`, which may be on another server, keeping their names.
+    ///
+    /// Stops at the first failure; annotations already copied stay stored.
+    /// When cancelled, the copies in progress are completed, and the report
+    /// covers what was done.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container, target = %target.container)))]
+    pub async fn sync_to(
+        &self,
+        target: &AnnoRepoClient,
+        options: &SyncOptions,
+    ) -> Result<SyncReport, Error> {
+        // Only a full sync knows up front how much there is to do.
+        let total = match (&options.mode, &options.progress) {
+            (SyncMode::Full, Some(_)) => {
+                Some(self.get_container_metadata().await?.annotation_count)
+            }
+            _ => None,
+        };
+        let (annotations, pointer) = match &options.mode {
+            SyncMode::Full => (self.all_annotations().await?, None),
+            SyncMode::Incremental { field, since } => {
+                let query = match since {
+                    Some(since) => Query::new().greater_than_or_equal(field, since.clone()),
+                    None => Query::new(),
+                };
+                let pointer = format!("/{}", field.replace('.', "/"));
+                (self.search(&query).await?.annotations(), Some(pointer))
+            }
+        };
+        let annotations = match &options.cancellation {
+            Some(cancellation) => annotations.until_cancelled(cancellation.clone()),
+            None => annotations,
+        };
+        let pointer = pointer.as_deref();
+        let on_conflict = options.on_conflict;
+        let progress = options.progress.as_deref();
+
+        annotations
+            .map_ok(|annotation| async move {
+                let value = pointer.and_then(|p| annotation.pointer(p)).cloned();
+                let outcome = target.copy_annotation(annotation, on_conflict).await?;
+                Ok((outcome, value))
+            })
+            .try_buffer_unordered(options.concurrency.max(1))
+            .try_fold(
+                SyncReport::default(),
+                |mut report, (outcome, value)| async move {
+                    match outcome {
+                        Outcome::Created => report.created += 1,
+                        Outcome::Overwritten => report.overwritten += 1,
+                        Outcome::Skipped => report.skipped += 1,
+                        Outcome::Unchanged => report.unchanged += 1,
+                    }
+                    if let Some(value) = value {
+                        if report.latest.as_ref().is_none_or(|l| is_later(&value, l)) {
+                            report.latest = Some(value);
+                        }
+                    }
+                    if let Some(progress) = progress {
+                        progress.update(ProgressUpdate {
+                            phase: Phase::Syncing,
+                            done: report.done() as u64,
+                            total,
+                        });
+                    }
+                    Ok(report)
+                },
+            )
+            .await
+    }
+
+    /// Store `annotation` in the container under the name in its `id`.
+    async fn copy_annotation(
+        &self,
+        mut annotation: Value,
+        on_conflict: ConflictPolicy,
+    ) -> Result<Outcome, Error> {
+        let name = annotation_name(&annotation)?;
+        // The target assigns its own id.
+        strip_id(&mut annotation);
+        let url = self.annotation_url(&name);
+
+        let existing = match self.send(self.client.get(&url)).await {
+            Ok(res) => res,
+            Err(Error::NotFound(_)) => {
+                let request = self
+                    .client
+                    .post(self.container_url())
+                    .header("Slug", &name)
+                    .json(&annotation);
+                let created = self.send_create(request, Some(&name)).await?;
+                // Under another name, the next sync wouldn't find it, and
+                // would create it again.
+                if !created.slug_honored {
+                    self.delete_created(&created).await?;
+                    return Err(Error::SlugNotHonored {
+                        requested: name,
+                        assigned: created.annotation_name,
+                    });
+                }
+                return Ok(Outcome::Created);
+            }
+            Err(e) => return Err(e),
+        };
+        let etag = existing
+            .headers()
+            .get(ETAG)
+            .and_then(|etag| etag.to_str().ok())
+            .map(str::to_string);
+        let (request, body) = existing.body().await?;
+        let existing: Value = response::decode(&request, &body)?;
+        // Without the fields the target sets itself.
+        if content_hash(&existing) == content_hash(&annotation) {
+            return Ok(Outcome::Unchanged);
+        }
+
+        match on_conflict {
+            ConflictPolicy::Skip => Ok(Outcome::Skipped),
+            ConflictPolicy::Fail => Err(Error::SyncConflict {
+                annotation_name: name,
+            }),
+            ConflictPolicy::Overwrite => {
+                let etag = etag.ok_or_else(|| Error::MissingETag(name))?;
+                let request = self
+                    .client
+                    .put(&url)
+                    .json(&annotation)
+                    .header(IF_MATCH, etag);
+                let res = self.send(request).await;
+                self.invalidate_container_metadata();
+                self.invalidate_annotation(&url);
+                res?;
+                Ok(Outcome::Overwritten)
+            }
+        }
+    }
+
+    pub(crate) fn invalidate_annotation(&self, url: &str) {
+        if let Some(cache) = &self.annotation_cache {
+            cache.remove(url);
+        }
+        if let Some(cache) = &self.etag_cache {
+            cache.remove(url);
+        }
+    }
+}
+
+/// The name of an annotation: the last segment of its `id`.
+pub(crate) fn annotation_name(annotation: &Value) -> Result<String, Error> {
+    match annotation.get("id").and_then(Value::as_str) {
+        Some(id) => Ok(last_segment(id)),
+        None => Err(Error::MissingAnnotationId),
+    }
+}
+
+pub(crate) fn strip_id(annotation: &mut Value) {
+    if let Value::Object(fields) = annotation {
+        fields.remove("id");
+    }
+}
+
+/// Whether `value` comes after `than`, for numbers and strings such as
+/// ISO 8601 timestamps, which are compared by the instant they stand for.
+fn is_later(value: &Value, than: &Value) -> bool {
+    match (value, than) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64() > b.as_f64(),
+        (Value::String(a), Value::String(b)) => compare_timestamps(a, b).is_gt(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{body_json, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[test]
+    fn later_timestamps_are_found_across_offsets() {
+        assert!(is_later(
+            &json!("2024-03-01T09:30:00Z"),
+            &json!("2024-03-01T11:00:00+02:00")
+        ));
+        assert!(!is_later(
+            &json!("2024-03-01T10:00:00+01:00"),
+            &json!("2024-03-01T09:00:00.000Z")
+        ));
+        assert!(is_later(&json!(2), &json!(1.5)));
+    }
+
+    async fn mount_source(server: &MockServer, query: Value, items: Value) {
+        Mock::given(method("POST"))
+            .and(path("/services/source/search"))
+            .and(body_json(query))
+            .respond_with(ResponseTemplate::new(201).insert_header(
+                "location",
+                format!("{}/services/source/search/s1", server.uri()),
+            ))
+            .mount(server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/services/source/search/s1"))
+            .and(query_param("page", "0"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"items": items})))
+            .mount(server)
+            .await;
+    }
+
+    fn clients(server: &MockServer) -> (AnnoRepoClient, AnnoRepoClient) {
+        (
+            AnnoRepoClient::new(server.uri(), "source".to_string()).unwrap(),
+            AnnoRepoClient::new(server.uri(), "mirror".to_string()).unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn full_sync_creates_missing_annotations_and_skips_conflicts() {
+        let server = MockServer::start().await;
+        mount_source(
+            &server,
+            json!({}),
+            json!([
+                {"id": "https://source.example.com/w3c/source/new", "target": "t1"},
+                {"id": "https://source.example.com/w3c/source/same", "target": "t2"},
+                {"id": "https://source.example.com/w3c/source/changed", "target": "t3"}
+            ]),
+        )
+        .await;
+        Mock::given(method("GET"))
+            .and(path("/w3c/mirror/new"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/w3c/mirror/"))
+            .and(header("slug", "new"))
+            .and(body_json(json!({"target": "t1"})))
+            .respond_with(
+                ResponseTemplate::new(201)
+                    .insert_header("location", format!("{}/w3c/mirror/new", server.uri())),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/w3c/mirror/same"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "https://mirror.example.com/w3c/mirror/same",
+                "via": "https://source.example.com/w3c/source/same",
+                "target": "t2"
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/w3c/mirror/changed"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"target": "old"})))
+            .mount(&server)
+            .await;
+        let (source, mirror) = clients(&server);
+
+        let report = source
+            .sync_to(&mirror, &SyncOptions::default())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            report,
+            SyncReport {
+                created: 1,
+                skipped: 1,
+                unchanged: 1,
+                ..SyncReport::default()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn copies_the_target_renames_are_deleted_again() {
+        let server = MockServer::start().await;
+        mount_source(
+            &server,
+            json!({}),
+            json!([{"id": "https://source.example.com/w3c/source/a1", "target": "t1"}]),
+        )
+        .await;
+        Mock::given(method("GET"))
+            .and(path("/w3c/mirror/a1"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/w3c/mirror/"))
+            .respond_with(
+                ResponseTemplate::new(201)
+                    .insert_header("location", format!("{}/w3c/mirror/x9", server.uri())),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("DELETE"))
+            .and(path("/w3c/mirror/x9"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let (source, mirror) = clients(&server);
+
+        let result = source.sync_to(&mirror, &SyncOptions::default()).await;
+
+        assert!(matches!(
+            result,
+            Err(Error::SlugNotHonored { assigned, .. }) if assigned == "x9"
+        ));
+    }
+
+    #[tokio::test]
+    async fn incremental_sync_overwrites_changes_since_the_last_one() {
+        let server = MockServer::start().await;
+        mount_source(
+            &server,
+            json!({"modified": {":isGreaterThanOrEqualTo": "2024-03-01"}}),
+            json!([
+                {"id": "https://source.example.com/w3c/source/a1", "modified": "2024-03-05"},
+                {"id": "https://source.example.com/w3c/source/a2", "modified": "2024-03-02"}
+            ]),
+        )
+        .await;
+        for name in ["a1", "a2"] {
+            Mock::given(method("GET"))
+                .and(path(format!("/w3c/mirror/{name}")))
+                .respond_with(
+                    ResponseTemplate::new(200)
+                        .insert_header("etag", format!("\"{name}\""))
+                        .set_body_json(json!({"modified": "2024-01-01"})),
+                )
+                .mount(&server)
+                .await;
+            Mock::given(method("PUT"))
+                .and(path(format!("/w3c/mirror/{name}")))
+                .and(header("if-match", format!("\"{name}\"").as_str()))
+                .respond_with(ResponseTemplate::new(200))
+                .expect(1)
+                .mount(&server)
+                .await;
+        }
+        let (source, mirror) = clients(&server);
+        let options = SyncOptions {
+            mode: SyncMode::Incremental {
+                field: "modified".to_string(),
+                since: Some(json!("2024-03-01")),
+            },
+            on_conflict: ConflictPolicy::Overwrite,
+            concurrency: 2,
+            ..SyncOptions::default()
+        };
+
+        let report = source.sync_to(&mirror, &options).await.unwrap();
+
+        assert_eq!(report.overwritten, 2);
+        assert_eq!(report.latest, Some(json!("2024-03-05")));
+    }
+
+    #[tokio::test]
+    async fn overwrites_need_an_etag() {
+        let server = MockServer::start().await;
+        mount_source(
+            &server,
+            json!({}),
+            json!([{"id": "https://source.example.com/w3c/source/a1", "target": "new"}]),
+        )
+        .await;
+        Mock::given(method("GET"))
+            .and(path("/w3c/mirror/a1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"target": "old"})))
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&server)
+            .await;
+        let (source, mirror) = clients(&server);
+        let options = SyncOptions {
+            on_conflict: ConflictPolicy::Overwrite,
+            ..SyncOptions::default()
+        };
+
+        let result = source.sync_to(&mirror, &options).await;
+
+        assert!(matches!(result, Err(Error::MissingETag(name)) if name == "a1"));
+    }
+}
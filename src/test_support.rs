@@ -0,0 +1,168 @@
+//! A fake AnnoRepo server for testing code that uses the client, without
+//! network access.
+//!
+//! ```no_run
+//! # async fn example() {
+//! use annorepo_client::test_support::FakeAnnoRepo;
+//! use serde_json::json;
+//!
+//! let fake = FakeAnnoRepo::start().await;
+//! fake.mount_search("letters", "s1", vec![vec![json!({"id": "a1"})]]).await;
+//! let client = fake.client("letters");
+//! # }
+//! ```
+
+use crate::AnnoRepoClient;
+use serde_json::{json, Value};
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// A local server answering like AnnoRepo with canned responses.
+///
+/// `/about` is answered from the start; everything else has to be mounted.
+/// Requests nothing was mounted for get a 404.
+#[derive(Debug)]
+pub struct FakeAnnoRepo {
+    server: MockServer,
+}
+
+impl FakeAnnoRepo {
+    /// Start a server without authentication.
+    pub async fn start() -> Self {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/about"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "appName": "AnnoRepo",
+                "version": "0.7.2",
+                "startedAt": "2024-03-01T09:00:00Z",
+                "withAuthentication": false
+            })))
+            .mount(&server)
+            .await;
+        Self { server }
+    }
+
+    pub fn uri(&self) -> String {
+        self.server.uri()
+    }
+
+    /// The underlying mock server, to mount other responses or inspect the
+    /// requests received.
+    pub fn server(&self) -> &MockServer {
+        &self.server
+    }
+
+    /// A client for `container` on this server.
+    pub fn client(&self, container: &str) -> AnnoRepoClient {
+        AnnoRepoClient::new(self.uri(), container.to_string())
+            .expect("the fake server has a valid URL")
+    }
+
+    /// Answer `/about` with `about`, instead of the default.
+    pub async fn mount_about(&self, about: Value) {
+        Mock::given(method("GET"))
+            .and(path("/about"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(about))
+            .with_priority(1)
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Answer the fields service of `container`.
+    pub async fn mount_fields(&self, container: &str, fields: Value) {
+        Mock::given(method("GET"))
+            .and(path(format!("/services/{container}/fields")))
+            .respond_with(ResponseTemplate::new(200).set_body_json(fields))
+            .mount(&self.server)
+            .await;
+    }
+
+    /// Answer any search in `container` with search `search_id`, whose
+    /// results are `pages`, linked by `next` like the real server does.
+    pub async fn mount_search(&self, container: &str, search_id: &str, pages: Vec<Vec<Value>>) {
+        let search_url = format!("{}/services/{container}/search/{search_id}", self.uri());
+        Mock::given(method("POST"))
+            .and(path(format!("/services/{container}/search")))
+            .respond_with(ResponseTemplate::new(201).insert_header("location", &search_url))
+            .mount(&self.server)
+            .await;
+
+        let page_count = pages.len();
+        for (number, items) in pages.into_iter().enumerate() {
+            let mut page = json!({
+                "id": format!("{search_url}?page={number}"),
+                "type": "AnnotationPage",
+                "partOf": search_url,
+                "startIndex": 0,
+                "items": items
+            });
+            if number + 1 < page_count {
+                page["next"] = json!(format!("{search_url}?page={}", number + 1));
+            }
+            Mock::given(method("GET"))
+                .and(path(format!("/services/{container}/search/{search_id}")))
+                .and(query_param("page", number.to_string()))
+                .respond_with(ResponseTemplate::new(200).set_body_json(page))
+                .mount(&self.server)
+                .await;
+        }
+    }
+
+    /// Answer `http_method` requests to `request_path` with an error status and
+    /// body, taking precedence over the responses mounted otherwise.
+    pub async fn mount_error(
+        &self,
+        http_method: &str,
+        request_path: &str,
+        status: u16,
+        body: &str,
+    ) {
+        Mock::given(method(http_method))
+            .and(path(request_path))
+            .respond_with(ResponseTemplate::new(status).set_body_string(body))
+            .with_priority(1)
+            .mount(&self.server)
+            .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+    use futures::TryStreamExt;
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn searches_are_paged_like_the_real_server() {
+        let fake = FakeAnnoRepo::start().await;
+        fake.mount_search(
+            "c",
+            "s1",
+            vec![
+                vec![json!({"id": "a1"}), json!({"id": "a2"})],
+                vec![json!({"id": "a3"})],
+            ],
+        )
+        .await;
+        let client = fake.client("c");
+
+        let search = client.create_search(HashMap::new()).await.unwrap();
+        let annotations: Vec<Value> = search.annotations().try_collect().await.unwrap();
+
+        assert_eq!(annotations.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn errors_can_be_mounted() {
+        let fake = FakeAnnoRepo::start().await;
+        fake.mount_fields("c", json!({"body.type": 1})).await;
+        fake.mount_error("GET", "/services/c/fields", 503, "maintenance")
+            .await;
+
+        let result = fake.client("c").get_fields().await;
+
+        assert!(matches!(result, Err(Error::Http(e)) if e.body == "maintenance"));
+    }
+}
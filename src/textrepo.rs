@@ -0,0 +1,152 @@
+use crate::builder::normalize_base_url;
+use crate::encode::segment;
+use crate::response::Response;
+use crate::{AnnotationExt, Error, RequestContext, APP_USER_AGENT};
+use serde::Deserialize;
+use std::sync::Arc;
+
+/// Fetches the text annotations point at from a TextRepo server, so it can be
+/// shown alongside them.
+///
+/// It has a client of its own, as the API key of an [`AnnoRepoClient`](crate::AnnoRepoClient)
+/// must not be sent to another server.
+#[derive(Debug, Clone)]
+pub struct TextRepoClient {
+    base_url: Arc<str>,
+    client: reqwest::Client,
+}
+
+#[derive(Deserialize)]
+struct Segments {
+    segments: Vec<String>,
+}
+
+impl TextRepoClient {
+    /// A client for the TextRepo API at `base_url`, like
+    /// `https://textrepo.example.com/api`.
+    pub fn new<S: Into<String>>(base_url: S) -> Result<Self, Error> {
+        let base_url = normalize_base_url(&base_url.into())?;
+        let client = reqwest::ClientBuilder::new()
+            .user_agent(APP_USER_AGENT)
+            .build()
+            .map_err(Error::ReqError)?;
+
+        Ok(Self {
+            base_url: base_url.into(),
+            client,
+        })
+    }
+
+    /// The text segments `start..=end` of a version of a segmented text.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(skip_all, fields(version_id, start, end))
+    )]
+    pub async fn get_segments(
+        &self,
+        version_id: &str,
+        start: u64,
+        end: u64,
+    ) -> Result<Vec<String>, Error> {
+        let url = format!(
+            "{base}/view/versions/{version_id}/segments/index/{start}/{end}",
+            base = self.base_url,
+            version_id = segment(version_id)
+        );
+        let request = self.client.get(url).build().map_err(Error::ReqError)?;
+        let context = RequestContext {
+            method: request.method().clone(),
+            url: request.url().clone(),
+            request_id: None,
+        };
+
+        let res = match self.client.execute(request).await {
+            Ok(res) => res,
+            Err(e) => return Err(Error::request(context, e)),
+        };
+        if !res.status().is_success() {
+            return Err(Error::from_response(context, res).await);
+        }
+        let segments: Segments = Response::new(context, res, None).json().await?;
+
+        Ok(segments.segments)
+    }
+
+    /// The text segments the `TextAnchorSelector` of `annotation` selects,
+    /// or `None` when it has none on a text version of this server.
+    pub async fn target_segments<A: AnnotationExt + ?Sized>(
+        &self,
+        annotation: &A,
+    ) -> Result<Option<Vec<String>>, Error> {
+        let Some(range) = annotation.text_anchor_range() else {
+            return Ok(None);
+        };
+        let Some(version_id) = range.source.and_then(|source| self.version_id(source)) else {
+            return Ok(None);
+        };
+
+        self.get_segments(version_id, range.start, range.end)
+            .await
+            .map(Some)
+    }
+
+    /// The version id in a URL of this server, like
+    /// `{base}/rest/versions/{id}/contents`.
+    fn version_id<'a>(&self, url: &'a str) -> Option<&'a str> {
+        let path = url.strip_prefix(&*self.base_url)?;
+        let mut parts = path.split('/');
+        parts.find(|part| *part == "versions")?;
+
+        parts.next().filter(|id| !id.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn anchor_range_selects_the_segments() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/view/versions/v1/segments/index/4/6"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({"segments": ["Hooge", "Mogende", "Heeren"]})),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        let textrepo = TextRepoClient::new(format!("{}/api/", server.uri())).unwrap();
+        let annotation = json!({
+            "type": "Annotation",
+            "target": {
+                "source": format!("{}/api/rest/versions/v1/contents", server.uri()),
+                "selector": {"type": "urn:republic:TextAnchorSelector", "start": 4, "end": 6}
+            }
+        });
+
+        let segments = textrepo.target_segments(&annotation).await.unwrap();
+
+        assert_eq!(segments.unwrap(), ["Hooge", "Mogende", "Heeren"]);
+    }
+
+    #[tokio::test]
+    async fn targets_on_other_servers_are_not_fetched() {
+        let textrepo = TextRepoClient::new("https://textrepo.example.com/api").unwrap();
+        let annotation = json!({
+            "type": "Annotation",
+            "target": {
+                "source": "https://elsewhere.example.com/api/rest/versions/v1/contents",
+                "selector": {"type": "urn:republic:TextAnchorSelector", "start": 4, "end": 6}
+            }
+        });
+
+        let segments = textrepo.target_segments(&annotation).await.unwrap();
+
+        assert_eq!(segments, None);
+    }
+}
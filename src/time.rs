@@ -0,0 +1,16 @@
+//! Timers that work both natively and in the browser, where there is no
+//! tokio runtime and `std::time::Instant` panics.
+
+pub(crate) use web_time::Instant;
+
+use std::time::Duration;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration).await
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) async fn sleep(duration: Duration) {
+    gloo_timers::future::sleep(duration).await
+}
@@ -0,0 +1,123 @@
+use std::cmp::Ordering;
+
+/// Order two ISO 8601 timestamps by the instant they stand for, so that
+/// `2024-03-01T10:00:00+01:00` equals `2024-03-01T09:00:00.000Z`. Values
+/// that aren't timestamps are compared as text.
+pub(crate) fn compare_timestamps(a: &str, b: &str) -> Ordering {
+    match (parse_timestamp(a), parse_timestamp(b)) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        _ => a.cmp(b),
+    }
+}
+
+/// Nanoseconds since the Unix epoch of a timestamp such as
+/// `2024-03-01T09:00:00.000Z`, a date-time with optional fraction and
+/// offset, UTC without one, or a plain date, taken as midnight UTC.
+fn parse_timestamp(timestamp: &str) -> Option<i128> {
+    let (date, time) = match timestamp.split_once(['T', 't', ' ']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (timestamp, None),
+    };
+    let mut date = date.splitn(3, '-');
+    let year: i64 = number(date.next()?, 4)?;
+    let month = number(date.next()?, 2)?;
+    let day = number(date.next()?, 2)?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    let days = days_from_civil(year, month, day);
+    let Some(time) = time else {
+        return Some(i128::from(days) * 86_400 * NANOS);
+    };
+
+    let (time, offset) = split_offset(time)?;
+    let (time, fraction) = match time.split_once(['.', ',']) {
+        Some((time, fraction)) => (time, fraction),
+        None => (time, ""),
+    };
+    let mut time = time.splitn(3, ':');
+    let hours: i64 = number(time.next()?, 2)?;
+    let minutes: i64 = number(time.next()?, 2)?;
+    let seconds: i64 = number(time.next().unwrap_or("00"), 2)?;
+    if hours > 23 || minutes > 59 || seconds > 60 {
+        return None;
+    }
+    let nanos = match fraction {
+        "" => 0,
+        fraction if fraction.len() <= 9 => {
+            number::<i64>(fraction, fraction.len())? * 10_i64.pow(9 - fraction.len() as u32)
+        }
+        _ => return None,
+    };
+    let seconds = days * 86_400 + hours * 3_600 + minutes * 60 + seconds - offset;
+
+    Some(i128::from(seconds) * NANOS + i128::from(nanos))
+}
+
+const NANOS: i128 = 1_000_000_000;
+
+/// The time before a `Z` or `±HH:MM` offset, and the offset in seconds.
+fn split_offset(time: &str) -> Option<(&str, i64)> {
+    if let Some(time) = time.strip_suffix(['Z', 'z']) {
+        return Some((time, 0));
+    }
+    let Some(at) = time.rfind(['+', '-']) else {
+        return Some((time, 0));
+    };
+    let (time, offset) = time.split_at(at);
+    let sign = if offset.starts_with('-') { -1 } else { 1 };
+    let offset = offset[1..].replace(':', "");
+    let hours: i64 = number(offset.get(..2)?, 2)?;
+    let minutes: i64 = match offset.get(2..)? {
+        "" => 0,
+        minutes => number(minutes, 2)?,
+    };
+
+    Some((time, sign * (hours * 3_600 + minutes * 60)))
+}
+
+/// `digits`, which must be exactly `len` ASCII digits.
+fn number<T: std::str::FromStr>(digits: &str, len: usize) -> Option<T> {
+    if digits.len() != len || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
+
+/// Days since 1970-01-01 of a date in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_compare_by_instant() {
+        assert_eq!(parse_timestamp("1970-01-01T00:00:00Z"), Some(0));
+        assert_eq!(
+            compare_timestamps("2024-03-01T10:00:00+01:00", "2024-03-01T09:00:00.000Z"),
+            Ordering::Equal
+        );
+        assert_eq!(
+            compare_timestamps("2024-03-01T09:00:00Z", "2024-03-01T09:00:00.5Z"),
+            Ordering::Less
+        );
+        assert_eq!(
+            compare_timestamps("2024-03-01T00:30:00+01:00", "2024-02-29T23:45:00Z"),
+            Ordering::Less
+        );
+        assert_eq!(
+            compare_timestamps("2024-03-02", "2024-03-01T23:59:59Z"),
+            Ordering::Greater
+        );
+        assert_eq!(compare_timestamps("b", "a"), Ordering::Greater);
+    }
+}
@@ -0,0 +1,274 @@
+use crate::sync::{annotation_name, strip_id};
+use crate::{AnnoRepoClient, Error, Query};
+use futures::TryStreamExt;
+use reqwest::header::{ETAG, IF_MATCH};
+use serde_json::Value;
+
+/// How [`AnnoRepoClient::update_by_query_with`] stores its changes.
+#[derive(Debug, Clone)]
+pub struct UpdateOptions {
+    /// Annotations stored at the same time.
+    pub concurrency: usize,
+    /// Apply the transform and report what would change, but store nothing.
+    pub dry_run: bool,
+}
+
+impl Default for UpdateOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: 4,
+            dry_run: false,
+        }
+    }
+}
+
+/// What [`AnnoRepoClient::update_by_query`] did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UpdateReport {
+    /// Annotations found by the query.
+    pub matched: usize,
+    /// Annotations changed, or that would be in a dry run.
+    pub updated: usize,
+    /// Annotations the transform left alone or returned unchanged.
+    pub unchanged: usize,
+}
+
+impl AnnoRepoClient {
+    /// Pass every annotation found by `query` to `transform`, and store the
+    /// changed versions it returns, a few at a time. `None` leaves an
+    /// annotation alone.
+    ///
+    /// Each annotation is replaced using its current ETag, failing with
+    /// [`Error::MissingETag`] if the server sends none. Stops at the first
+    /// failure; annotations already stored stay changed.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container)))]
+    pub async fn update_by_query<F>(
+        &self,
+        query: &Query,
+        transform: F,
+    ) -> Result<UpdateReport, Error>
+    where
+        F: Fn(&Value) -> Option<Value>,
+    {
+        self.update_by_query_with(query, transform, &UpdateOptions::default())
+            .await
+    }
+
+    /// [`AnnoRepoClient::update_by_query`], with control over concurrency and
+    /// a dry run.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container, dry_run = options.dry_run)))]
+    pub async fn update_by_query_with<F>(
+        &self,
+        query: &Query,
+        transform: F,
+        options: &UpdateOptions,
+    ) -> Result<UpdateReport, Error>
+    where
+        F: Fn(&Value) -> Option<Value>,
+    {
+        let dry_run = options.dry_run;
+        let search = self.search(query).await?;
+
+        search
+            .annotations()
+            .map_ok(|annotation| {
+                let changed = transform(&annotation).filter(|changed| *changed != annotation);
+                async move {
+                    match changed {
+                        Some(changed) => {
+                            let name = annotation_name(&annotation)?;
+                            if dry_run {
+                                #[cfg(feature = "tracing")]
+                                tracing::info!(annotation_name = name, "dry run: would update");
+                            } else {
+                                // Fetched here, so that the HEAD counts
+                                // against the concurrency as well.
+                                let url = self.annotation_url(&name);
+                                let etag = self
+                                    .send(self.client.head(&url))
+                                    .await?
+                                    .headers()
+                                    .get(ETAG)
+                                    .and_then(|etag| etag.to_str().ok())
+                                    .map(str::to_string)
+                                    .ok_or_else(|| Error::MissingETag(name))?;
+                                self.replace_annotation(&url, changed, &etag).await?;
+                            }
+                            Ok(true)
+                        }
+                        None => Ok(false),
+                    }
+                }
+            })
+            .try_buffer_unordered(options.concurrency.max(1))
+            .try_fold(UpdateReport::default(), |mut report, updated| async move {
+                report.matched += 1;
+                if updated {
+                    report.updated += 1;
+                } else {
+                    report.unchanged += 1;
+                }
+                Ok(report)
+            })
+            .await
+    }
+
+    /// Store a new version of the annotation at `url`, if its ETag is still
+    /// `etag`.
+    async fn replace_annotation(
+        &self,
+        url: &str,
+        mut annotation: Value,
+        etag: &str,
+    ) -> Result<(), Error> {
+        // The server keeps the annotation's id.
+        strip_id(&mut annotation);
+        let request = self
+            .client
+            .put(url)
+            .json(&annotation)
+            .header(IF_MATCH, etag);
+        let res = self.send(request).await;
+        self.invalidate_container_metadata();
+        self.invalidate_annotation(url);
+        res.map(drop)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{body_json, header, method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn changed_annotations_are_put_back_with_their_etag() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/services/c/search"))
+            .respond_with(
+                ResponseTemplate::new(201)
+                    .insert_header("location", format!("{}/services/c/search/s1", server.uri())),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/search/s1"))
+            .and(query_param("page", "0"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [
+                    {"id": "https://annorepo.example.com/w3c/c/a1", "body": {"value": "teh"}},
+                    {"id": "https://annorepo.example.com/w3c/c/a2", "body": {"value": "the"}}
+                ]
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("HEAD"))
+            .and(path("/w3c/c/a1"))
+            .respond_with(ResponseTemplate::new(200).insert_header("etag", "\"1\""))
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/w3c/c/a1"))
+            .and(header("if-match", "\"1\""))
+            .and(body_json(json!({"body": {"value": "the"}})))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        let report = client
+            .update_by_query(&Query::new(), |annotation| {
+                let mut fixed = annotation.clone();
+                fixed["body"]["value"] = json!("the");
+                Some(fixed)
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(
+            report,
+            UpdateReport {
+                matched: 2,
+                updated: 1,
+                unchanged: 1
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn annotations_without_an_etag_are_not_replaced() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/services/c/search"))
+            .respond_with(
+                ResponseTemplate::new(201)
+                    .insert_header("location", format!("{}/services/c/search/s1", server.uri())),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/search/s1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [{"id": "https://annorepo.example.com/w3c/c/a1", "body": "teh"}]
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("HEAD"))
+            .and(path("/w3c/c/a1"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+
+        let result = client
+            .update_by_query(&Query::new(), |_| Some(json!({"body": "the"})))
+            .await;
+
+        assert!(matches!(result, Err(Error::MissingETag(name)) if name == "a1"));
+    }
+
+    #[tokio::test]
+    async fn dry_runs_store_nothing() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/services/c/search"))
+            .respond_with(
+                ResponseTemplate::new(201)
+                    .insert_header("location", format!("{}/services/c/search/s1", server.uri())),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/search/s1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "items": [{"id": "https://annorepo.example.com/w3c/c/a1", "body": "teh"}]
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("PUT"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+        let options = UpdateOptions {
+            dry_run: true,
+            ..UpdateOptions::default()
+        };
+
+        let report = client
+            .update_by_query_with(&Query::new(), |_| Some(json!({"body": "the"})), &options)
+            .await
+            .unwrap();
+
+        assert_eq!(report.updated, 1);
+    }
+}
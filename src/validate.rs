@@ -0,0 +1,220 @@
+//! Checks of annotations against the W3C Web Annotation data model, to catch
+//! malformed annotations before the server rejects them.
+
+use crate::Error;
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+
+const ANNO_CONTEXT: &str = "http://www.w3.org/ns/anno.jsonld";
+
+/// A way an annotation breaks the W3C Web Annotation data model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// JSON pointer to the offending value; empty for the annotation itself.
+    pub pointer: String,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.pointer.as_str() {
+            "" => f.write_str(&self.message),
+            pointer => write!(f, "{pointer}: {}", self.message),
+        }
+    }
+}
+
+/// Check an annotation's required fields, its `type` and `@context`, and the
+/// structure of its bodies and targets, returning every violation found.
+pub fn validate_annotation(annotation: &Value) -> Vec<Violation> {
+    let mut violations = Violations::default();
+    let Some(fields) = annotation.as_object() else {
+        violations.add("", "an annotation must be a JSON object");
+        return violations.0;
+    };
+
+    match fields.get("@context") {
+        None => violations.add("/@context", "is required"),
+        Some(context) if !one_or_many(context).any(|c| c == ANNO_CONTEXT) => {
+            violations.add("/@context", format!("must include {ANNO_CONTEXT}"))
+        }
+        Some(_) => {}
+    }
+    match fields.get("type") {
+        None => violations.add("/type", "is required"),
+        Some(t) if !one_or_many(t).any(|t| t == "Annotation") => {
+            violations.add("/type", "must be or include \"Annotation\"")
+        }
+        Some(_) => {}
+    }
+    if let Some(id) = fields.get("id") {
+        if !id.is_string() {
+            violations.add("/id", "must be an IRI string");
+        }
+    }
+    if let Some(motivation) = fields.get("motivation") {
+        if !one_or_many(motivation).all(Value::is_string) {
+            violations.add("/motivation", "must be a string or an array of strings");
+        }
+    }
+    match fields.get("target") {
+        None => violations.add("/target", "is required"),
+        Some(target) => violations.resources(target, "/target", Resource::Target),
+    }
+    if let Some(body) = fields.get("body") {
+        violations.resources(body, "/body", Resource::Body);
+    }
+
+    violations.0
+}
+
+/// Fail with [`Error::InvalidAnnotations`] if any annotation is invalid.
+pub(crate) fn validate_all<T: Serialize>(annotations: &[T]) -> Result<(), Error> {
+    let mut invalid = Vec::new();
+    for (i, annotation) in annotations.iter().enumerate() {
+        let annotation = serde_json::to_value(annotation).map_err(|e| Error::Io(e.into()))?;
+        invalid.extend(validate_annotation(&annotation).into_iter().map(|v| (i, v)));
+    }
+
+    if invalid.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::InvalidAnnotations(invalid))
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Resource {
+    Body,
+    Target,
+}
+
+#[derive(Default)]
+struct Violations(Vec<Violation>);
+
+impl Violations {
+    fn add(&mut self, pointer: &str, message: impl Into<String>) {
+        self.0.push(Violation {
+            pointer: pointer.to_string(),
+            message: message.into(),
+        });
+    }
+
+    fn resources(&mut self, value: &Value, pointer: &str, kind: Resource) {
+        match value {
+            Value::Array(items) if items.is_empty() => self.add(pointer, "must not be empty"),
+            Value::Array(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    self.resource(item, &format!("{pointer}/{i}"), kind);
+                }
+            }
+            value => self.resource(value, pointer, kind),
+        }
+    }
+
+    fn resource(&mut self, value: &Value, pointer: &str, kind: Resource) {
+        let fields = match value {
+            Value::String(_) => return,
+            Value::Object(fields) => fields,
+            _ => return self.add(pointer, "must be an IRI string or an object"),
+        };
+        let is_type = |name: &str| {
+            fields
+                .get("type")
+                .is_some_and(|t| one_or_many(t).any(|t| t == name))
+        };
+
+        match kind {
+            Resource::Target if !fields.contains_key("id") && !fields.contains_key("source") => {
+                self.add(pointer, "must have an \"id\" or a \"source\"")
+            }
+            Resource::Body
+                if is_type("TextualBody") && !fields.get("value").is_some_and(Value::is_string) =>
+            {
+                self.add(
+                    &format!("{pointer}/value"),
+                    "a TextualBody must have a string value",
+                )
+            }
+            _ => {}
+        }
+        if let Some(selector) = fields.get("selector") {
+            let pointer = format!("{pointer}/selector");
+            match selector {
+                Value::Array(selectors) => {
+                    for (i, selector) in selectors.iter().enumerate() {
+                        self.selector(selector, &format!("{pointer}/{i}"));
+                    }
+                }
+                selector => self.selector(selector, &pointer),
+            }
+        }
+    }
+
+    fn selector(&mut self, selector: &Value, pointer: &str) {
+        match selector {
+            // A selector can be referenced by IRI.
+            Value::String(_) => {}
+            Value::Object(fields) if fields.get("type").is_some_and(Value::is_string) => {}
+            Value::Object(_) => self.add(&format!("{pointer}/type"), "a selector must have a type"),
+            _ => self.add(pointer, "must be an object"),
+        }
+    }
+}
+
+fn one_or_many(value: &Value) -> impl Iterator<Item = &Value> {
+    match value {
+        Value::Array(values) => values.iter(),
+        value => std::slice::from_ref(value).iter(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn valid_annotations_have_no_violations() {
+        let annotation = json!({
+            "@context": "http://www.w3.org/ns/anno.jsonld",
+            "type": "Annotation",
+            "motivation": "commenting",
+            "body": {"type": "TextualBody", "value": "A letter"},
+            "target": {
+                "source": "https://example.com/letter-1",
+                "selector": {"type": "TextPositionSelector", "start": 0, "end": 10}
+            }
+        });
+
+        assert_eq!(validate_annotation(&annotation), []);
+    }
+
+    #[test]
+    fn violations_point_at_the_offending_values() {
+        let annotation = json!({
+            "@context": ["http://www.w3.org/ns/ldp.jsonld"],
+            "type": "Note",
+            "body": [{"type": "TextualBody"}],
+            "target": [{"selector": {"start": 0}}, 42]
+        });
+
+        let pointers: Vec<String> = validate_annotation(&annotation)
+            .into_iter()
+            .map(|v| v.pointer)
+            .collect();
+
+        assert_eq!(
+            pointers,
+            [
+                "/@context",
+                "/type",
+                "/target/0",
+                "/target/0/selector/type",
+                "/target/1",
+                "/body/0/value"
+            ]
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use crate::{AnnoRepoClient, Error};
+use std::fmt;
+
+/// Oldest server version this client is known to work with.
+pub const MIN_SERVER_VERSION: ServerVersion = ServerVersion::new(0, 6, 0);
+/// First server version this client is no longer expected to work with.
+pub const MAX_SERVER_VERSION: ServerVersion = ServerVersion::new(1, 0, 0);
+
+/// First server version with the `:matchesRegex` query operator, used by
+/// [`Query::matches_regex`](crate::Query::matches_regex) and
+/// [`Query::contains`](crate::Query::contains).
+pub const REGEX_SERVER_VERSION: ServerVersion = ServerVersion::new(0, 8, 0);
+
+/// The `major.minor.patch` part of an AnnoRepo version; suffixes such as
+/// `-SNAPSHOT` are ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ServerVersion {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl ServerVersion {
+    pub const fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    pub fn parse(version: &str) -> Option<Self> {
+        let version = version.trim().trim_start_matches('v');
+        let numbers = version.split(['-', '+']).next()?;
+        let mut parts = numbers.split('.').map(str::parse::<u64>);
+        let major = parts.next()?.ok()?;
+        let minor = parts.next().unwrap_or(Ok(0)).ok()?;
+        let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(Self::new(major, minor, patch))
+    }
+
+    /// Whether this client supports a server of this version.
+    pub fn is_supported(&self) -> bool {
+        (MIN_SERVER_VERSION..MAX_SERVER_VERSION).contains(self)
+    }
+}
+
+impl fmt::Display for ServerVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+impl AnnoRepoClient {
+    /// Check the server version from `/about` against the versions this
+    /// client supports, failing with [`Error::IncompatibleServer`] outside
+    /// them.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all))]
+    pub async fn check_server_version(&self) -> Result<ServerVersion, Error> {
+        let version = self.capabilities().await?.server_version;
+        match ServerVersion::parse(&version) {
+            Some(parsed) if parsed.is_supported() => Ok(parsed),
+            _ => Err(Error::IncompatibleServer {
+                version,
+                supported: format!(">={MIN_SERVER_VERSION}, <{MAX_SERVER_VERSION}"),
+            }),
+        }
+    }
+
+    /// Fail with [`Error::UnsupportedByServer`] if the server is older than
+    /// `since`. A version that can't be parsed is given the benefit of the
+    /// doubt.
+    pub(crate) async fn require_server_version(
+        &self,
+        feature: &'static str,
+        since: ServerVersion,
+    ) -> Result<(), Error> {
+        let version = self.capabilities().await?.server_version;
+        match ServerVersion::parse(&version) {
+            Some(parsed) if parsed < since => Err(Error::UnsupportedByServer {
+                feature,
+                version,
+                since,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_are_parsed_and_compared() {
+        assert_eq!(
+            ServerVersion::parse("0.7.3-SNAPSHOT"),
+            Some(ServerVersion::new(0, 7, 3))
+        );
+        assert_eq!(
+            ServerVersion::parse("v1.2"),
+            Some(ServerVersion::new(1, 2, 0))
+        );
+        assert_eq!(ServerVersion::parse("unknown"), None);
+        assert_eq!(ServerVersion::parse("1.2.3.4"), None);
+
+        assert!(ServerVersion::new(0, 6, 0).is_supported());
+        assert!(ServerVersion::new(0, 12, 1).is_supported());
+        assert!(!ServerVersion::new(0, 5, 9).is_supported());
+        assert!(!ServerVersion::new(1, 0, 0).is_supported());
+    }
+}
@@ -0,0 +1,192 @@
+use crate::modified::{last_changed, modified_query};
+use crate::time::sleep;
+use crate::timestamp::compare_timestamps;
+use crate::{AnnoRepoClient, Error};
+use futures::{stream, Stream, TryStreamExt};
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::{HashSet, VecDeque};
+use std::time::Duration;
+
+/// How [`AnnoRepoClient::watch`] polls the container.
+#[derive(Debug, Clone)]
+pub struct WatchOptions {
+    /// Time between polls.
+    pub interval: Duration,
+    /// Longest time between polls while they fail; each failure doubles the
+    /// wait, starting from `interval`.
+    pub max_backoff: Duration,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(30),
+            max_backoff: Duration::from_secs(600),
+        }
+    }
+}
+
+impl AnnoRepoClient {
+    /// Poll the container for annotations created or modified at or after
+    /// `since`, an ISO 8601 timestamp, and yield every new version of an
+    /// annotation once, as it appears. The stream never ends; drop it to stop.
+    ///
+    /// A failed poll is yielded as an error, after which polling goes on
+    /// with [backoff](WatchOptions::max_backoff). The server searches on
+    /// timestamps as text, so `since` has to be in the same format as the
+    /// stored ones. Creates the indexes [`modified_since`](Self::modified_since)
+    /// needs first.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(container = %self.container, since)))]
+    pub fn watch(
+        &self,
+        since: &str,
+        options: WatchOptions,
+    ) -> impl Stream<Item = Result<Value, Error>> + '_ {
+        let watcher = Watcher {
+            client: self,
+            options,
+            cursor: since.to_string(),
+            seen: HashSet::new(),
+            pending: VecDeque::new(),
+            delay: None,
+            indexed: false,
+        };
+
+        stream::unfold(watcher, |mut watcher| async move {
+            loop {
+                if let Some(annotation) = watcher.pending.pop_front() {
+                    return Some((Ok(annotation), watcher));
+                }
+                if let Some(delay) = watcher.delay {
+                    sleep(delay).await;
+                }
+                let interval = watcher.options.interval;
+                match watcher.poll().await {
+                    Ok(()) => watcher.delay = Some(interval),
+                    Err(e) => {
+                        let delay = watcher.delay.unwrap_or(interval).max(interval) * 2;
+                        watcher.delay = Some(delay.min(watcher.options.max_backoff));
+                        return Some((Err(e), watcher));
+                    }
+                }
+            }
+        })
+    }
+}
+
+struct Watcher<'a> {
+    client: &'a AnnoRepoClient,
+    options: WatchOptions,
+    /// The latest change seen, which the next poll starts from.
+    cursor: String,
+    /// The ids of the annotations last changed at `cursor`, which the next
+    /// poll finds again.
+    seen: HashSet<String>,
+    pending: VecDeque<Value>,
+    delay: Option<Duration>,
+    indexed: bool,
+}
+
+impl Watcher<'_> {
+    async fn poll(&mut self) -> Result<(), Error> {
+        if !self.indexed {
+            self.client.ensure_timestamp_indexes().await?;
+            self.indexed = true;
+        }
+        let changed: Vec<Value> = self
+            .client
+            .search(&modified_query(&self.cursor))
+            .await?
+            .annotations()
+            .try_collect()
+            .await?;
+
+        let mut cursor = self.cursor.clone();
+        let mut seen = self.seen.clone();
+        for annotation in changed {
+            let id = annotation["id"].as_str().map(str::to_string);
+            let changed_at = last_changed(&annotation).unwrap_or(&self.cursor);
+            if compare_timestamps(changed_at, &self.cursor).is_eq()
+                && id.as_ref().is_some_and(|id| self.seen.contains(id))
+            {
+                continue;
+            }
+            match compare_timestamps(changed_at, &cursor) {
+                Ordering::Greater => {
+                    cursor = changed_at.to_string();
+                    seen = id.into_iter().collect();
+                }
+                Ordering::Equal => seen.extend(id),
+                Ordering::Less => {}
+            }
+            self.pending.push_back(annotation);
+        }
+        self.cursor = cursor;
+        self.seen = seen;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use serde_json::json;
+    use wiremock::matchers::{body_json, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn mount_poll(server: &MockServer, since: &str, search_id: &str, items: Value) {
+        Mock::given(method("POST"))
+            .and(path("/services/c/search"))
+            .and(body_json(json!({":or": [
+                {"created": {":isGreaterThanOrEqualTo": since}},
+                {"modified": {":isGreaterThanOrEqualTo": since}}
+            ]})))
+            .respond_with(ResponseTemplate::new(201).insert_header(
+                "location",
+                format!("{}/services/c/search/{search_id}", server.uri()),
+            ))
+            .mount(server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(format!("/services/c/search/{search_id}")))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "items": items })))
+            .mount(server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn each_change_is_yielded_once() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/services/c/indexes"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                {"field": "created", "type": "ascending"},
+                {"field": "modified", "type": "ascending"}
+            ])))
+            .mount(&server)
+            .await;
+        let a1 = json!({"id": "a1", "created": "2024-03-01T00:00:00Z"});
+        let a2 = json!({"id": "a2", "created": "2024-03-01T00:00:00Z", "modified": "2024-03-02T00:00:00Z"});
+        let a3 = json!({"id": "a3", "created": "2024-03-03T00:00:00Z"});
+        mount_poll(&server, "2024-03-01T00:00:00Z", "s1", json!([a1, a2])).await;
+        mount_poll(&server, "2024-03-02T00:00:00Z", "s2", json!([a2, a3])).await;
+        mount_poll(&server, "2024-03-03T00:00:00Z", "s3", json!([a3])).await;
+        let client = AnnoRepoClient::new(server.uri(), "c".to_string()).unwrap();
+        let options = WatchOptions {
+            interval: Duration::from_millis(10),
+            ..WatchOptions::default()
+        };
+
+        let changes: Vec<Value> = client
+            .watch("2024-03-01T00:00:00Z", options)
+            .take(3)
+            .map(Result::unwrap)
+            .collect()
+            .await;
+
+        assert_eq!(changes, [a1, a2, a3]);
+    }
+}
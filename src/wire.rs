@@ -0,0 +1,193 @@
+use bytes::Bytes;
+use futures::{Stream, StreamExt};
+use reqwest::header::{HeaderMap, HeaderName};
+use reqwest::ResponseBuilderExt;
+use std::fmt::Write;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+const TARGET: &str = "annorepo_client::wire";
+
+/// Headers whose values are never logged, besides those marked sensitive.
+const SECRET_HEADERS: [&str; 5] = [
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+    "x-api-key",
+];
+
+/// Log the request line, headers and up to `max_body` bytes of the body.
+pub(crate) fn log_request(request: &reqwest::Request, max_body: usize) {
+    let body = match request.body() {
+        None => String::new(),
+        Some(body) => match body.as_bytes() {
+            Some(bytes) => truncated(bytes, bytes.len(), max_body),
+            None => "<streamed body>".to_string(),
+        },
+    };
+    tracing::debug!(
+        target: TARGET,
+        headers = %headers(request.headers()),
+        body = %body,
+        "> {} {}",
+        request.method(),
+        request.url()
+    );
+}
+
+/// Log the status and headers of `res`, and its body once up to `max_body`
+/// bytes of it have been read, or all of it, or when it is dropped.
+pub(crate) fn log_response(res: reqwest::Response, max_body: usize) -> reqwest::Response {
+    let mut builder = http::Response::builder()
+        .status(res.status())
+        .version(res.version())
+        .url(res.url().clone());
+    if let Some(headers) = builder.headers_mut() {
+        headers.extend(res.headers().clone());
+    }
+    tracing::debug!(
+        target: TARGET,
+        headers = %headers(res.headers()),
+        "< {} {}",
+        res.status(),
+        res.url()
+    );
+    let body = LoggedBody {
+        inner: Box::pin(res.bytes_stream()),
+        logged: Vec::new(),
+        seen: 0,
+        max_body,
+        done: false,
+    };
+
+    builder
+        .body(reqwest::Body::wrap_stream(body))
+        .expect("the parts of a valid response")
+        .into()
+}
+
+fn headers(headers: &HeaderMap) -> String {
+    let mut out = String::new();
+    for (name, value) in headers {
+        let value = if is_secret(name) || value.is_sensitive() {
+            "<redacted>"
+        } else {
+            value.to_str().unwrap_or("<binary>")
+        };
+        let _ = write!(
+            out,
+            "{}{name}: {value}",
+            if out.is_empty() { "" } else { ", " }
+        );
+    }
+    out
+}
+
+fn is_secret(name: &HeaderName) -> bool {
+    SECRET_HEADERS.contains(&name.as_str())
+}
+
+fn truncated(start: &[u8], len: usize, max_body: usize) -> String {
+    let shown = &start[..start.len().min(max_body)];
+    let mut text = String::from_utf8_lossy(shown).into_owned();
+    if len > shown.len() {
+        let _ = write!(text, "... ({} more bytes)", len - shown.len());
+    }
+    text
+}
+
+struct LoggedBody {
+    inner: Pin<Box<dyn Stream<Item = reqwest::Result<Bytes>> + Send>>,
+    logged: Vec<u8>,
+    seen: usize,
+    max_body: usize,
+    done: bool,
+}
+
+impl LoggedBody {
+    fn log(&mut self, complete: bool) {
+        if self.done {
+            return;
+        }
+        self.done = true;
+        let body = truncated(&self.logged, self.seen, self.max_body);
+        let body = if complete {
+            body
+        } else {
+            format!("{body}... (not read further)")
+        };
+        tracing::debug!(target: TARGET, body = %body, "< body");
+    }
+}
+
+impl Stream for LoggedBody {
+    type Item = reqwest::Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let item = futures::ready!(self.inner.poll_next_unpin(cx));
+        match &item {
+            Some(Ok(chunk)) => {
+                let room = self.max_body.saturating_sub(self.logged.len());
+                self.logged
+                    .extend_from_slice(&chunk[..chunk.len().min(room)]);
+                self.seen += chunk.len();
+            }
+            Some(Err(_)) => self.log(false),
+            None => self.log(true),
+        }
+        Poll::Ready(item)
+    }
+}
+
+impl Drop for LoggedBody {
+    fn drop(&mut self) {
+        self.log(false);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn secrets_are_redacted() {
+        let mut map = HeaderMap::new();
+        map.insert("authorization", HeaderValue::from_static("Bearer secret"));
+        map.insert("content-type", HeaderValue::from_static("application/json"));
+
+        assert_eq!(
+            headers(&map),
+            "authorization: <redacted>, content-type: application/json"
+        );
+    }
+
+    #[tokio::test]
+    async fn logged_responses_are_read_in_full() {
+        use crate::AnnoRepoClient;
+        use serde_json::json;
+        use wiremock::matchers::method;
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({"body.type": 3})))
+            .mount(&server)
+            .await;
+        let client = AnnoRepoClient::builder(server.uri(), "c".to_string())
+            .wire_log(4)
+            .build()
+            .unwrap();
+
+        let fields = client.get_fields().await.unwrap();
+
+        assert_eq!(fields["body.type"], 3);
+    }
+
+    #[test]
+    fn bodies_are_truncated() {
+        assert_eq!(truncated(b"abcdef", 6, 4), "abcd... (2 more bytes)");
+        assert_eq!(truncated(b"abc", 3, 4), "abc");
+    }
+}
@@ -0,0 +1,112 @@
+//! End-to-end tests against a real AnnoRepo and MongoDB, started with
+//! testcontainers. They need Docker, and only run with
+//! `cargo test --features integration-tests`.
+//!
+//! The server image can be overridden with `ANNOREPO_IMAGE` (`name:tag`).
+#![cfg(all(feature = "integration-tests", not(target_arch = "wasm32")))]
+
+use annorepo_client::{AnnoRepoClient, Query};
+use futures::TryStreamExt;
+use serde_json::{json, Value};
+use std::time::Duration;
+use testcontainers::core::{IntoContainerPort, WaitFor};
+use testcontainers::runners::AsyncRunner;
+use testcontainers::{ContainerAsync, GenericImage, ImageExt};
+
+const DEFAULT_IMAGE: &str = "ghcr.io/knaw-huc/annorepo-server:v0.7.2";
+const PORT: u16 = 8080;
+
+struct AnnoRepo {
+    _mongo: ContainerAsync<GenericImage>,
+    server: ContainerAsync<GenericImage>,
+}
+
+impl AnnoRepo {
+    async fn start() -> Self {
+        let network = format!("annorepo-it-{}", std::process::id());
+        let mongo_name = format!("{network}-mongo");
+        let mongo = GenericImage::new("mongo", "7")
+            .with_wait_for(WaitFor::message_on_stdout("Waiting for connections"))
+            .with_network(&network)
+            .with_container_name(&mongo_name)
+            .start()
+            .await
+            .expect("MongoDB should start");
+
+        let image = std::env::var("ANNOREPO_IMAGE").unwrap_or_else(|_| DEFAULT_IMAGE.to_string());
+        let (name, tag) = image.rsplit_once(':').unwrap_or((&image, "latest"));
+        let server = GenericImage::new(name, tag)
+            .with_exposed_port(PORT.tcp())
+            .with_wait_for(WaitFor::message_on_stdout("Started"))
+            .with_network(&network)
+            .with_env_var("AR_DB_URL", format!("mongodb://{mongo_name}:27017"))
+            .with_env_var("AR_EXTERNAL_BASE_URL", format!("http://localhost:{PORT}"))
+            .with_env_var("AR_WITH_AUTHENTICATION", "false")
+            .with_startup_timeout(Duration::from_secs(120))
+            .start()
+            .await
+            .expect("AnnoRepo should start");
+
+        Self {
+            _mongo: mongo,
+            server,
+        }
+    }
+
+    async fn client(&self, container: &str) -> AnnoRepoClient {
+        let host = self.server.get_host().await.unwrap();
+        let port = self.server.get_host_port_ipv4(PORT).await.unwrap();
+        AnnoRepoClient::new(format!("http://{host}:{port}"), container.to_string()).unwrap()
+    }
+}
+
+fn annotation(n: usize) -> Value {
+    json!({
+        "@context": "http://www.w3.org/ns/anno.jsonld",
+        "type": "Annotation",
+        "body": {"type": "TextualBody", "purpose": "tagging", "value": format!("tag-{}", n % 3)},
+        "target": format!("https://example.com/text/{n}")
+    })
+}
+
+#[tokio::test]
+async fn upload_and_search_end_to_end() {
+    let annorepo = AnnoRepo::start().await;
+    let client = annorepo.client("integration").await;
+
+    client
+        .create_container(Some("Integration tests"))
+        .await
+        .unwrap();
+
+    // Enough annotations to span several result pages.
+    let annotations: Vec<Value> = (0..450).map(annotation).collect();
+    for batch in annotations.chunks(100) {
+        let identifiers = client.add_annotations(batch).await.unwrap();
+        assert_eq!(identifiers.len(), batch.len());
+    }
+    assert_eq!(
+        client
+            .get_container_metadata()
+            .await
+            .unwrap()
+            .annotation_count,
+        450
+    );
+
+    let search = client
+        .search(&Query::new().field("body.value", "tag-1"))
+        .await
+        .unwrap();
+    let found: Vec<Value> = search.annotations().try_collect().await.unwrap();
+    assert_eq!(found.len(), 150);
+
+    let all: Vec<Value> = client
+        .all_annotations()
+        .await
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(all.len(), 450);
+}